@@ -0,0 +1,26 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+/*! Prints a report of the available adapters, to be attached to bug reports.
+!*/
+
+use wgc::gfx_select;
+
+fn main() {
+    let global = wgc::hub::Global::new(
+        "wgpu-info",
+        wgc::hub::IdentityManagerFactory,
+        wgt::BackendBit::all(),
+    );
+    let adapters = global.enumerate_adapters(wgc::instance::AdapterInputs::Mask(
+        wgt::BackendBit::all(),
+        |_| (),
+    ));
+    let reports = adapters
+        .iter()
+        .map(|&adapter| gfx_select!(adapter => global.adapter_get_report(adapter)).unwrap())
+        .collect::<Vec<_>>();
+    let config = ron::ser::PrettyConfig::new();
+    println!("{}", ron::ser::to_string_pretty(&reports, config).unwrap());
+}
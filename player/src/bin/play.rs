@@ -8,11 +8,125 @@
 use player::{GlobalPlay as _, IdentityPassThroughFactory};
 use wgc::{device::trace, gfx_select};
 
+#[cfg(feature = "winit")]
+use std::ops::Range;
 use std::{
     fs,
     path::{Path, PathBuf},
 };
 
+/// Range of frames to replay interactively, from `WGPU_PLAYER_FRAMES` ("start..end").
+///
+/// Frames are numbered from 1, and like in a Rust range the end is excluded.
+/// The frames before the start are replayed without waiting for redraws,
+/// and the player pauses once frame `end - 1` is presented.
+#[cfg(feature = "winit")]
+fn frame_range() -> Range<u32> {
+    let range = match std::env::var("WGPU_PLAYER_FRAMES") {
+        Ok(range) => range,
+        Err(_) => return 0..u32::MAX,
+    };
+    let mut bounds = range.splitn(2, "..");
+    let mut parse = |default| match bounds.next() {
+        Some("") | None => default,
+        Some(bound) => bound
+            .parse()
+            .unwrap_or_else(|e| panic!("Invalid frame range '{}': {:?}", range, e)),
+    };
+    let start = parse(0);
+    let end = parse(u32::MAX);
+    start..end
+}
+
+/// Copy an action that can be executed again to replay a frame in a loop.
+#[cfg(feature = "winit")]
+fn repeatable_copy(action: &trace::Action) -> Option<trace::Action<'static>> {
+    Some(match *action {
+        trace::Action::WriteBuffer {
+            id,
+            ref data,
+            ref range,
+            queued,
+        } => trace::Action::WriteBuffer {
+            id,
+            data: data.clone(),
+            range: range.clone(),
+            queued,
+        },
+        trace::Action::WriteTexture {
+            ref to,
+            ref data,
+            ref layout,
+            size,
+        } => trace::Action::WriteTexture {
+            to: to.clone(),
+            data: data.clone(),
+            layout: layout.clone(),
+            size,
+        },
+        trace::Action::GetSwapChainTexture { id, parent_id } => {
+            trace::Action::GetSwapChainTexture { id, parent_id }
+        }
+        trace::Action::Submit(index, ref commands) => {
+            trace::Action::Submit(index, commands.clone())
+        }
+        trace::Action::PresentSwapChain(id) => trace::Action::PresentSwapChain(id),
+        _ => return None,
+    })
+}
+
+/// Name of the file listing the hashes of the dumped frames, one per line.
+#[cfg(not(feature = "winit"))]
+const HASH_FILE_NAME: &str = "frame-hashes.txt";
+
+#[cfg(not(feature = "winit"))]
+fn format_hashes(hashes: &[u64]) -> String {
+    hashes
+        .iter()
+        .map(|hash| format!("{:016x}\n", hash))
+        .collect()
+}
+
+/// Compare the frame hashes with a reference hash file, and report the divergences.
+#[cfg(not(feature = "winit"))]
+fn verify_hashes(hashes: &[u64], reference: &str) -> bool {
+    let expected = reference.lines().collect::<Vec<_>>();
+    let actual = format_hashes(hashes);
+    let mut matches = true;
+    for (index, (expected, actual)) in expected.iter().zip(actual.lines()).enumerate() {
+        if expected != &actual {
+            log::error!(
+                "Frame {} diverges: expected hash {}, got {}",
+                index + 1,
+                expected,
+                actual
+            );
+            matches = false;
+        }
+    }
+    if expected.len() != hashes.len() {
+        log::error!("Expected {} frames, got {}", expected.len(), hashes.len());
+        matches = false;
+    }
+    if matches {
+        log::info!("All {} frames match the reference", hashes.len());
+    }
+    matches
+}
+
+fn load_actions(dir: &Path) -> Vec<trace::Action<'static>> {
+    #[cfg(feature = "binary-trace")]
+    {
+        let path = dir.join(trace::BINARY_FILE_NAME);
+        if path.exists() {
+            let file = std::io::BufReader::new(fs::File::open(path).unwrap());
+            return trace::read_binary(file).unwrap();
+        }
+    }
+    let file = fs::File::open(dir.join(trace::FILE_NAME)).unwrap();
+    ron::de::from_reader(file).unwrap()
+}
+
 fn main() {
     #[cfg(feature = "winit")]
     use winit::{event_loop::EventLoop, window::WindowBuilder};
@@ -36,10 +150,11 @@ fn main() {
         Some(arg) if Path::new(&arg).is_dir() => PathBuf::from(arg),
         _ => panic!("Provide the dir path as the parameter"),
     };
+    #[cfg_attr(feature = "winit", allow(unused))]
+    let dump_dir = std::env::args().nth(2).map(PathBuf::from);
 
     log::info!("Loading trace '{:?}'", dir);
-    let file = fs::File::open(dir.join(trace::FILE_NAME)).unwrap();
-    let mut actions: Vec<trace::Action> = ron::de::from_reader(file).unwrap();
+    let mut actions = load_actions(&dir);
     actions.reverse(); // allows us to pop from the top
     log::info!("Found {} actions", actions.len());
 
@@ -108,13 +223,37 @@ fn main() {
         #[cfg(feature = "renderdoc")]
         rd.start_frame_capture(std::ptr::null(), std::ptr::null());
 
-        while let Some(action) = actions.pop() {
+        let mut dumper = dump_dir.as_ref().map(|path| {
+            log::info!("Dumping frames into {:?}", path);
+            player::FrameDumper::new(&path, &actions, device.backend())
+        });
+        while let Some(mut action) = actions.pop() {
+            if let Some(ref mut dumper) = dumper {
+                action = match gfx_select!(device => dumper.process(&global, device, action, &mut command_buffer_id_manager))
+                {
+                    Some(action) => action,
+                    None => continue,
+                };
+            }
             gfx_select!(device => global.process(device, action, &dir, &mut command_buffer_id_manager));
         }
+        if let Some(dumper) = dumper {
+            log::info!("Dumped {} frames", dumper.frame_count());
+            let hashes = dumper.frame_hashes();
+            let hash_path = dump_dir.as_ref().unwrap().join(HASH_FILE_NAME);
+            fs::write(&hash_path, format_hashes(hashes)).unwrap();
+            if let Ok(reference) = std::env::var("WGPU_PLAYER_VERIFY") {
+                let reference = fs::read_to_string(&reference)
+                    .unwrap_or_else(|e| panic!("Unable to read '{}': {:?}", reference, e));
+                if !verify_hashes(hashes, &reference) {
+                    std::process::exit(1);
+                }
+            }
+        }
 
         #[cfg(feature = "renderdoc")]
         rd.end_frame_capture(std::ptr::null(), std::ptr::null());
-        gfx_select!(device => global.device_poll(device, true)).unwrap();
+        gfx_select!(device => global.device_poll(device, wgt::Maintain::Wait)).unwrap();
     }
     #[cfg(feature = "winit")]
     {
@@ -123,36 +262,70 @@ fn main() {
             event_loop::ControlFlow,
         };
 
+        let frames = frame_range();
         let mut frame_count = 0;
+        let mut paused = false;
+        let mut step = false;
+        let mut looping = false;
+        // Repeatable actions of the last frame, kept to be replayed in a loop.
+        let mut frame_actions = Vec::<trace::Action>::new();
+        log::info!("Controls: Space to pause, Right to step a frame, L to loop the last frame");
         event_loop.run(move |event, _, control_flow| {
             *control_flow = ControlFlow::Poll;
             match event {
                 Event::MainEventsCleared => {
                     window.request_redraw();
                 }
-                Event::RedrawRequested(_) => loop {
-                    match actions.pop() {
-                        Some(trace::Action::CreateSwapChain(id, desc)) => {
-                            log::info!("Initializing the swapchain");
-                            assert_eq!(id.to_surface_id(), surface);
-                            window.set_inner_size(winit::dpi::PhysicalSize::new(
-                                desc.width,
-                                desc.height,
-                            ));
-                            gfx_select!(device => global.device_create_swap_chain(device, surface, &desc)).unwrap();
-                        }
-                        Some(trace::Action::PresentSwapChain(id)) => {
-                            frame_count += 1;
-                            log::debug!("Presenting frame {}", frame_count);
-                            gfx_select!(device => global.swap_chain_present(id)).unwrap();
-                            break;
+                Event::RedrawRequested(_) if looping => {
+                    for action in frame_actions.iter() {
+                        match repeatable_copy(action).unwrap() {
+                            trace::Action::PresentSwapChain(id) => {
+                                gfx_select!(device => global.swap_chain_present(id)).unwrap();
+                            }
+                            action => {
+                                gfx_select!(device => global.process(device, action, &dir, &mut command_buffer_id_manager));
+                            }
                         }
-                        Some(action) => {
-                            gfx_select!(device => global.process(device, action, &dir, &mut command_buffer_id_manager));
+                    }
+                }
+                Event::RedrawRequested(_) if paused && !step => {}
+                Event::RedrawRequested(_) => {
+                    step = false;
+                    frame_actions.clear();
+                    loop {
+                        match actions.pop() {
+                            Some(trace::Action::CreateSwapChain(id, desc)) => {
+                                log::info!("Initializing the swapchain");
+                                assert_eq!(id.to_surface_id(), surface);
+                                window.set_inner_size(winit::dpi::PhysicalSize::new(
+                                    desc.width,
+                                    desc.height,
+                                ));
+                                gfx_select!(device => global.device_create_swap_chain(device, surface, &desc)).unwrap();
+                            }
+                            Some(trace::Action::PresentSwapChain(id)) => {
+                                frame_count += 1;
+                                log::debug!("Presenting frame {}", frame_count);
+                                gfx_select!(device => global.swap_chain_present(id)).unwrap();
+                                frame_actions.push(trace::Action::PresentSwapChain(id));
+                                if frame_count + 1 == frames.end {
+                                    log::info!("Reached the end of the frame range, pausing");
+                                    paused = true;
+                                }
+                                if frame_count < frames.start {
+                                    frame_actions.clear();
+                                    continue;
+                                }
+                                break;
+                            }
+                            Some(action) => {
+                                frame_actions.extend(repeatable_copy(&action));
+                                gfx_select!(device => global.process(device, action, &dir, &mut command_buffer_id_manager));
+                            }
+                            None => break,
                         }
-                        None => break,
                     }
-                },
+                }
                 Event::WindowEvent { event, .. } => match event {
                     WindowEvent::KeyboardInput {
                         input:
@@ -166,11 +339,34 @@ fn main() {
                     | WindowEvent::CloseRequested => {
                         *control_flow = ControlFlow::Exit;
                     }
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                virtual_keycode: Some(key),
+                                state: ElementState::Pressed,
+                                ..
+                            },
+                        ..
+                    } => match key {
+                        VirtualKeyCode::Space => {
+                            paused = !paused;
+                            let state = if paused { "Paused" } else { "Resumed" };
+                            log::info!("{} at frame {}", state, frame_count);
+                        }
+                        VirtualKeyCode::Right => {
+                            step = true;
+                        }
+                        VirtualKeyCode::L => {
+                            looping = !looping;
+                            log::info!("Looping of frame {}: {}", frame_count, looping);
+                        }
+                        _ => {}
+                    },
                     _ => {}
                 },
                 Event::LoopDestroyed => {
                     log::info!("Closing");
-                    gfx_select!(device => global.device_poll(device, true)).unwrap();
+                    gfx_select!(device => global.device_poll(device, wgt::Maintain::Wait)).unwrap();
                 }
                 _ => {}
             }
@@ -0,0 +1,330 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::IdentityPassThroughFactory;
+use wgc::{device::trace, id::TypedId as _};
+
+use std::{
+    fs,
+    hash::Hasher as _,
+    io::Write as _,
+    path::{Path, PathBuf},
+    ptr, slice,
+};
+
+type Global = wgc::hub::Global<IdentityPassThroughFactory>;
+
+extern "C" fn map_callback(status: wgc::resource::BufferMapAsyncStatus, _user_data: *mut u8) {
+    match status {
+        wgc::resource::BufferMapAsyncStatus::Success => (),
+        _ => panic!("Unable to map the frame buffer"),
+    }
+}
+
+/// Offscreen replacement of a swap chain.
+///
+/// It allows replaying the traces that present to a swap chain without a window:
+/// the frames are rendered into a regular texture, and every presented frame is
+/// read back and written into a numbered image file.
+///
+/// The frames are written as binary PPM for 8-bit color formats, and as raw texel
+/// dumps for everything else. The texel values are written as stored, which is what
+/// the presentation engine would show: `*Srgb` formats are already encoded by the
+/// hardware, and linear formats are interpreted as sRGB by the display anyway.
+#[derive(Debug)]
+pub struct FrameDumper {
+    dir: PathBuf,
+    texture_id: wgc::id::TextureId,
+    buffer_id: wgc::id::BufferId,
+    desc: Option<wgt::SwapChainDescriptor>,
+    view_id: Option<wgc::id::TextureViewId>,
+    frame_count: u32,
+    hashes: Vec<u64>,
+}
+
+impl FrameDumper {
+    /// Create a frame dumper writing into `dir`.
+    ///
+    /// The `actions` are only scanned to pick the texture and buffer IDs
+    /// that are never used by the trace itself.
+    pub fn new(dir: &Path, actions: &[trace::Action], backend: wgt::Backend) -> Self {
+        let mut texture_index = 0;
+        let mut buffer_index = 0;
+        for action in actions {
+            match *action {
+                trace::Action::CreateTexture(id, _) => {
+                    texture_index = texture_index.max(id.unzip().0 + 1);
+                }
+                trace::Action::CreateBuffer(id, _) => {
+                    buffer_index = buffer_index.max(id.unzip().0 + 1);
+                }
+                _ => {}
+            }
+        }
+        fs::create_dir_all(dir).unwrap();
+        FrameDumper {
+            dir: dir.to_path_buf(),
+            texture_id: wgc::id::TypedId::zip(texture_index, 0, backend),
+            buffer_id: wgc::id::TypedId::zip(buffer_index, 0, backend),
+            desc: None,
+            view_id: None,
+            frame_count: 0,
+            hashes: Vec::new(),
+        }
+    }
+
+    /// Number of frames written so far.
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    /// Hashes of the texels of the frames written so far.
+    ///
+    /// They don't depend on the platform, so they can be compared between runs
+    /// on different machines and backends.
+    pub fn frame_hashes(&self) -> &[u64] {
+        &self.hashes
+    }
+
+    fn padded_bytes_per_row(desc: &wgt::SwapChainDescriptor) -> u32 {
+        let unpadded = desc.width * desc.format.describe().block_size as u32;
+        let align = wgt::COPY_BYTES_PER_ROW_ALIGNMENT;
+        (unpadded + align - 1) / align * align
+    }
+
+    fn release<B: wgc::hub::GfxBackend>(&mut self, global: &Global) {
+        if let Some(view_id) = self.view_id.take() {
+            global.texture_view_drop::<B>(view_id, true).unwrap();
+        }
+        if self.desc.take().is_some() {
+            global.texture_drop::<B>(self.texture_id, true);
+            global.buffer_drop::<B>(self.buffer_id, true);
+            // The IDs are passed through, so bump the epochs to create new objects.
+            let (index, epoch, backend) = self.texture_id.unzip();
+            self.texture_id = wgc::id::TypedId::zip(index, epoch + 1, backend);
+            let (index, epoch, backend) = self.buffer_id.unzip();
+            self.buffer_id = wgc::id::TypedId::zip(index, epoch + 1, backend);
+        }
+    }
+
+    /// Process a swap chain action. Returns the action back if it's not related to swap chains.
+    pub fn process<'a, B: wgc::hub::GfxBackend>(
+        &mut self,
+        global: &Global,
+        device: wgc::id::DeviceId,
+        action: trace::Action<'a>,
+        comb_manager: &mut wgc::hub::IdentityManager,
+    ) -> Option<trace::Action<'a>> {
+        match action {
+            trace::Action::CreateSwapChain(_, desc) => {
+                self.release::<B>(global);
+                global.device_maintain_ids::<B>(device).unwrap();
+                let (_, error) = global.device_create_texture::<B>(
+                    device,
+                    &wgt::TextureDescriptor {
+                        label: Some("frame".into()),
+                        size: wgt::Extent3d {
+                            width: desc.width,
+                            height: desc.height,
+                            depth: 1,
+                        },
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgt::TextureDimension::D2,
+                        format: desc.format,
+                        usage: desc.usage | wgt::TextureUsage::COPY_SRC,
+                    },
+                    self.texture_id,
+                );
+                if let Some(e) = error {
+                    panic!("{:?}", e);
+                }
+                let (_, error) = global.device_create_buffer::<B>(
+                    device,
+                    &wgt::BufferDescriptor {
+                        label: Some("frame readback".into()),
+                        size: Self::padded_bytes_per_row(&desc) as wgt::BufferAddress
+                            * desc.height as wgt::BufferAddress,
+                        usage: wgt::BufferUsage::MAP_READ | wgt::BufferUsage::COPY_DST,
+                        mapped_at_creation: false,
+                    },
+                    self.buffer_id,
+                );
+                if let Some(e) = error {
+                    panic!("{:?}", e);
+                }
+                self.desc = Some(desc);
+                None
+            }
+            trace::Action::GetSwapChainTexture { id, .. } => {
+                if let Some(id) = id {
+                    global.device_maintain_ids::<B>(device).unwrap();
+                    let (_, error) = global.texture_create_view::<B>(
+                        self.texture_id,
+                        &wgc::resource::TextureViewDescriptor {
+                            label: Some("frame".into()),
+                            format: None,
+                            dimension: None,
+                            aspect: wgt::TextureAspect::All,
+                            base_mip_level: 0,
+                            level_count: None,
+                            base_array_layer: 0,
+                            array_layer_count: None,
+                            swizzle: [wgt::ComponentSwizzle::Identity; 4],
+                        },
+                        id,
+                    );
+                    if let Some(e) = error {
+                        panic!("{:?}", e);
+                    }
+                    self.view_id = Some(id);
+                }
+                None
+            }
+            trace::Action::PresentSwapChain(_) => {
+                self.dump_frame::<B>(global, device, comb_manager);
+                // Presenting consumes the swap chain view.
+                if let Some(view_id) = self.view_id.take() {
+                    global.texture_view_drop::<B>(view_id, true).unwrap();
+                }
+                None
+            }
+            other => Some(other),
+        }
+    }
+
+    fn dump_frame<B: wgc::hub::GfxBackend>(
+        &mut self,
+        global: &Global,
+        device: wgc::id::DeviceId,
+        comb_manager: &mut wgc::hub::IdentityManager,
+    ) {
+        let desc = self.desc.as_ref().expect("Presenting without a swap chain");
+        let bytes_per_row = Self::padded_bytes_per_row(desc);
+        let size = bytes_per_row as wgt::BufferAddress * desc.height as wgt::BufferAddress;
+
+        let (encoder, error) = global.device_create_command_encoder::<B>(
+            device,
+            &wgt::CommandEncoderDescriptor { label: None },
+            comb_manager.alloc(device.backend()),
+        );
+        if let Some(e) = error {
+            panic!("{:?}", e);
+        }
+        global
+            .command_encoder_copy_texture_to_buffer::<B>(
+                encoder,
+                &wgc::command::TextureCopyView {
+                    texture: self.texture_id,
+                    mip_level: 0,
+                    origin: wgt::Origin3d::ZERO,
+                    aspect: wgt::TextureAspect::All,
+                },
+                &wgc::command::BufferCopyView {
+                    buffer: self.buffer_id,
+                    layout: wgt::TextureDataLayout {
+                        offset: 0,
+                        bytes_per_row,
+                        rows_per_image: 0,
+                    },
+                },
+                &wgt::Extent3d {
+                    width: desc.width,
+                    height: desc.height,
+                    depth: 1,
+                },
+            )
+            .unwrap();
+        let (cmdbuf, error) = global
+            .command_encoder_finish::<B>(encoder, &wgt::CommandBufferDescriptor { label: None });
+        if let Some(e) = error {
+            panic!("{:?}", e);
+        }
+        global.queue_submit::<B>(device, &[cmdbuf]).unwrap();
+
+        global
+            .buffer_map_async::<B>(
+                self.buffer_id,
+                0..size,
+                wgc::resource::BufferMapOperation {
+                    host: wgc::device::HostMap::Read,
+                    callback: map_callback,
+                    user_data: ptr::null_mut(),
+                },
+            )
+            .unwrap();
+        global
+            .device_poll::<B>(device, wgt::Maintain::Wait)
+            .unwrap();
+        let data = unsafe {
+            let ptr = global
+                .buffer_get_mapped_range::<B>(self.buffer_id, 0, None)
+                .unwrap();
+            slice::from_raw_parts(ptr, size as usize)
+        };
+
+        self.frame_count += 1;
+        let unpadded = desc.width as usize * desc.format.describe().block_size as usize;
+        self.hashes.push(hash_rows(
+            data.chunks(bytes_per_row as usize)
+                .map(|row| &row[..unpadded]),
+        ));
+        write_frame(&self.dir, self.frame_count, desc, bytes_per_row, data);
+        global.buffer_unmap::<B>(self.buffer_id).unwrap();
+    }
+}
+
+/// Hash of the frame rows, which is stable across platforms and releases.
+fn hash_rows<'a>(rows: impl Iterator<Item = &'a [u8]>) -> u64 {
+    let mut hasher = wgt::StableHasher::default();
+    for row in rows {
+        hasher.write(row);
+    }
+    hasher.finish()
+}
+
+fn write_frame(
+    dir: &Path,
+    index: u32,
+    desc: &wgt::SwapChainDescriptor,
+    bytes_per_row: u32,
+    data: &[u8],
+) {
+    let texel_size = desc.format.describe().block_size as usize;
+    let unpadded = desc.width as usize * texel_size;
+    let rows = data
+        .chunks(bytes_per_row as usize)
+        .map(|row| &row[..unpadded]);
+
+    let (swizzle, extension) = match desc.format {
+        wgt::TextureFormat::Rgba8Unorm | wgt::TextureFormat::Rgba8UnormSrgb => {
+            (Some([0, 1, 2]), "ppm")
+        }
+        wgt::TextureFormat::Bgra8Unorm | wgt::TextureFormat::Bgra8UnormSrgb => {
+            (Some([2, 1, 0]), "ppm")
+        }
+        _ => (None, "raw"),
+    };
+
+    let path = dir.join(format!("frame-{:04}.{}", index, extension));
+    log::info!("Writing frame {} into {:?}", index, path);
+    let mut file = fs::File::create(path).unwrap();
+    match swizzle {
+        Some(swizzle) => {
+            write!(file, "P6\n{} {}\n255\n", desc.width, desc.height).unwrap();
+            for row in rows {
+                let rgb = row
+                    .chunks(texel_size)
+                    .flat_map(|texel| swizzle.iter().map(move |&i| texel[i]))
+                    .collect::<Vec<_>>();
+                file.write_all(&rgb).unwrap();
+            }
+        }
+        None => {
+            for row in rows {
+                file.write_all(row).unwrap();
+            }
+        }
+    }
+}
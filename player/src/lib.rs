@@ -14,6 +14,35 @@ use wgc::device::trace;
 
 use std::{borrow::Cow, fmt::Debug, fs, marker::PhantomData, path::Path};
 
+mod dump;
+
+pub use dump::FrameDumper;
+
+/// Read a data file of the trace, decompressing it if needed.
+fn read_data(dir: &Path, name: &str) -> Vec<u8> {
+    let data =
+        fs::read(dir.join(name)).unwrap_or_else(|e| panic!("Unable to open '{}': {:?}", name, e));
+    if name.ends_with(trace::COMPRESSED_SUFFIX) {
+        decompress(name, data)
+    } else {
+        data
+    }
+}
+
+#[cfg(feature = "trace-compression")]
+fn decompress(name: &str, data: Vec<u8>) -> Vec<u8> {
+    zstd::decode_all(&data[..])
+        .unwrap_or_else(|e| panic!("Unable to decompress '{}': {:?}", name, e))
+}
+
+#[cfg(not(feature = "trace-compression"))]
+fn decompress(name: &str, _data: Vec<u8>) -> Vec<u8> {
+    panic!(
+        "Data file '{}' is compressed, enable the \"trace-compression\" feature",
+        name
+    )
+}
+
 #[derive(Debug)]
 pub struct IdentityPassThrough<I>(PhantomData<I>);
 
@@ -81,6 +110,9 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
                 trace::Command::CopyTextureToTexture { src, dst, size } => self
                     .command_encoder_copy_texture_to_texture::<B>(encoder, &src, &dst, &size)
                     .unwrap(),
+                trace::Command::GenerateMipmaps(texture) => self
+                    .command_encoder_generate_mipmaps::<B>(encoder, texture)
+                    .unwrap(),
                 trace::Command::WriteTimestamp {
                     query_set_id,
                     query_index,
@@ -103,6 +135,15 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
                         destination_offset,
                     )
                     .unwrap(),
+                trace::Command::PushDebugGroup(label) => self
+                    .command_encoder_push_debug_group::<B>(encoder, &label)
+                    .unwrap(),
+                trace::Command::PopDebugGroup => {
+                    self.command_encoder_pop_debug_group::<B>(encoder).unwrap()
+                }
+                trace::Command::InsertDebugMarker(label) => self
+                    .command_encoder_insert_debug_marker::<B>(encoder, &label)
+                    .unwrap(),
                 trace::Command::RunComputePass { base } => {
                     self.command_encoder_run_compute_pass_impl::<B>(encoder, base.as_ref())
                         .unwrap();
@@ -234,12 +275,14 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
                 self.bind_group_drop::<B>(id);
             }
             A::CreateShaderModule { id, desc, data } => {
-                let source = if data.ends_with(".wgsl") {
-                    let code = fs::read_to_string(dir.join(data)).unwrap();
+                let byte_vec = read_data(dir, &data);
+                let source = if data
+                    .trim_end_matches(trace::COMPRESSED_SUFFIX)
+                    .ends_with(".wgsl")
+                {
+                    let code = String::from_utf8(byte_vec).unwrap();
                     wgc::pipeline::ShaderModuleSource::Wgsl(Cow::Owned(code))
                 } else {
-                    let byte_vec = fs::read(dir.join(&data))
-                        .unwrap_or_else(|e| panic!("Unable to open '{}': {:?}", data, e));
                     let spv = byte_vec
                         .chunks(4)
                         .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
@@ -307,7 +350,7 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
                 range,
                 queued,
             } => {
-                let bin = std::fs::read(dir.join(data)).unwrap();
+                let bin = read_data(dir, &data);
                 let size = (range.end - range.start) as usize;
                 if queued {
                     self.queue_write_buffer::<B>(device, id, range.start, &bin)
@@ -324,7 +367,7 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
                 layout,
                 size,
             } => {
-                let bin = std::fs::read(dir.join(data)).unwrap();
+                let bin = read_data(dir, &data);
                 self.queue_write_texture::<B>(device, &to, &bin, &layout, &size)
                     .unwrap();
             }
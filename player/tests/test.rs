@@ -122,7 +122,7 @@ impl Test<'_> {
         }
 
         println!("\t\t\tWaiting...");
-        wgc::gfx_select!(device => global.device_poll(device, true)).unwrap();
+        wgc::gfx_select!(device => global.device_poll(device, wgt::Maintain::Wait)).unwrap();
 
         for expect in self.expectations {
             println!("\t\t\tChecking {}", expect.name);
@@ -166,6 +166,23 @@ const BACKENDS: &[wgt::Backend] = &[
     wgt::Backend::Gl,
 ];
 
+fn request_adapter(
+    global: &wgc::hub::Global<IdentityPassThroughFactory>,
+    backend: wgt::Backend,
+) -> Option<wgc::id::AdapterId> {
+    global
+        .request_adapter(
+            &wgc::instance::RequestAdapterOptions {
+                power_preference: wgt::PowerPreference::LowPower,
+                compatible_surface: None,
+            },
+            wgc::instance::AdapterInputs::IdSet(&[wgc::id::TypedId::zip(0, 0, backend)], |id| {
+                id.backend()
+            }),
+        )
+        .ok()
+}
+
 impl Corpus {
     fn run_from(path: PathBuf) {
         println!("Corpus {:?}", path);
@@ -177,18 +194,9 @@ impl Corpus {
             if !corpus.backends.contains(backend.into()) {
                 continue;
             }
-            let adapter = match global.request_adapter(
-                &wgc::instance::RequestAdapterOptions {
-                    power_preference: wgt::PowerPreference::LowPower,
-                    compatible_surface: None,
-                },
-                wgc::instance::AdapterInputs::IdSet(
-                    &[wgc::id::TypedId::zip(0, 0, backend)],
-                    |id| id.backend(),
-                ),
-            ) {
-                Ok(adapter) => adapter,
-                Err(_) => continue,
+            let adapter = match request_adapter(&global, backend) {
+                Some(adapter) => adapter,
+                None => continue,
             };
 
             println!("\tBackend {:?}", backend);
@@ -223,3 +231,108 @@ fn test_api() {
 
     Corpus::run_from(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data/all.ron"))
 }
+
+#[test]
+fn test_submit_unbalanced_debug_groups() {
+    let global =
+        wgc::hub::Global::new("test", IdentityPassThroughFactory, wgt::BackendBit::PRIMARY);
+    for &backend in BACKENDS {
+        let adapter = match request_adapter(&global, backend) {
+            Some(adapter) => adapter,
+            None => continue,
+        };
+        println!("Backend {:?}", backend);
+
+        let device = wgc::id::TypedId::zip(0, 0, backend);
+        let (_, error) = wgc::gfx_select!(adapter => global.adapter_request_device(
+            adapter,
+            &wgt::DeviceDescriptor {
+                label: None,
+                features: wgt::Features::empty(),
+                limits: wgt::Limits::default(),
+            },
+            None,
+            device
+        ));
+        if let Some(e) = error {
+            panic!("{:?}", e);
+        }
+        let (encoder, error) = wgc::gfx_select!(device => global.device_create_command_encoder(
+            device,
+            &wgt::CommandEncoderDescriptor { label: None },
+            wgc::id::TypedId::zip(0, 0, backend)
+        ));
+        if let Some(e) = error {
+            panic!("{:?}", e);
+        }
+        wgc::gfx_select!(device => global.command_encoder_push_debug_group(encoder, "unbalanced"))
+            .unwrap();
+
+        let (cmd_buf, error) = wgc::gfx_select!(device => global.command_encoder_finish(
+            encoder,
+            &wgt::CommandBufferDescriptor { label: None }
+        ));
+        match error {
+            Some(wgc::command::CommandEncoderError::UnbalancedDebugGroups(1)) => {}
+            other => panic!("unexpected finish error {:?}", other),
+        }
+        // The command buffer that failed to finish can't be submitted.
+        match wgc::gfx_select!(device => global.queue_submit(device, &[cmd_buf])) {
+            Err(wgc::device::queue::QueueSubmitError::InvalidCommandBuffer(id))
+                if id == cmd_buf => {}
+            other => panic!("unexpected submit result {:?}", other),
+        }
+
+        wgc::gfx_select!(device => global.clear_backend(()));
+    }
+}
+
+#[test]
+fn test_attachment_view_cache() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let global =
+        wgc::hub::Global::new("test", IdentityPassThroughFactory, wgt::BackendBit::PRIMARY);
+    for &backend in BACKENDS {
+        let adapter = match request_adapter(&global, backend) {
+            Some(adapter) => adapter,
+            None => continue,
+        };
+        println!("Backend {:?}", backend);
+
+        let test = Test::load(dir.join("texture-subresource.ron"), backend);
+        let device = wgc::id::TypedId::zip(0, 0, backend);
+        let (_, error) = wgc::gfx_select!(adapter => global.adapter_request_device(
+            adapter,
+            &wgt::DeviceDescriptor {
+                label: None,
+                features: test.features,
+                limits: wgt::Limits::default(),
+            },
+            None,
+            device
+        ));
+        if let Some(e) = error {
+            panic!("{:?}", e);
+        }
+
+        let texture = wgc::id::TypedId::zip(0, 1, backend);
+        let mut command_buffer_id_manager = wgc::hub::IdentityManager::default();
+        for action in test.actions {
+            let expected_views = match action {
+                // Three passes render into two layers, so one view is reused.
+                wgc::device::trace::Action::Submit(1, _) => Some(2),
+                wgc::device::trace::Action::FreeTexture(_) => Some(0),
+                _ => None,
+            };
+            wgc::gfx_select!(device => global.process(device, action, &dir, &mut command_buffer_id_manager));
+            if let Some(expected) = expected_views {
+                let count =
+                    wgc::gfx_select!(device => global.texture_attachment_view_count(texture))
+                        .unwrap();
+                assert_eq!(count, expected);
+            }
+        }
+
+        wgc::gfx_select!(device => global.clear_backend(()));
+    }
+}
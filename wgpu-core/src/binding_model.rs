@@ -23,6 +23,7 @@ use serde::Serialize;
 
 use std::{
     borrow::{Borrow, Cow},
+    hash::{Hash, Hasher},
     ops::Range,
 };
 
@@ -96,8 +97,15 @@ pub enum CreateBindGroupError {
     WrongSamplerComparison,
     #[error("bound texture views can not have both depth and stencil aspects enabled")]
     DepthStencilAspect,
+    #[error("binding {binding} samples a stencil aspect view as {actual:?}, but stencil values are unsigned integers")]
+    StencilSampleType {
+        binding: u32,
+        actual: wgt::TextureSampleType,
+    },
     #[error("the adapter does not support simultaneous read + write storage texture access for the format {0:?}")]
     StorageReadWriteNotSupported(wgt::TextureFormat),
+    #[error("texture view {0:?} has swizzled components and can't be storage")]
+    SwizzledStorageTexture(TextureViewId),
 }
 
 #[derive(Clone, Debug, Error)]
@@ -133,6 +141,8 @@ pub(crate) struct PerStageBindingTypeCounter {
     vertex: u32,
     fragment: u32,
     compute: u32,
+    task: u32,
+    mesh: u32,
 }
 
 impl PerStageBindingTypeCounter {
@@ -146,10 +156,21 @@ impl PerStageBindingTypeCounter {
         if stage.contains(wgt::ShaderStage::COMPUTE) {
             self.compute += count;
         }
+        if stage.contains(wgt::ShaderStage::TASK) {
+            self.task += count;
+        }
+        if stage.contains(wgt::ShaderStage::MESH) {
+            self.mesh += count;
+        }
     }
 
     pub(crate) fn max(&self) -> (BindingZone, u32) {
-        let max_value = self.vertex.max(self.fragment.max(self.compute));
+        let max_value = self
+            .vertex
+            .max(self.fragment)
+            .max(self.compute)
+            .max(self.task)
+            .max(self.mesh);
         let mut stage = wgt::ShaderStage::NONE;
         if max_value == self.vertex {
             stage |= wgt::ShaderStage::VERTEX
@@ -160,13 +181,22 @@ impl PerStageBindingTypeCounter {
         if max_value == self.compute {
             stage |= wgt::ShaderStage::COMPUTE
         }
+        if max_value == self.task {
+            stage |= wgt::ShaderStage::TASK
+        }
+        if max_value == self.mesh {
+            stage |= wgt::ShaderStage::MESH
+        }
         (BindingZone::Stage(stage), max_value)
     }
 
+    /// Accumulate the counts of another bind group layout of the same pipeline layout.
     pub(crate) fn merge(&mut self, other: &Self) {
-        self.vertex = self.vertex.max(other.vertex);
-        self.fragment = self.fragment.max(other.fragment);
-        self.compute = self.compute.max(other.compute);
+        self.vertex += other.vertex;
+        self.fragment += other.fragment;
+        self.compute += other.compute;
+        self.task += other.task;
+        self.mesh += other.mesh;
     }
 
     pub(crate) fn validate(
@@ -266,10 +296,6 @@ impl BindingTypeMaxCountValidator {
             limits.max_sampled_textures_per_shader_stage,
             BindingTypeMaxCountErrorKind::SampledTextures,
         )?;
-        self.storage_buffers.validate(
-            limits.max_storage_buffers_per_shader_stage,
-            BindingTypeMaxCountErrorKind::StorageBuffers,
-        )?;
         self.samplers.validate(
             limits.max_samplers_per_shader_stage,
             BindingTypeMaxCountErrorKind::Samplers,
@@ -326,6 +352,25 @@ pub struct BindGroupLayoutDescriptor<'a> {
     pub entries: Cow<'a, [wgt::BindGroupLayoutEntry]>,
 }
 
+/// The label isn't hashed, so that it doesn't affect the [`wgt::stable_hash`] of the descriptor.
+impl Hash for BindGroupLayoutDescriptor<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(hash_bind_group_layout_entries(self.entries.iter()));
+    }
+}
+
+/// Stable hash of the entries of a bind group layout, taken in binding order.
+///
+/// The order in which the entries are listed doesn't matter, so a layout hashes
+/// the same whether it was created from a descriptor or derived from shaders.
+pub(crate) fn hash_bind_group_layout_entries<'a>(
+    entries: impl Iterator<Item = &'a wgt::BindGroupLayoutEntry>,
+) -> u64 {
+    let mut sorted = entries.collect::<Vec<_>>();
+    sorted.sort_by_key(|entry| entry.binding);
+    wgt::stable_hash(&sorted)
+}
+
 pub(crate) type BindEntryMap = FastHashMap<u32, wgt::BindGroupLayoutEntry>;
 
 #[derive(Debug)]
@@ -420,7 +465,7 @@ pub enum PushConstantUploadError {
 /// Describes a pipeline layout.
 ///
 /// A `PipelineLayoutDescriptor` can be used to create a pipeline layout.
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "trace", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub struct PipelineLayoutDescriptor<'a> {
@@ -437,6 +482,20 @@ pub struct PipelineLayoutDescriptor<'a> {
     pub push_constant_ranges: Cow<'a, [wgt::PushConstantRange]>,
 }
 
+/// Stable hash of a pipeline layout, given the hashes of its bind group layouts.
+///
+/// The bind group layouts are hashed by contents rather than by ID, so the result
+/// doesn't depend on the IDs handed out at runtime. The label isn't hashed.
+pub(crate) fn hash_pipeline_layout(
+    bind_group_layouts: &[u64],
+    push_constant_ranges: &[wgt::PushConstantRange],
+) -> u64 {
+    let mut hasher = wgt::StableHasher::default();
+    bind_group_layouts.hash(&mut hasher);
+    push_constant_ranges.hash(&mut hasher);
+    hasher.finish()
+}
+
 #[derive(Debug)]
 pub struct PipelineLayout<B: hal::Backend> {
     pub(crate) raw: B::PipelineLayout,
@@ -640,3 +699,35 @@ pub enum GetBindGroupLayoutError {
     #[error("invalid group index {0}")]
     InvalidGroupIndex(u32),
 }
+
+#[test]
+fn test_per_stage_binding_count_merge() {
+    use wgt::ShaderStage as Ss;
+
+    let limit = 4;
+    let mut first = PerStageBindingTypeCounter::default();
+    first.add(Ss::VERTEX, 3);
+    first.add(Ss::FRAGMENT, 1);
+    let mut second = PerStageBindingTypeCounter::default();
+    second.add(Ss::FRAGMENT, 3);
+
+    // 7 bindings in total, but no single stage sees more than 4.
+    let mut pipeline = PerStageBindingTypeCounter::default();
+    pipeline.merge(&first);
+    pipeline.merge(&second);
+    assert!(pipeline
+        .validate(limit, BindingTypeMaxCountErrorKind::SampledTextures)
+        .is_ok());
+
+    let mut third = PerStageBindingTypeCounter::default();
+    third.add(Ss::FRAGMENT, 1);
+    pipeline.merge(&third);
+    match pipeline.validate(limit, BindingTypeMaxCountErrorKind::SampledTextures) {
+        Err(BindingTypeMaxCountError {
+            zone: BindingZone::Stage(Ss::FRAGMENT),
+            count: 5,
+            ..
+        }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
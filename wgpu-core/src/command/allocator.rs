@@ -2,7 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use super::CommandBuffer;
+use super::{CommandBuffer, CommandEncoderStatus, RecordingLimits};
 use crate::{
     device::DeviceError, hub::GfxBackend, id::DeviceId, track::TrackerSet, FastHashMap,
     PrivateFeatures, Stored, SubmissionIndex,
@@ -82,7 +82,9 @@ impl<B: GfxBackend> CommandAllocator<B> {
         device_id: Stored<DeviceId>,
         device: &B::Device,
         limits: wgt::Limits,
+        features: wgt::Features,
         private_features: PrivateFeatures,
+        recording_limits: RecordingLimits,
         label: &crate::Label,
         #[cfg(feature = "trace")] enable_tracing: bool,
     ) -> Result<CommandBuffer<B>, CommandAllocatorError> {
@@ -111,14 +113,19 @@ impl<B: GfxBackend> CommandAllocator<B> {
 
         Ok(CommandBuffer {
             raw: Vec::new(),
-            is_recording: true,
+            status: CommandEncoderStatus::Recording,
             recorded_thread_id: thread_id,
             device_id,
             trackers: TrackerSet::new(B::VARIANT),
             used_swap_chains: Default::default(),
             limits,
+            features,
             private_features,
             has_labels: label.is_some(),
+            debug_scope_depth: 0,
+            redundant_state: Default::default(),
+            recording_limits,
+            recorded_pass_commands: 0,
             #[cfg(feature = "trace")]
             commands: if enable_tracing {
                 Some(Vec::new())
@@ -226,6 +226,22 @@ impl Binder {
         })
     }
 
+    /// Checks if the bind group is already bound at `index` with the same offsets.
+    pub(super) fn is_bound(
+        &self,
+        index: usize,
+        bind_group_id: Valid<BindGroupId>,
+        offsets: &[DynamicOffset],
+    ) -> bool {
+        let entry = &self.entries[index];
+        match entry.provided {
+            Some(ref pair) => {
+                pair.group_id.value == bind_group_id && entry.dynamic_offsets.as_slice() == offsets
+            }
+            None => false,
+        }
+    }
+
     pub(super) fn invalid_mask(&self) -> BindGroupMask {
         self.entries.iter().enumerate().fold(0, |mask, (i, entry)| {
             if entry.is_valid().unwrap_or(true) {
@@ -34,13 +34,21 @@
     called. It goes through the commands and issues them into the native command
     buffer. Thanks to the "normalized" property, it doesn't track any bind group
     invalidations or index format changes.
+
+    ### Dynamic state
+
+    Bundles can't change the viewport, the scissor rectangle, the blend color,
+    or the stencil reference. They inherit these from the render pass at the
+    point of execution, and the pass re-applies them afterwards, so that the
+    following commands see the same values on all backends. The pipeline, bind
+    groups, and vertex and index buffers are reset after a bundle is executed.
 !*/
 #![allow(clippy::reversed_empty_ranges)]
 
 use crate::{
     command::{
-        BasePass, DrawError, MapPassErr, PassErrorScope, RenderCommand, RenderCommandError,
-        StateChange,
+        BasePass, DrawError, MapPassErr, PassErrorScope, RecordingBudget, RecordingLimitError,
+        RecordingLimitKind, RecordingLimits, RenderCommand, RenderCommandError, StateChange,
     },
     conv,
     device::{
@@ -49,6 +57,7 @@ use crate::{
     },
     hub::{GfxBackend, GlobalIdentityHandlerFactory, Hub, Resource, Storage, Token},
     id,
+    pipeline::PipelineFlags,
     resource::BufferUse,
     span,
     track::{TrackerSet, UsageConflict},
@@ -83,6 +92,8 @@ pub struct RenderBundleEncoder {
     base: BasePass<RenderCommand>,
     parent_id: id::DeviceId,
     pub(crate) context: RenderPassContext,
+    #[cfg_attr(feature = "serial-pass", serde(skip))]
+    budget: RecordingBudget,
 }
 
 impl RenderBundleEncoder {
@@ -109,6 +120,7 @@ impl RenderBundleEncoder {
                     sc as u8
                 },
             },
+            budget: RecordingBudget::default(),
         })
     }
 
@@ -124,6 +136,7 @@ impl RenderBundleEncoder {
                 },
                 sample_count: 0,
             },
+            budget: RecordingBudget::default(),
         }
     }
 
@@ -131,6 +144,13 @@ impl RenderBundleEncoder {
         self.parent_id
     }
 
+    /// Bounds the commands recorded into this bundle, see `Global::device_recording_limits`.
+    ///
+    /// Has to be called before any command is recorded.
+    pub fn set_recording_limits(&mut self, limits: &RecordingLimits) {
+        self.budget = RecordingBudget::for_bundle(limits);
+    }
+
     pub(crate) fn finish<B: hal::Backend, G: GlobalIdentityHandlerFactory>(
         self,
         desc: &RenderBundleDescriptor,
@@ -156,6 +176,14 @@ impl RenderBundleEncoder {
             used_bind_groups: 0,
             pipeline: StateChange::new(),
         };
+        self.budget.check().map_pass_err(PassErrorScope::Bundle)?;
+        RecordingLimitError::check(
+            RecordingLimitKind::RenderBundleCommands,
+            device.recording_limits.max_render_bundle_commands,
+            self.base.commands.len() as u64,
+        )
+        .map_pass_err(PassErrorScope::Bundle)?;
+
         let mut commands = Vec::new();
         let mut base = self.base.as_ref();
         let mut pipeline_layout_id = None::<id::Valid<id::PipelineLayoutId>>;
@@ -229,6 +257,10 @@ impl RenderBundleEncoder {
 
                     //TODO: check read-only depth
 
+                    if pipeline.flags.contains(PipelineFlags::MESH_SHADING) {
+                        return Err(RenderBundleErrorInner::MeshPipeline).map_pass_err(scope);
+                    }
+
                     let layout = &pipeline_layout_guard[pipeline.layout_id.value];
                     pipeline_layout_id = Some(pipeline.layout_id.value);
 
@@ -437,7 +469,11 @@ impl RenderBundleEncoder {
                 | RenderCommand::SetBlendColor(_)
                 | RenderCommand::SetStencilReference(_)
                 | RenderCommand::SetViewport { .. }
-                | RenderCommand::SetScissor(_) => unreachable!("not supported by a render bundle"),
+                | RenderCommand::SetScissor(_)
+                | RenderCommand::DrawMeshTasks { .. }
+                | RenderCommand::MultiDrawMeshTasksIndirect { .. } => {
+                    unreachable!("not supported by a render bundle")
+                }
             }
         }
 
@@ -467,6 +503,9 @@ impl RenderBundleEncoder {
         size: Option<wgt::BufferSize>,
     ) {
         span!(_guard, DEBUG, "RenderBundle::set_index_buffer");
+        if !self.budget.admit(self.base.commands.len()) {
+            return;
+        }
         self.base.commands.push(RenderCommand::SetIndexBuffer {
             buffer_id,
             index_format,
@@ -534,6 +573,7 @@ impl RenderBundle {
         bind_group_guard: &Storage<crate::binding_model::BindGroup<B>, id::BindGroupId>,
         pipeline_guard: &Storage<crate::pipeline::RenderPipeline<B>, id::RenderPipelineId>,
         buffer_guard: &Storage<crate::resource::Buffer<B>, id::BufferId>,
+        stencil_reference: u32,
     ) -> Result<(), ExecutionError> {
         use hal::command::CommandBuffer as _;
 
@@ -562,6 +602,9 @@ impl RenderBundle {
                 RenderCommand::SetPipeline(pipeline_id) => {
                     let pipeline = pipeline_guard.get(pipeline_id).unwrap();
                     cmd_buf.bind_graphics_pipeline(&pipeline.raw);
+                    if pipeline.flags.contains(PipelineFlags::STENCIL_REFERENCE) {
+                        cmd_buf.set_stencil_reference(hal::pso::Face::all(), stencil_reference);
+                    }
 
                     pipeline_layout_id = Some(pipeline.layout_id.value);
                 }
@@ -703,7 +746,9 @@ impl RenderBundle {
                 | RenderCommand::SetBlendColor(_)
                 | RenderCommand::SetStencilReference(_)
                 | RenderCommand::SetViewport { .. }
-                | RenderCommand::SetScissor(_) => unreachable!(),
+                | RenderCommand::SetScissor(_)
+                | RenderCommand::DrawMeshTasks { .. }
+                | RenderCommand::MultiDrawMeshTasksIndirect { .. } => unreachable!(),
             }
         }
 
@@ -1062,11 +1107,15 @@ pub(super) enum RenderBundleErrorInner {
     #[error(transparent)]
     Device(#[from] DeviceError),
     #[error(transparent)]
+    RecordingLimit(#[from] RecordingLimitError),
+    #[error(transparent)]
     RenderCommand(RenderCommandError),
     #[error(transparent)]
     ResourceUsageConflict(#[from] UsageConflict),
     #[error(transparent)]
     Draw(#[from] DrawError),
+    #[error("mesh pipelines can't be used in render bundles")]
+    MeshPipeline,
 }
 
 impl<T> From<T> for RenderBundleErrorInner
@@ -1127,6 +1176,9 @@ pub mod bundle_ffi {
         offset_length: usize,
     ) {
         span!(_guard, DEBUG, "RenderBundle::set_bind_group");
+        if !bundle.budget.admit_bind_group(bundle.base.commands.len()) {
+            return;
+        }
         bundle.base.commands.push(RenderCommand::SetBindGroup {
             index: index.try_into().unwrap(),
             num_dynamic_offsets: offset_length.try_into().unwrap(),
@@ -1146,6 +1198,9 @@ pub mod bundle_ffi {
         pipeline_id: id::RenderPipelineId,
     ) {
         span!(_guard, DEBUG, "RenderBundle::set_pipeline");
+        if !bundle.budget.admit(bundle.base.commands.len()) {
+            return;
+        }
         bundle
             .base
             .commands
@@ -1161,6 +1216,9 @@ pub mod bundle_ffi {
         size: Option<BufferSize>,
     ) {
         span!(_guard, DEBUG, "RenderBundle::set_vertex_buffer");
+        if !bundle.budget.admit(bundle.base.commands.len()) {
+            return;
+        }
         bundle.base.commands.push(RenderCommand::SetVertexBuffer {
             slot,
             buffer_id,
@@ -1178,6 +1236,9 @@ pub mod bundle_ffi {
         data: *const u8,
     ) {
         span!(_guard, DEBUG, "RenderBundle::set_push_constants");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         assert_eq!(
             offset & (wgt::PUSH_CONSTANT_ALIGNMENT - 1),
             0,
@@ -1216,6 +1277,9 @@ pub mod bundle_ffi {
         first_instance: u32,
     ) {
         span!(_guard, DEBUG, "RenderBundle::draw");
+        if !bundle.budget.admit(bundle.base.commands.len()) {
+            return;
+        }
         bundle.base.commands.push(RenderCommand::Draw {
             vertex_count,
             instance_count,
@@ -1234,6 +1298,9 @@ pub mod bundle_ffi {
         first_instance: u32,
     ) {
         span!(_guard, DEBUG, "RenderBundle::draw_indexed");
+        if !bundle.budget.admit(bundle.base.commands.len()) {
+            return;
+        }
         bundle.base.commands.push(RenderCommand::DrawIndexed {
             index_count,
             instance_count,
@@ -1250,6 +1317,9 @@ pub mod bundle_ffi {
         offset: BufferAddress,
     ) {
         span!(_guard, DEBUG, "RenderBundle::draw_indirect");
+        if !bundle.budget.admit(bundle.base.commands.len()) {
+            return;
+        }
         bundle.base.commands.push(RenderCommand::MultiDrawIndirect {
             buffer_id,
             offset,
@@ -1265,6 +1335,9 @@ pub mod bundle_ffi {
         offset: BufferAddress,
     ) {
         span!(_guard, DEBUG, "RenderBundle::draw_indexed_indirect");
+        if !bundle.budget.admit(bundle.base.commands.len()) {
+            return;
+        }
         bundle.base.commands.push(RenderCommand::MultiDrawIndirect {
             buffer_id,
             offset,
@@ -7,7 +7,8 @@ use crate::{
     command::{
         bind::{Binder, LayoutChange},
         end_pipeline_statistics_query, BasePass, BasePassRef, CommandBuffer, CommandEncoderError,
-        MapPassErr, PassErrorScope, QueryUseError, StateChange,
+        MapPassErr, PassErrorScope, QueryUseError, RecordingBudget, RecordingLimitError,
+        RecordingLimits, StateChange,
     },
     hub::{GfxBackend, Global, GlobalIdentityHandlerFactory, Storage, Token},
     id,
@@ -77,6 +78,8 @@ pub enum ComputeCommand {
 pub struct ComputePass {
     base: BasePass<ComputeCommand>,
     parent_id: id::CommandEncoderId,
+    #[cfg_attr(feature = "serial-pass", serde(skip))]
+    budget: RecordingBudget,
 }
 
 impl ComputePass {
@@ -84,9 +87,17 @@ impl ComputePass {
         Self {
             base: BasePass::new(&desc.label),
             parent_id,
+            budget: RecordingBudget::default(),
         }
     }
 
+    /// Bounds the commands recorded into this pass, see `Global::device_recording_limits`.
+    ///
+    /// Has to be called before any command is recorded.
+    pub fn set_recording_limits(&mut self, limits: &RecordingLimits) {
+        self.budget = RecordingBudget::for_pass(limits);
+    }
+
     pub fn parent_id(&self) -> id::CommandEncoderId {
         self.parent_id
     }
@@ -131,6 +142,8 @@ pub enum DispatchError {
 pub enum ComputePassErrorInner {
     #[error(transparent)]
     Encoder(#[from] CommandEncoderError),
+    #[error(transparent)]
+    RecordingLimit(#[from] RecordingLimitError),
     #[error("bind group {0:?} is invalid")]
     InvalidBindGroup(id::BindGroupId),
     #[error("bind group index {index} is greater than the device's requested `max_bind_group` limit {max}")]
@@ -147,6 +160,8 @@ pub enum ComputePassErrorInner {
     MissingBufferUsage(#[from] MissingBufferUsageError),
     #[error("cannot pop debug group, because number of pushed debug groups is zero")]
     InvalidPopDebugGroup,
+    #[error("compute pass ended with {0} debug groups still pushed")]
+    UnbalancedDebugGroups(u32),
     #[error(transparent)]
     Dispatch(#[from] DispatchError),
     #[error(transparent)]
@@ -237,6 +252,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         encoder_id: id::CommandEncoderId,
         pass: &ComputePass,
     ) -> Result<(), ComputePassError> {
+        pass.budget
+            .check()
+            .map_pass_err(PassErrorScope::Pass(encoder_id))?;
         self.command_encoder_run_compute_pass_impl::<B>(encoder_id, pass.base.as_ref())
     }
 
@@ -255,6 +273,15 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         let (mut cmd_buf_guard, mut token) = hub.command_buffers.write(&mut token);
         let cmd_buf =
             CommandBuffer::get_encoder_mut(&mut *cmd_buf_guard, encoder_id).map_pass_err(scope)?;
+        let bind_group_changes = base
+            .commands
+            .iter()
+            .filter(|command| matches!(command, ComputeCommand::SetBindGroup { .. }))
+            .count();
+        cmd_buf
+            .check_pass_limits(base.commands.len(), bind_group_changes)
+            .map_pass_err(scope)?;
+        cmd_buf.recorded_pass_commands += base.commands.len() as u64;
         let raw = cmd_buf.raw.last_mut().unwrap();
 
         #[cfg(feature = "trace")]
@@ -324,6 +351,13 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         .validate_dynamic_bindings(&temp_offsets)
                         .map_pass_err(scope)?;
 
+                    if state.binder.is_bound(
+                        index as usize,
+                        id::Valid(bind_group_id),
+                        &temp_offsets,
+                    ) {
+                        cmd_buf.redundant_state.bind_groups += 1;
+                    }
                     if let Some((pipeline_layout_id, follow_ups)) = state.binder.provide_entry(
                         index as usize,
                         id::Valid(bind_group_id),
@@ -352,6 +386,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     let scope = PassErrorScope::SetPipelineCompute(pipeline_id);
 
                     if state.pipeline.set_and_check_redundant(pipeline_id) {
+                        cmd_buf.redundant_state.pipelines += 1;
                         continue;
                     }
 
@@ -600,6 +635,15 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             }
         }
 
+        // The pass label is itself a debug group, which would otherwise
+        // be closed by the unbalanced `end_debug_marker` below.
+        if state.debug_scope_depth != 0 {
+            return Err(ComputePassErrorInner::UnbalancedDebugGroups(
+                state.debug_scope_depth,
+            ))
+            .map_pass_err(scope);
+        }
+
         if let Some(_) = base.label {
             unsafe {
                 raw.end_debug_marker();
@@ -631,6 +675,9 @@ pub mod compute_ffi {
         offset_length: usize,
     ) {
         span!(_guard, DEBUG, "ComputePass::set_bind_group");
+        if !pass.budget.admit_bind_group(pass.base.commands.len()) {
+            return;
+        }
         pass.base.commands.push(ComputeCommand::SetBindGroup {
             index: index.try_into().unwrap(),
             num_dynamic_offsets: offset_length.try_into().unwrap(),
@@ -649,6 +696,9 @@ pub mod compute_ffi {
         pipeline_id: id::ComputePipelineId,
     ) {
         span!(_guard, DEBUG, "ComputePass::set_pipeline");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         pass.base
             .commands
             .push(ComputeCommand::SetPipeline(pipeline_id));
@@ -662,6 +712,9 @@ pub mod compute_ffi {
         data: *const u8,
     ) {
         span!(_guard, DEBUG, "ComputePass::set_push_constant");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         assert_eq!(
             offset & (wgt::PUSH_CONSTANT_ALIGNMENT - 1),
             0,
@@ -698,6 +751,9 @@ pub mod compute_ffi {
         groups_z: u32,
     ) {
         span!(_guard, DEBUG, "ComputePass::dispatch");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         pass.base
             .commands
             .push(ComputeCommand::Dispatch([groups_x, groups_y, groups_z]));
@@ -710,6 +766,9 @@ pub mod compute_ffi {
         offset: BufferAddress,
     ) {
         span!(_guard, DEBUG, "ComputePass::dispatch_indirect");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         pass.base
             .commands
             .push(ComputeCommand::DispatchIndirect { buffer_id, offset });
@@ -722,6 +781,9 @@ pub mod compute_ffi {
         color: u32,
     ) {
         span!(_guard, DEBUG, "ComputePass::push_debug_group");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         let bytes = ffi::CStr::from_ptr(label).to_bytes();
         pass.base.string_data.extend_from_slice(bytes);
 
@@ -734,6 +796,9 @@ pub mod compute_ffi {
     #[no_mangle]
     pub extern "C" fn wgpu_compute_pass_pop_debug_group(pass: &mut ComputePass) {
         span!(_guard, DEBUG, "ComputePass::pop_debug_group");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         pass.base.commands.push(ComputeCommand::PopDebugGroup);
     }
 
@@ -744,6 +809,9 @@ pub mod compute_ffi {
         color: u32,
     ) {
         span!(_guard, DEBUG, "ComputePass::insert_debug_marker");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         let bytes = ffi::CStr::from_ptr(label).to_bytes();
         pass.base.string_data.extend_from_slice(bytes);
 
@@ -760,6 +828,9 @@ pub mod compute_ffi {
         query_index: u32,
     ) {
         span!(_guard, DEBUG, "ComputePass::write_timestamp");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
 
         pass.base.commands.push(ComputeCommand::WriteTimestamp {
             query_set_id,
@@ -778,6 +849,9 @@ pub mod compute_ffi {
             DEBUG,
             "ComputePass::begin_pipeline_statistics query"
         );
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
 
         pass.base
             .commands
@@ -792,6 +866,9 @@ pub mod compute_ffi {
         pass: &mut ComputePass,
     ) {
         span!(_guard, DEBUG, "ComputePass::end_pipeline_statistics_query");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
 
         pass.base
             .commands
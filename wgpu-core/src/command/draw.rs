@@ -57,6 +57,10 @@ pub enum DrawError {
         pipeline: wgt::IndexFormat,
         buffer: wgt::IndexFormat,
     },
+    #[error("mesh pipelines can only be drawn with `draw_mesh_tasks`")]
+    MeshPipeline,
+    #[error("`draw_mesh_tasks` requires a mesh pipeline")]
+    MissingMeshPipeline,
 }
 
 /// Error encountered when encoding a render command.
@@ -188,6 +192,16 @@ pub enum RenderCommand {
         max_count: u32,
         indexed: bool,
     },
+    DrawMeshTasks {
+        task_count: u32,
+        first_task: u32,
+    },
+    MultiDrawMeshTasksIndirect {
+        buffer_id: id::BufferId,
+        offset: BufferAddress,
+        /// Count of `None` represents a non-multi call.
+        count: Option<NonZeroU32>,
+    },
     PushDebugGroup {
         color: u32,
         len: usize,
@@ -20,6 +20,8 @@ pub use self::query::*;
 pub use self::render::*;
 pub use self::transfer::*;
 
+#[cfg(feature = "trace")]
+use crate::device::trace::Command as TraceCommand;
 use crate::{
     device::{all_buffer_stages, all_image_stages},
     hub::{GfxBackend, Global, GlobalIdentityHandlerFactory, Storage, Token},
@@ -30,7 +32,7 @@ use crate::{
     Label, PrivateFeatures, Stored,
 };
 
-use hal::command::CommandBuffer as _;
+use hal::{command::CommandBuffer as _, device::Device as _};
 use smallvec::SmallVec;
 use thiserror::Error;
 
@@ -38,17 +40,30 @@ use std::thread::ThreadId;
 
 const PUSH_CONSTANT_CLEAR_ARRAY: &[u32] = &[0_u32; 64];
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CommandEncoderStatus {
+    Recording,
+    Finished,
+    /// Finishing failed, so the command buffer can't be submitted.
+    Error,
+}
+
 #[derive(Debug)]
 pub struct CommandBuffer<B: hal::Backend> {
     pub(crate) raw: Vec<B::CommandBuffer>,
-    is_recording: bool,
+    status: CommandEncoderStatus,
     recorded_thread_id: ThreadId,
     pub(crate) device_id: Stored<id::DeviceId>,
     pub(crate) trackers: TrackerSet,
     pub(crate) used_swap_chains: SmallVec<[Stored<id::SwapChainId>; 1]>,
     limits: wgt::Limits,
+    features: wgt::Features,
     private_features: PrivateFeatures,
     has_labels: bool,
+    debug_scope_depth: u32,
+    redundant_state: RedundantStateReport,
+    recording_limits: RecordingLimits,
+    recorded_pass_commands: u64,
     #[cfg(feature = "trace")]
     pub(crate) commands: Option<Vec<crate::device::trace::Command>>,
     #[cfg(debug_assertions)]
@@ -61,7 +76,7 @@ impl<B: GfxBackend> CommandBuffer<B> {
         id: id::CommandEncoderId,
     ) -> Result<&Self, CommandEncoderError> {
         match storage.get(id) {
-            Ok(cmd_buf) if cmd_buf.is_recording => Ok(cmd_buf),
+            Ok(cmd_buf) if cmd_buf.status == CommandEncoderStatus::Recording => Ok(cmd_buf),
             Ok(_) => Err(CommandEncoderError::NotRecording),
             Err(_) => Err(CommandEncoderError::Invalid),
         }
@@ -72,12 +87,41 @@ impl<B: GfxBackend> CommandBuffer<B> {
         id: id::CommandEncoderId,
     ) -> Result<&mut Self, CommandEncoderError> {
         match storage.get_mut(id) {
-            Ok(cmd_buf) if cmd_buf.is_recording => Ok(cmd_buf),
+            Ok(cmd_buf) if cmd_buf.status == CommandEncoderStatus::Recording => Ok(cmd_buf),
             Ok(_) => Err(CommandEncoderError::NotRecording),
             Err(_) => Err(CommandEncoderError::Invalid),
         }
     }
 
+    /// Check if the command buffer was finished successfully, and can be submitted.
+    pub(crate) fn is_finished(&self) -> bool {
+        self.status == CommandEncoderStatus::Finished
+    }
+
+    /// Check a pass about to be recorded against the recording limits.
+    fn check_pass_limits(
+        &self,
+        commands: usize,
+        bind_group_changes: usize,
+    ) -> Result<(), RecordingLimitError> {
+        let limits = &self.recording_limits;
+        RecordingLimitError::check(
+            RecordingLimitKind::CommandsPerPass,
+            limits.max_commands_per_pass,
+            commands as u64,
+        )?;
+        RecordingLimitError::check(
+            RecordingLimitKind::BindGroupChangesPerPass,
+            limits.max_bind_group_changes_per_pass,
+            bind_group_changes as u64,
+        )?;
+        RecordingLimitError::check(
+            RecordingLimitKind::CommandsPerEncoder,
+            limits.max_commands_per_encoder,
+            self.recorded_pass_commands + commands as u64,
+        )
+    }
+
     pub(crate) fn insert_barriers(
         raw: &mut B::CommandBuffer,
         base: &mut TrackerSet,
@@ -143,7 +187,7 @@ pub struct BasePassRef<'a, C> {
 }
 
 #[doc(hidden)]
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 #[cfg_attr(
     any(feature = "serial-pass", feature = "trace"),
     derive(serde::Serialize)
@@ -193,31 +237,219 @@ impl<C: Clone> BasePass<C> {
     }
 }
 
+/// State changes recorded into a command buffer that had no effect.
+///
+/// The report is advisory: it helps finding the hot paths that re-set the same
+/// state, which costs validation time. Redundant pipeline changes are skipped
+/// when encoding, but redundant bind group changes are still encoded. Commands
+/// executed from render bundles are not counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RedundantStateReport {
+    /// Number of `set_pipeline` calls binding the pipeline that was already bound.
+    pub pipelines: u32,
+    /// Number of `set_bind_group` calls binding the bind group, with the same dynamic
+    /// offsets, that was already bound at the same index.
+    pub bind_groups: u32,
+}
+
+impl RedundantStateReport {
+    fn merge(&mut self, other: &Self) {
+        self.pipelines += other.pipelines;
+        self.bind_groups += other.bind_groups;
+    }
+}
+
+/// Bounds on the amount of work recorded into command encoders and render bundles.
+///
+/// These are not hardware limits: they let applications running untrusted content
+/// fail with an error instead of exhausting memory. Nothing is bounded by default.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordingLimits {
+    /// Maximum number of commands in a single render or compute pass.
+    pub max_commands_per_pass: u32,
+    /// Maximum number of `set_bind_group` commands in a single render or compute pass.
+    pub max_bind_group_changes_per_pass: u32,
+    /// Maximum number of pass commands recorded into a single command encoder.
+    pub max_commands_per_encoder: u32,
+    /// Maximum number of commands in a single render bundle.
+    pub max_render_bundle_commands: u32,
+}
+
+impl Default for RecordingLimits {
+    fn default() -> Self {
+        Self {
+            max_commands_per_pass: u32::MAX,
+            max_bind_group_changes_per_pass: u32::MAX,
+            max_commands_per_encoder: u32::MAX,
+            max_render_bundle_commands: u32::MAX,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum RecordingLimitKind {
+    CommandsPerPass,
+    BindGroupChangesPerPass,
+    CommandsPerEncoder,
+    RenderBundleCommands,
+}
+
+#[derive(Clone, Debug, Error)]
+#[error("too many {kind:?}, limit is {limit}, count was {count}")]
+pub struct RecordingLimitError {
+    pub kind: RecordingLimitKind,
+    pub limit: u32,
+    pub count: u64,
+}
+
+impl RecordingLimitError {
+    pub(crate) fn check(
+        kind: RecordingLimitKind,
+        limit: u32,
+        count: u64,
+    ) -> Result<(), RecordingLimitError> {
+        if count > limit as u64 {
+            Err(Self { kind, limit, count })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Bounds the commands of a pass or a render bundle encoder as they are recorded,
+/// so that hostile content can't grow them without limit before they are run.
+///
+/// Commands past the first overflow are dropped, and the error is reported
+/// when the pass is run or the bundle is finished.
+#[derive(Debug)]
+struct RecordingBudget {
+    commands_kind: RecordingLimitKind,
+    max_commands: u32,
+    max_bind_group_changes: u32,
+    bind_group_changes: u32,
+    error: Option<RecordingLimitError>,
+}
+
+impl RecordingBudget {
+    fn for_pass(limits: &RecordingLimits) -> Self {
+        Self {
+            commands_kind: RecordingLimitKind::CommandsPerPass,
+            max_commands: limits.max_commands_per_pass,
+            max_bind_group_changes: limits.max_bind_group_changes_per_pass,
+            bind_group_changes: 0,
+            error: None,
+        }
+    }
+
+    fn for_bundle(limits: &RecordingLimits) -> Self {
+        Self {
+            commands_kind: RecordingLimitKind::RenderBundleCommands,
+            max_commands: limits.max_render_bundle_commands,
+            max_bind_group_changes: u32::MAX,
+            bind_group_changes: 0,
+            error: None,
+        }
+    }
+
+    /// Accounts for a command recorded after `recorded` others.
+    /// Returns `false` if the command has to be dropped.
+    fn admit(&mut self, recorded: usize) -> bool {
+        if self.error.is_some() {
+            return false;
+        }
+        match RecordingLimitError::check(
+            self.commands_kind.clone(),
+            self.max_commands,
+            recorded as u64 + 1,
+        ) {
+            Ok(()) => true,
+            Err(err) => {
+                self.error = Some(err);
+                false
+            }
+        }
+    }
+
+    /// Same as `admit`, for a `set_bind_group` command.
+    fn admit_bind_group(&mut self, recorded: usize) -> bool {
+        if !self.admit(recorded) {
+            return false;
+        }
+        self.bind_group_changes += 1;
+        match RecordingLimitError::check(
+            RecordingLimitKind::BindGroupChangesPerPass,
+            self.max_bind_group_changes,
+            self.bind_group_changes as u64,
+        ) {
+            Ok(()) => true,
+            Err(err) => {
+                self.error = Some(err);
+                false
+            }
+        }
+    }
+
+    fn check(&self) -> Result<(), RecordingLimitError> {
+        match self.error {
+            Some(ref err) => Err(err.clone()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for RecordingBudget {
+    fn default() -> Self {
+        Self::for_pass(&RecordingLimits::default())
+    }
+}
+
 #[derive(Clone, Debug, Error)]
 pub enum CommandEncoderError {
     #[error("command encoder is invalid")]
     Invalid,
     #[error("command encoder must be active")]
     NotRecording,
+    #[error("cannot pop debug group, because number of pushed debug groups is zero")]
+    InvalidPopDebugGroup,
+    #[error("command encoder finished with {0} debug groups still pushed")]
+    UnbalancedDebugGroups(u32),
 }
 
 impl<G: GlobalIdentityHandlerFactory> Global<G> {
     pub fn command_encoder_finish<B: GfxBackend>(
         &self,
         encoder_id: id::CommandEncoderId,
-        _desc: &wgt::CommandBufferDescriptor<Label>,
+        desc: &wgt::CommandBufferDescriptor<Label>,
     ) -> (id::CommandBufferId, Option<CommandEncoderError>) {
         span!(_guard, INFO, "CommandEncoder::finish");
 
         let hub = B::hub(self);
         let mut token = Token::root();
+        let (device_guard, mut token) = hub.devices.read(&mut token);
         let (swap_chain_guard, mut token) = hub.swap_chains.read(&mut token);
         //TODO: actually close the last recorded command buffer
         let (mut cmd_buf_guard, _) = hub.command_buffers.write(&mut token);
 
         let error = match CommandBuffer::get_encoder_mut(&mut *cmd_buf_guard, encoder_id) {
+            Ok(cmd_buf) if cmd_buf.debug_scope_depth != 0 => {
+                cmd_buf.status = CommandEncoderStatus::Error;
+                Some(CommandEncoderError::UnbalancedDebugGroups(
+                    cmd_buf.debug_scope_depth,
+                ))
+            }
             Ok(cmd_buf) => {
-                cmd_buf.is_recording = false;
+                cmd_buf.status = CommandEncoderStatus::Finished;
+                if let Some(ref label) = desc.label {
+                    let device = &device_guard[cmd_buf.device_id.value];
+                    for raw in cmd_buf.raw.iter_mut() {
+                        unsafe { device.raw.set_command_buffer_name(raw, label) };
+                    }
+                    cmd_buf.has_labels = true;
+                    #[cfg(debug_assertions)]
+                    {
+                        cmd_buf.label = label.to_string();
+                    }
+                }
                 // stop tracking the swapchain image, if used
                 for sc_id in cmd_buf.used_swap_chains.iter() {
                     let view_id = swap_chain_guard[sc_id.value]
@@ -235,6 +467,56 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         (encoder_id, error)
     }
 
+    /// Give temporary access to the backend command buffer an encoder is recording.
+    ///
+    /// The callback gets `None` if the encoder is invalid or not recording anymore.
+    ///
+    /// The callback runs under the write lock of the command buffer storage, and must
+    /// not call into this `Global`, see [`Global::device_as_hal`].
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Global::device_as_hal`]. The usage tracking of wgpu doesn't know
+    /// about the commands recorded here, so the resources they use have to be in the
+    /// right state already, and have to be kept alive until the commands are done.
+    /// These commands aren't part of API traces either.
+    pub unsafe fn command_encoder_as_hal_mut<
+        B: GfxBackend,
+        F: FnOnce(Option<&mut B::CommandBuffer>) -> R,
+        R,
+    >(
+        &self,
+        encoder_id: id::CommandEncoderId,
+        hal_command_encoder_callback: F,
+    ) -> R {
+        span!(_guard, INFO, "CommandEncoder::as_hal_mut");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (mut cmd_buf_guard, _) = hub.command_buffers.write(&mut token);
+        let hal_command_buffer = CommandBuffer::get_encoder_mut(&mut *cmd_buf_guard, encoder_id)
+            .ok()
+            .and_then(|cmd_buf| cmd_buf.raw.last_mut());
+
+        hal_command_encoder_callback(hal_command_buffer)
+    }
+
+    /// Returns the redundant state changes found in the passes of a command buffer so far.
+    pub fn command_buffer_redundant_state<B: GfxBackend>(
+        &self,
+        command_buffer_id: id::CommandBufferId,
+    ) -> Result<RedundantStateReport, CommandEncoderError> {
+        span!(_guard, INFO, "CommandBuffer::redundant_state");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (cmd_buf_guard, _) = hub.command_buffers.read(&mut token);
+        cmd_buf_guard
+            .get(command_buffer_id)
+            .map(|cmd_buf| cmd_buf.redundant_state)
+            .map_err(|_| CommandEncoderError::Invalid)
+    }
+
     pub fn command_encoder_push_debug_group<B: GfxBackend>(
         &self,
         encoder_id: id::CommandEncoderId,
@@ -247,8 +529,14 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
 
         let (mut cmd_buf_guard, _) = hub.command_buffers.write(&mut token);
         let cmd_buf = CommandBuffer::get_encoder_mut(&mut *cmd_buf_guard, encoder_id)?;
-        let cmd_buf_raw = cmd_buf.raw.last_mut().unwrap();
 
+        #[cfg(feature = "trace")]
+        if let Some(ref mut list) = cmd_buf.commands {
+            list.push(TraceCommand::PushDebugGroup(label.to_string()));
+        }
+
+        cmd_buf.debug_scope_depth += 1;
+        let cmd_buf_raw = cmd_buf.raw.last_mut().unwrap();
         unsafe {
             cmd_buf_raw.begin_debug_marker(label, 0);
         }
@@ -267,8 +555,13 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
 
         let (mut cmd_buf_guard, _) = hub.command_buffers.write(&mut token);
         let cmd_buf = CommandBuffer::get_encoder_mut(&mut *cmd_buf_guard, encoder_id)?;
-        let cmd_buf_raw = cmd_buf.raw.last_mut().unwrap();
 
+        #[cfg(feature = "trace")]
+        if let Some(ref mut list) = cmd_buf.commands {
+            list.push(TraceCommand::InsertDebugMarker(label.to_string()));
+        }
+
+        let cmd_buf_raw = cmd_buf.raw.last_mut().unwrap();
         unsafe {
             cmd_buf_raw.insert_debug_marker(label, 0);
         }
@@ -286,8 +579,17 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
 
         let (mut cmd_buf_guard, _) = hub.command_buffers.write(&mut token);
         let cmd_buf = CommandBuffer::get_encoder_mut(&mut *cmd_buf_guard, encoder_id)?;
-        let cmd_buf_raw = cmd_buf.raw.last_mut().unwrap();
 
+        #[cfg(feature = "trace")]
+        if let Some(ref mut list) = cmd_buf.commands {
+            list.push(TraceCommand::PopDebugGroup);
+        }
+
+        if cmd_buf.debug_scope_depth == 0 {
+            return Err(CommandEncoderError::InvalidPopDebugGroup);
+        }
+        cmd_buf.debug_scope_depth -= 1;
+        let cmd_buf_raw = cmd_buf.raw.last_mut().unwrap();
         unsafe {
             cmd_buf_raw.end_debug_marker();
         }
@@ -369,6 +671,11 @@ pub enum PassErrorScope {
         indirect: bool,
         pipeline: Option<id::RenderPipelineId>,
     },
+    #[error("In a draw_mesh_tasks command, indirect:{indirect}")]
+    DrawMeshTasks {
+        indirect: bool,
+        pipeline: Option<id::RenderPipelineId>,
+    },
     #[error("While resetting queries after the renderpass was ran")]
     QueryReset,
     #[error("In a write_timestamp command")]
@@ -387,3 +694,30 @@ pub enum PassErrorScope {
     #[error("In a pop_debug_group command")]
     PopDebugGroup,
 }
+
+#[test]
+fn test_recording_budget() {
+    let limits = RecordingLimits {
+        max_commands_per_pass: 3,
+        max_bind_group_changes_per_pass: 1,
+        ..RecordingLimits::default()
+    };
+    let mut budget = RecordingBudget::for_pass(&limits);
+    assert!(budget.admit_bind_group(0));
+    assert!(budget.admit(1));
+    assert!(!budget.admit_bind_group(2));
+    // Everything after the first overflow is dropped, and the first error is kept.
+    assert!(!budget.admit(2));
+    match budget.check() {
+        Err(RecordingLimitError {
+            kind: RecordingLimitKind::BindGroupChangesPerPass,
+            limit: 1,
+            count: 2,
+        }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    let mut budget = RecordingBudget::for_bundle(&limits);
+    assert!(budget.admit(0));
+    assert!(budget.check().is_ok());
+}
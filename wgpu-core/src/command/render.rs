@@ -8,7 +8,8 @@ use crate::{
         bind::{Binder, LayoutChange},
         end_pipeline_statistics_query, BasePass, BasePassRef, CommandBuffer, CommandEncoderError,
         DrawError, ExecutionError, MapPassErr, PassErrorScope, QueryResetMap, QueryUseError,
-        RenderCommand, RenderCommandError, StateChange,
+        RecordingBudget, RecordingLimitError, RecordingLimits, RedundantStateReport, RenderCommand,
+        RenderCommandError, StateChange,
     },
     conv,
     device::{
@@ -18,7 +19,9 @@ use crate::{
     hub::{GfxBackend, Global, GlobalIdentityHandlerFactory, Storage, Token},
     id,
     pipeline::PipelineFlags,
-    resource::{BufferUse, Texture, TextureUse, TextureView, TextureViewInner},
+    resource::{
+        BufferUse, CreateTextureViewError, Texture, TextureUse, TextureView, TextureViewInner,
+    },
     span,
     track::{TextureSelector, TrackerSet, UsageConflict},
     validation::{
@@ -48,6 +51,7 @@ use std::{
     num::NonZeroU32,
     ops::Range,
     str,
+    sync::Arc,
 };
 
 /// Operation to perform to the output attachment at the start of a renderpass.
@@ -89,21 +93,121 @@ pub struct PassChannel<V> {
     pub clear_value: V,
     /// If true, the relevant channel is not changed by a renderpass, and the corresponding attachment
     /// can be used inside the pass by other read-only usages.
+    ///
+    /// When every aspect of a depth/stencil attachment is read-only, the same texture can be
+    /// sampled in the pass while depth testing against it, e.g. for soft particles.
+    /// The load and store operations of a read-only channel must be [`LoadOp::Load`] and
+    /// [`StoreOp::Store`].
     pub read_only: bool,
 }
 
+/// A mip level and array layer of a 2D texture, attached without an explicit view.
+///
+/// The view is created on the first use and cached by the texture, so rendering
+/// into each face of a cubemap or each cascade of a shadow map doesn't need
+/// a view per subresource.
+///
+/// In C, `texture` is a 64-bit ID where 0 means no texture.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(any(feature = "serial-pass", feature = "trace"), derive(Serialize))]
+#[cfg_attr(any(feature = "serial-pass", feature = "replay"), derive(Deserialize))]
+pub struct AttachmentSubresource {
+    /// The texture to attach, or `None` to attach the view instead.
+    pub texture: Option<id::TextureId>,
+    pub mip_level: u32,
+    pub array_layer: u32,
+}
+
+impl AttachmentSubresource {
+    /// Returns the attached view, which is `attachment_view` for texture subresources.
+    ///
+    /// Exactly one of `view_id` and the texture of the subresource has to be set.
+    fn view<'a, B: hal::Backend>(
+        &self,
+        view_id: Option<id::TextureViewId>,
+        attachment_view: Option<&'a TextureView<B>>,
+        view_guard: &'a Storage<TextureView<B>, id::TextureViewId>,
+        trackers: &mut TrackerSet,
+    ) -> Result<&'a TextureView<B>, RenderPassErrorInner> {
+        match (view_id, self.texture) {
+            (Some(view_id), None) => trackers
+                .views
+                .use_extend(view_guard, view_id, (), ())
+                .map_err(|_| RenderPassErrorInner::InvalidAttachment(view_id)),
+            (None, Some(_)) => Ok(attachment_view.expect("Attachment view is not created")),
+            (Some(_), Some(_)) | (None, None) => Err(RenderPassErrorInner::AttachmentSource),
+        }
+    }
+}
+
+/// Deserializes the view of an attachment.
+///
+/// Traces recorded before the view became optional have a bare ID there,
+/// so it's accepted in human-readable formats, along with `Some` and `None`.
+#[cfg(feature = "replay")]
+fn deserialize_attachment_view<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<id::TextureViewId>, D::Error> {
+    #[derive(Deserialize)]
+    enum AttachmentView {
+        None,
+        Some(id::TextureViewId),
+        Id(crate::Index, crate::Epoch, wgt::Backend),
+    }
+
+    if !deserializer.is_human_readable() {
+        return Option::deserialize(deserializer);
+    }
+    Ok(match AttachmentView::deserialize(deserializer)? {
+        AttachmentView::None => None,
+        AttachmentView::Some(id) => Some(id),
+        AttachmentView::Id(index, epoch, backend) => Some(id::TypedId::zip(index, epoch, backend)),
+    })
+}
+
 /// Describes a color attachment to a render pass.
 #[repr(C)]
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(any(feature = "serial-pass", feature = "trace"), derive(Serialize))]
 #[cfg_attr(any(feature = "serial-pass", feature = "replay"), derive(Deserialize))]
 pub struct ColorAttachmentDescriptor {
-    /// The view to use as an attachment.
-    pub attachment: id::TextureViewId,
+    /// The view to use as an attachment, or `None` to attach `texture_subresource`.
+    ///
+    /// In C, this is a 64-bit ID where 0 means no view.
+    #[cfg_attr(
+        feature = "replay",
+        serde(deserialize_with = "deserialize_attachment_view")
+    )]
+    pub attachment: Option<id::TextureViewId>,
+    /// The texture subresource to use as an attachment instead of a view.
+    ///
+    /// Its `texture` has to be set if and only if `attachment` is `None`.
+    #[cfg_attr(any(feature = "serial-pass", feature = "replay"), serde(default))]
+    pub texture_subresource: AttachmentSubresource,
     /// The view that will receive the resolved output if multisampling is used.
     pub resolve_target: Option<id::TextureViewId>,
     /// What operations will be performed on this color attachment.
     pub channel: PassChannel<Color>,
+    /// Depth slice to render into, when the view is a 3D texture view.
+    /// It must be [`DEPTH_SLICE_UNDEFINED`] for all other views.
+    ///
+    /// Requires [`wgt::Features::RENDER_ATTACHMENT_3D_SLICES`].
+    #[cfg_attr(
+        any(feature = "serial-pass", feature = "replay"),
+        serde(default = "ColorAttachmentDescriptor::default_depth_slice")
+    )]
+    pub depth_slice: u32,
+}
+
+/// Value of [`ColorAttachmentDescriptor::depth_slice`] for views that aren't 3D.
+pub const DEPTH_SLICE_UNDEFINED: u32 = u32::MAX;
+
+impl ColorAttachmentDescriptor {
+    #[cfg(any(feature = "serial-pass", feature = "replay"))]
+    fn default_depth_slice() -> u32 {
+        DEPTH_SLICE_UNDEFINED
+    }
 }
 
 /// Describes a depth/stencil attachment to a render pass.
@@ -112,8 +216,19 @@ pub struct ColorAttachmentDescriptor {
 #[cfg_attr(any(feature = "serial-pass", feature = "trace"), derive(Serialize))]
 #[cfg_attr(any(feature = "serial-pass", feature = "replay"), derive(Deserialize))]
 pub struct DepthStencilAttachmentDescriptor {
-    /// The view to use as an attachment.
-    pub attachment: id::TextureViewId,
+    /// The view to use as an attachment, or `None` to attach `texture_subresource`.
+    ///
+    /// In C, this is a 64-bit ID where 0 means no view.
+    #[cfg_attr(
+        feature = "replay",
+        serde(deserialize_with = "deserialize_attachment_view")
+    )]
+    pub attachment: Option<id::TextureViewId>,
+    /// The texture subresource to use as an attachment instead of a view.
+    ///
+    /// Its `texture` has to be set if and only if `attachment` is `None`.
+    #[cfg_attr(any(feature = "serial-pass", feature = "replay"), serde(default))]
+    pub texture_subresource: AttachmentSubresource,
     /// What operations will be performed on the depth part of the attachment.
     pub depth: PassChannel<f32>,
     /// What operations will be performed on the stencil part of the attachment.
@@ -121,18 +236,26 @@ pub struct DepthStencilAttachmentDescriptor {
 }
 
 impl DepthStencilAttachmentDescriptor {
+    /// Returns true if all the aspects of the attachment are read-only.
+    ///
+    /// Only the channels of the aspects present in the view are considered,
+    /// so depth-only formats don't need to mark the stencil as read-only.
     fn is_read_only(&self, aspects: hal::format::Aspects) -> Result<bool, RenderPassErrorInner> {
-        if aspects.contains(hal::format::Aspects::DEPTH) && !self.depth.read_only {
-            return Ok(false);
-        }
-        if (self.depth.load_op, self.depth.store_op) != (LoadOp::Load, StoreOp::Store) {
-            return Err(RenderPassErrorInner::InvalidDepthOps);
-        }
-        if aspects.contains(hal::format::Aspects::STENCIL) && !self.stencil.read_only {
-            return Ok(false);
+        if aspects.contains(hal::format::Aspects::DEPTH) {
+            if !self.depth.read_only {
+                return Ok(false);
+            }
+            if (self.depth.load_op, self.depth.store_op) != (LoadOp::Load, StoreOp::Store) {
+                return Err(RenderPassErrorInner::InvalidDepthOps);
+            }
         }
-        if (self.stencil.load_op, self.stencil.store_op) != (LoadOp::Load, StoreOp::Store) {
-            return Err(RenderPassErrorInner::InvalidStencilOps);
+        if aspects.contains(hal::format::Aspects::STENCIL) {
+            if !self.stencil.read_only {
+                return Ok(false);
+            }
+            if (self.stencil.load_op, self.stencil.store_op) != (LoadOp::Load, StoreOp::Store) {
+                return Err(RenderPassErrorInner::InvalidStencilOps);
+            }
         }
         Ok(true)
     }
@@ -154,6 +277,8 @@ pub struct RenderPass {
     parent_id: id::CommandEncoderId,
     color_targets: ArrayVec<[ColorAttachmentDescriptor; MAX_COLOR_TARGETS]>,
     depth_stencil_target: Option<DepthStencilAttachmentDescriptor>,
+    #[cfg_attr(feature = "serial-pass", serde(skip))]
+    budget: RecordingBudget,
 }
 
 impl RenderPass {
@@ -163,9 +288,17 @@ impl RenderPass {
             parent_id,
             color_targets: desc.color_attachments.iter().cloned().collect(),
             depth_stencil_target: desc.depth_stencil_attachment.cloned(),
+            budget: RecordingBudget::default(),
         }
     }
 
+    /// Bounds the commands recorded into this pass, see `Global::device_recording_limits`.
+    ///
+    /// Has to be called before any command is recorded.
+    pub fn set_recording_limits(&mut self, limits: &RecordingLimits) {
+        self.budget = RecordingBudget::for_pass(limits);
+    }
+
     pub fn parent_id(&self) -> id::CommandEncoderId {
         self.parent_id
     }
@@ -187,6 +320,9 @@ impl RenderPass {
         size: Option<BufferSize>,
     ) {
         span!(_guard, DEBUG, "RenderPass::set_index_buffer");
+        if !self.budget.admit(self.base.commands.len()) {
+            return;
+        }
         self.base.commands.push(RenderCommand::SetIndexBuffer {
             buffer_id,
             index_format,
@@ -327,7 +463,10 @@ struct State {
     pipeline_flags: PipelineFlags,
     binder: Binder,
     blend_color: OptionalState,
+    blend_constants: hal::pso::ColorValue,
     stencil_reference: u32,
+    viewport: hal::pso::Viewport,
+    scissor: hal::pso::Rect,
     pipeline: StateChange<id::RenderPipelineId>,
     index: IndexState,
     vertex: VertexState,
@@ -335,6 +474,19 @@ struct State {
 }
 
 impl State {
+    /// Checks that the kind of draw matches the pipeline, which has to happen
+    /// even when the other draw validation is skipped.
+    fn check_draw_kind(&self, mesh_tasks: bool) -> Result<(), DrawError> {
+        match (
+            mesh_tasks,
+            self.pipeline_flags.contains(PipelineFlags::MESH_SHADING),
+        ) {
+            (false, true) => Err(DrawError::MeshPipeline),
+            (true, false) => Err(DrawError::MissingMeshPipeline),
+            _ => Ok(()),
+        }
+    }
+
     fn is_ready(&self) -> Result<(), DrawError> {
         // Determine how many vertex buffers have already been bound
         let bound_buffers = self.vertex.inputs.iter().take_while(|v| v.bound).count() as u32;
@@ -374,6 +526,18 @@ impl State {
         Ok(())
     }
 
+    /// Re-apply the dynamic state of the pass after a `RenderBundle`.
+    ///
+    /// Bundles inherit the dynamic state but backends differ in whether
+    /// it survives the pipeline changes within them.
+    unsafe fn restore_dynamic_state<B: hal::Backend>(&self, raw: &mut B::CommandBuffer) {
+        raw.set_viewports(0, iter::once(self.viewport.clone()));
+        raw.set_scissors(0, iter::once(self.scissor));
+        if self.blend_color == OptionalState::Set {
+            raw.set_blend_constants(self.blend_constants);
+        }
+    }
+
     /// Reset the `RenderBundle`-related states.
     fn reset_bundle(&mut self) {
         self.binder.reset();
@@ -388,8 +552,16 @@ impl State {
 pub enum RenderPassErrorInner {
     #[error(transparent)]
     Encoder(#[from] CommandEncoderError),
+    #[error(transparent)]
+    RecordingLimit(#[from] RecordingLimitError),
     #[error("attachment texture view {0:?} is invalid")]
     InvalidAttachment(id::TextureViewId),
+    #[error("attachment texture {0:?} is invalid")]
+    InvalidAttachmentTexture(id::TextureId),
+    #[error("attachment must have either a view or a texture subresource")]
+    AttachmentSource,
+    #[error("unable to create the view of an attachment: {0}")]
+    AttachmentView(#[from] CreateTextureViewError),
     #[error("necessary attachments are missing")]
     MissingAttachments,
     #[error("attachments have differing sizes: {previous:?} is followed by {mismatch:?}")]
@@ -397,8 +569,20 @@ pub enum RenderPassErrorInner {
         previous: (&'static str, wgt::Extent3d),
         mismatch: (&'static str, wgt::Extent3d),
     },
+    #[error("{type_name} attachment view must cover a single mip level and array layer, but it covers {levels} levels and {layers} layers")]
+    AttachmentNotSingleSubresource {
+        type_name: &'static str,
+        levels: u32,
+        layers: u32,
+    },
     #[error("attachment's sample count {0} is invalid")]
     InvalidSampleCount(u8),
+    #[error("3D color attachment view needs a depth slice")]
+    MissingDepthSlice,
+    #[error("depth slice {slice} is out of range of the {count} slices of the view")]
+    InvalidDepthSlice { slice: u32, count: u32 },
+    #[error("{0} attachment view swizzles its components")]
+    SwizzledAttachment(&'static str),
     #[error("attachment with resolve target must be multi-sampled")]
     InvalidResolveSourceSampleCount,
     #[error("resolve target must have a sample count of 1")]
@@ -433,6 +617,8 @@ pub enum RenderPassErrorInner {
     },
     #[error("cannot pop debug group, because number of pushed debug groups is zero")]
     InvalidPopDebugGroup,
+    #[error("render pass ended with {0} debug groups still pushed")]
+    UnbalancedDebugGroups(u32),
     #[error(transparent)]
     ResourceUsageConflict(#[from] UsageConflict),
     #[error("render bundle is incompatible, {0}")]
@@ -491,6 +677,42 @@ fn check_device_features(
     }
 }
 
+/// Creates the views of the attachments that are texture subresources.
+fn create_attachment_views<B: GfxBackend>(
+    color_attachments: &[ColorAttachmentDescriptor],
+    depth_stencil_attachment: Option<&DepthStencilAttachmentDescriptor>,
+    device: &Device<B>,
+    texture_guard: &Storage<Texture<B>, id::TextureId>,
+) -> Result<AttachmentData<Option<TextureView<B>>>, RenderPassErrorInner> {
+    let create_view =
+        |sub: &AttachmentSubresource| -> Result<Option<TextureView<B>>, RenderPassErrorInner> {
+            let texture = match sub.texture {
+                Some(texture) => texture,
+                None => return Ok(None),
+            };
+            let texture_data = texture_guard
+                .get(texture)
+                .map_err(|_| RenderPassErrorInner::InvalidAttachmentTexture(texture))?;
+            let view = device.create_attachment_view(
+                texture_data,
+                texture,
+                sub.mip_level,
+                sub.array_layer,
+            )?;
+            Ok(Some(view))
+        };
+    Ok(AttachmentData {
+        colors: color_attachments
+            .iter()
+            .map(|at| create_view(&at.texture_subresource))
+            .collect::<Result<_, _>>()?,
+        resolves: ArrayVec::new(),
+        depth_stencil: depth_stencil_attachment
+            .map(|at| create_view(&at.texture_subresource))
+            .transpose()?,
+    })
+}
+
 struct RenderAttachment<'a> {
     texture_id: &'a Stored<id::TextureId>,
     selector: &'a TextureSelector,
@@ -516,6 +738,8 @@ impl<'a, B: GfxBackend> RenderPassInfo<'a, B> {
         cmd_buf: &CommandBuffer<B>,
         device: &Device<B>,
         view_guard: &'a Storage<TextureView<B>, id::TextureViewId>,
+        texture_guard: &Storage<Texture<B>, id::TextureId>,
+        attachment_views: &'a AttachmentData<Option<TextureView<B>>>,
     ) -> Result<Self, RenderPassErrorInner> {
         let sample_count_limit = device.hal_limits.framebuffer_color_sample_counts;
 
@@ -534,6 +758,18 @@ impl<'a, B: GfxBackend> RenderPassInfo<'a, B> {
         let mut trackers = TrackerSet::new(B::VARIANT);
 
         let mut add_view = |view: &TextureView<B>, type_name| {
+            let levels = (view.selector.levels.end - view.selector.levels.start) as u32;
+            let layers = (view.selector.layers.end - view.selector.layers.start) as u32;
+            if levels != 1 || layers != 1 {
+                return Err(RenderPassErrorInner::AttachmentNotSingleSubresource {
+                    type_name,
+                    levels,
+                    layers,
+                });
+            }
+            if view.swizzled {
+                return Err(RenderPassErrorInner::SwizzledAttachment(type_name));
+            }
             if let Some(ex) = extent {
                 if ex != view.extent {
                     return Err(RenderPassErrorInner::AttachmentsDimensionMismatch {
@@ -556,19 +792,31 @@ impl<'a, B: GfxBackend> RenderPassInfo<'a, B> {
             Ok(())
         };
 
+        let mut color_views = ArrayVec::<[&TextureView<B>; MAX_COLOR_TARGETS]>::new();
+        let mut depth_slice_views =
+            ArrayVec::<[Option<Arc<B::ImageView>>; MAX_COLOR_TARGETS]>::new();
+        let mut depth_stencil_view = None;
+
         let rp_key = {
             let depth_stencil = match depth_stencil_attachment {
                 Some(at) => {
-                    let view = trackers
-                        .views
-                        .use_extend(&*view_guard, at.attachment, (), ())
-                        .map_err(|_| RenderPassErrorInner::InvalidAttachment(at.attachment))?;
+                    let view = at.texture_subresource.view(
+                        at.attachment,
+                        attachment_views
+                            .depth_stencil
+                            .as_ref()
+                            .and_then(Option::as_ref),
+                        view_guard,
+                        &mut trackers,
+                    )?;
                     add_view(view, "depth")?;
+                    depth_stencil_view = Some(view);
 
                     depth_stencil_aspects = view.aspects;
 
                     let source_id = match view.inner {
-                        TextureViewInner::Native { ref source_id, .. } => source_id,
+                        TextureViewInner::Native { ref source_id, .. }
+                        | TextureViewInner::Attachment { ref source_id, .. } => source_id,
                         TextureViewInner::SwapChain { .. } => {
                             return Err(RenderPassErrorInner::SwapChainImageAsDepthStencil);
                         }
@@ -616,15 +864,55 @@ impl<'a, B: GfxBackend> RenderPassInfo<'a, B> {
             let mut colors = ArrayVec::new();
             let mut resolves = ArrayVec::new();
 
-            for at in color_attachments {
-                let view = trackers
-                    .views
-                    .use_extend(&*view_guard, at.attachment, (), ())
-                    .map_err(|_| RenderPassErrorInner::InvalidAttachment(at.attachment))?;
+            for (at, attachment_view) in color_attachments.iter().zip(&attachment_views.colors) {
+                let view = at.texture_subresource.view(
+                    at.attachment,
+                    attachment_view.as_ref(),
+                    view_guard,
+                    &mut trackers,
+                )?;
                 add_view(view, "color")?;
+                color_views.push(view);
+                let depth_slice_view = match at.depth_slice {
+                    DEPTH_SLICE_UNDEFINED if view.depth_slice_count != 0 => {
+                        return Err(RenderPassErrorInner::MissingDepthSlice);
+                    }
+                    DEPTH_SLICE_UNDEFINED => None,
+                    slice => {
+                        if !device
+                            .features
+                            .contains(wgt::Features::RENDER_ATTACHMENT_3D_SLICES)
+                        {
+                            return Err(RenderPassErrorInner::MissingDeviceFeatures(
+                                wgt::Features::RENDER_ATTACHMENT_3D_SLICES,
+                            ));
+                        }
+                        let source_id = match view.inner {
+                            TextureViewInner::Native { ref source_id, .. }
+                                if slice < view.depth_slice_count =>
+                            {
+                                source_id
+                            }
+                            _ => {
+                                return Err(RenderPassErrorInner::InvalidDepthSlice {
+                                    slice,
+                                    count: view.depth_slice_count,
+                                })
+                            }
+                        };
+                        let raw = device.attachment_raw_view(
+                            &texture_guard[source_id.value],
+                            view.selector.levels.start as u32,
+                            slice,
+                        )?;
+                        Some(raw)
+                    }
+                };
+                depth_slice_views.push(depth_slice_view);
 
                 let layouts = match view.inner {
-                    TextureViewInner::Native { ref source_id, .. } => {
+                    TextureViewInner::Native { ref source_id, .. }
+                    | TextureViewInner::Attachment { ref source_id, .. } => {
                         let previous_use = cmd_buf
                             .trackers
                             .textures
@@ -687,12 +975,16 @@ impl<'a, B: GfxBackend> RenderPassInfo<'a, B> {
                 if view.samples != 1 {
                     return Err(RenderPassErrorInner::InvalidResolveTargetSampleCount);
                 }
+                if view.swizzled {
+                    return Err(RenderPassErrorInner::SwizzledAttachment("resolve"));
+                }
                 if sample_count == 1 {
                     return Err(RenderPassErrorInner::InvalidResolveSourceSampleCount);
                 }
 
                 let layouts = match view.inner {
-                    TextureViewInner::Native { ref source_id, .. } => {
+                    TextureViewInner::Native { ref source_id, .. }
+                    | TextureViewInner::Attachment { ref source_id, .. } => {
                         let previous_use = cmd_buf
                             .trackers
                             .textures
@@ -819,17 +1111,13 @@ impl<'a, B: GfxBackend> RenderPassInfo<'a, B> {
         };
 
         let view_data = AttachmentData {
-            colors: color_attachments
-                .iter()
-                .map(|at| view_guard.get(at.attachment).unwrap())
-                .collect(),
+            colors: color_views,
             resolves: color_attachments
                 .iter()
                 .filter_map(|at| at.resolve_target)
                 .map(|attachment| view_guard.get(attachment).unwrap())
                 .collect(),
-            depth_stencil: depth_stencil_attachment
-                .map(|at| view_guard.get(at.attachment).unwrap()),
+            depth_stencil: depth_stencil_view,
         };
         let extent = extent.ok_or(RenderPassErrorInner::MissingAttachments)?;
         let fb_key = (
@@ -865,10 +1153,16 @@ impl<'a, B: GfxBackend> RenderPassInfo<'a, B> {
             w: extent.width as _,
             h: extent.height as _,
         };
-        let raw_views = view_data.map(|view| match view.inner {
+        let mut raw_views = view_data.map(|view| match view.inner {
             TextureViewInner::Native { ref raw, .. } => raw,
             TextureViewInner::SwapChain { ref image, .. } => Borrow::borrow(image),
+            TextureViewInner::Attachment { ref raw, .. } => &**raw,
         });
+        for (raw_view, depth_slice_view) in raw_views.colors.iter_mut().zip(&depth_slice_views) {
+            if let Some(ref slice_raw) = *depth_slice_view {
+                *raw_view = &**slice_raw;
+            }
+        }
 
         let attachments = color_attachments
             .iter()
@@ -965,7 +1259,8 @@ impl<'a, B: GfxBackend> RenderPassInfo<'a, B> {
             let texture = &texture_guard[ra.texture_id.value];
             check_texture_usage(texture.usage, TextureUsage::RENDER_ATTACHMENT)?;
 
-            // the tracker set of the pass is always in "extend" mode
+            // the tracker set of the pass is always in "extend" mode,
+            // where read-only attachments merge with sampling by bind groups
             self.trackers
                 .textures
                 .change_extend(
@@ -974,7 +1269,12 @@ impl<'a, B: GfxBackend> RenderPassInfo<'a, B> {
                     ra.selector.clone(),
                     ra.new_use,
                 )
-                .unwrap();
+                .map_err(|e| UsageConflict::Texture {
+                    id: e.id.0,
+                    mip_levels: e.selector.levels.start as u32..e.selector.levels.end as u32,
+                    array_layers: e.selector.layers.start as u32..e.selector.layers.end as u32,
+                    combined_use: e.usage.start | e.usage.end,
+                })?;
 
             if let Some(usage) = ra.previous_use {
                 // Make the attachment tracks to be aware of the internal
@@ -1003,6 +1303,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         encoder_id: id::CommandEncoderId,
         pass: &RenderPass,
     ) -> Result<(), RenderPassError> {
+        pass.budget
+            .check()
+            .map_pass_err(PassErrorScope::Pass(encoder_id))?;
         self.command_encoder_run_render_pass_impl::<B>(
             encoder_id,
             pass.base.as_ref(),
@@ -1027,12 +1330,20 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
 
         let (device_guard, mut token) = hub.devices.read(&mut token);
 
-        let (cmd_buf_raw, trackers, used_swapchain, query_reset_state) = {
+        let (cmd_buf_raw, trackers, used_swapchain, query_reset_state, redundant_state) = {
             // read-only lock guard
             let (cmb_guard, mut token) = hub.command_buffers.read(&mut token);
 
             let cmd_buf =
                 CommandBuffer::get_encoder(&*cmb_guard, encoder_id).map_pass_err(scope)?;
+            let bind_group_changes = base
+                .commands
+                .iter()
+                .filter(|command| matches!(command, RenderCommand::SetBindGroup { .. }))
+                .count();
+            cmd_buf
+                .check_pass_limits(base.commands.len(), bind_group_changes)
+                .map_pass_err(scope)?;
             let device = &device_guard[cmd_buf.device_id.value];
             let mut raw = device.cmd_allocator.extend(cmd_buf);
             unsafe {
@@ -1041,6 +1352,11 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     device.raw.set_command_buffer_name(&mut raw, label);
                 }
                 raw.begin_primary(hal::command::CommandBufferFlags::ONE_TIME_SUBMIT);
+                // Not every capture tool shows the command buffer names, but they
+                // all show debug groups, like the ones of compute passes.
+                if let Some(ref label) = base.label {
+                    raw.begin_debug_marker(label, 0);
+                }
             }
 
             let (bundle_guard, mut token) = hub.render_bundles.read(&mut token);
@@ -1057,6 +1373,13 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 encoder_id
             );
 
+            let attachment_views = create_attachment_views(
+                color_attachments,
+                depth_stencil_attachment,
+                device,
+                &*texture_guard,
+            )
+            .map_pass_err(scope)?;
             let mut info = RenderPassInfo::start(
                 &mut raw,
                 color_attachments,
@@ -1064,14 +1387,33 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 cmd_buf,
                 device,
                 &*view_guard,
+                &*texture_guard,
+                &attachment_views,
             )
             .map_pass_err(scope)?;
 
+            // Trusted content may opt out of the per-draw checks in release builds.
+            let validate_draws = cfg!(debug_assertions) || !device.skip_draw_validation;
+
+            // Extents past the range of `Rect` are clamped, like in `SetScissor`.
+            use std::convert::TryFrom;
+            let full_rect = hal::pso::Rect {
+                x: 0,
+                y: 0,
+                w: i16::try_from(info.extent.width).unwrap_or(i16::MAX),
+                h: i16::try_from(info.extent.height).unwrap_or(i16::MAX),
+            };
             let mut state = State {
                 pipeline_flags: PipelineFlags::empty(),
                 binder: Binder::new(cmd_buf.limits.max_bind_groups),
                 blend_color: OptionalState::Unused,
+                blend_constants: [0.0; 4],
                 stencil_reference: 0,
+                viewport: hal::pso::Viewport {
+                    rect: full_rect,
+                    depth: 0.0..1.0,
+                },
+                scissor: full_rect,
                 pipeline: StateChange::new(),
                 index: IndexState::default(),
                 vertex: VertexState::default(),
@@ -1082,6 +1424,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             let mut string_offset = 0;
             let mut active_query = None;
             let mut query_reset_state = QueryResetMap::new();
+            let mut redundant_state = RedundantStateReport::default();
 
             for command in base.commands {
                 match *command {
@@ -1120,6 +1463,13 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             .merge_extend(&bind_group.used)
                             .map_pass_err(scope)?;
 
+                        if state.binder.is_bound(
+                            index as usize,
+                            id::Valid(bind_group_id),
+                            &temp_offsets,
+                        ) {
+                            redundant_state.bind_groups += 1;
+                        }
                         if let Some((pipeline_layout_id, follow_ups)) = state.binder.provide_entry(
                             index as usize,
                             id::Valid(bind_group_id),
@@ -1147,6 +1497,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     RenderCommand::SetPipeline(pipeline_id) => {
                         let scope = PassErrorScope::SetPipelineRender(pipeline_id);
                         if state.pipeline.set_and_check_redundant(pipeline_id) {
+                            redundant_state.pipelines += 1;
                             continue;
                         }
 
@@ -1352,8 +1703,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     }
                     RenderCommand::SetBlendColor(ref color) => {
                         state.blend_color = OptionalState::Set;
+                        state.blend_constants = conv::map_color_f32(color);
                         unsafe {
-                            raw.set_blend_constants(conv::map_color_f32(color));
+                            raw.set_blend_constants(state.blend_constants);
                         }
                     }
                     RenderCommand::SetStencilReference(value) => {
@@ -1389,14 +1741,12 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             w: i16::try_from(rect.w.round() as i64).unwrap_or(i16::MAX),
                             h: i16::try_from(rect.h.round() as i64).unwrap_or(i16::MAX),
                         };
+                        state.viewport = hal::pso::Viewport {
+                            rect: r,
+                            depth: depth_min..depth_max,
+                        };
                         unsafe {
-                            raw.set_viewports(
-                                0,
-                                iter::once(hal::pso::Viewport {
-                                    rect: r,
-                                    depth: depth_min..depth_max,
-                                }),
-                            );
+                            raw.set_viewports(0, iter::once(state.viewport.clone()));
                         }
                     }
                     RenderCommand::SetPushConstant {
@@ -1453,6 +1803,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             w: i16::try_from(rect.w).unwrap_or(i16::MAX),
                             h: i16::try_from(rect.h).unwrap_or(i16::MAX),
                         };
+                        state.scissor = r;
                         unsafe {
                             raw.set_scissors(0, iter::once(r));
                         }
@@ -1468,26 +1819,29 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             indirect: false,
                             pipeline: state.pipeline.last_state,
                         };
-                        state.is_ready().map_pass_err(scope)?;
-                        let last_vertex = first_vertex + vertex_count;
-                        let vertex_limit = state.vertex.vertex_limit;
-                        if last_vertex > vertex_limit {
-                            return Err(DrawError::VertexBeyondLimit {
-                                last_vertex,
-                                vertex_limit,
-                                slot: state.vertex.vertex_limit_slot,
-                            })
-                            .map_pass_err(scope);
-                        }
-                        let last_instance = first_instance + instance_count;
-                        let instance_limit = state.vertex.instance_limit;
-                        if last_instance > instance_limit {
-                            return Err(DrawError::InstanceBeyondLimit {
-                                last_instance,
-                                instance_limit,
-                                slot: state.vertex.instance_limit_slot,
-                            })
-                            .map_pass_err(scope);
+                        state.check_draw_kind(false).map_pass_err(scope)?;
+                        if validate_draws {
+                            state.is_ready().map_pass_err(scope)?;
+                            let last_vertex = first_vertex + vertex_count;
+                            let vertex_limit = state.vertex.vertex_limit;
+                            if last_vertex > vertex_limit {
+                                return Err(DrawError::VertexBeyondLimit {
+                                    last_vertex,
+                                    vertex_limit,
+                                    slot: state.vertex.vertex_limit_slot,
+                                })
+                                .map_pass_err(scope);
+                            }
+                            let last_instance = first_instance + instance_count;
+                            let instance_limit = state.vertex.instance_limit;
+                            if last_instance > instance_limit {
+                                return Err(DrawError::InstanceBeyondLimit {
+                                    last_instance,
+                                    instance_limit,
+                                    slot: state.vertex.instance_limit_slot,
+                                })
+                                .map_pass_err(scope);
+                            }
                         }
 
                         unsafe {
@@ -1509,27 +1863,30 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             indirect: false,
                             pipeline: state.pipeline.last_state,
                         };
-                        state.is_ready().map_pass_err(scope)?;
-
-                        //TODO: validate that base_vertex + max_index() is within the provided range
-                        let last_index = first_index + index_count;
-                        let index_limit = state.index.limit;
-                        if last_index > index_limit {
-                            return Err(DrawError::IndexBeyondLimit {
-                                last_index,
-                                index_limit,
-                            })
-                            .map_pass_err(scope);
-                        }
-                        let last_instance = first_instance + instance_count;
-                        let instance_limit = state.vertex.instance_limit;
-                        if last_instance > instance_limit {
-                            return Err(DrawError::InstanceBeyondLimit {
-                                last_instance,
-                                instance_limit,
-                                slot: state.vertex.instance_limit_slot,
-                            })
-                            .map_pass_err(scope);
+                        state.check_draw_kind(false).map_pass_err(scope)?;
+                        if validate_draws {
+                            state.is_ready().map_pass_err(scope)?;
+
+                            //TODO: validate that base_vertex + max_index() is within the provided range
+                            let last_index = first_index + index_count;
+                            let index_limit = state.index.limit;
+                            if last_index > index_limit {
+                                return Err(DrawError::IndexBeyondLimit {
+                                    last_index,
+                                    index_limit,
+                                })
+                                .map_pass_err(scope);
+                            }
+                            let last_instance = first_instance + instance_count;
+                            let instance_limit = state.vertex.instance_limit;
+                            if last_instance > instance_limit {
+                                return Err(DrawError::InstanceBeyondLimit {
+                                    last_instance,
+                                    instance_limit,
+                                    slot: state.vertex.instance_limit_slot,
+                                })
+                                .map_pass_err(scope);
+                            }
                         }
 
                         unsafe {
@@ -1551,7 +1908,10 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             indirect: true,
                             pipeline: state.pipeline.last_state,
                         };
-                        state.is_ready().map_pass_err(scope)?;
+                        state.check_draw_kind(false).map_pass_err(scope)?;
+                        if validate_draws {
+                            state.is_ready().map_pass_err(scope)?;
+                        }
 
                         let stride = match indexed {
                             false => 16,
@@ -1627,7 +1987,10 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             indirect: true,
                             pipeline: state.pipeline.last_state,
                         };
-                        state.is_ready().map_pass_err(scope)?;
+                        state.check_draw_kind(false).map_pass_err(scope)?;
+                        if validate_draws {
+                            state.is_ready().map_pass_err(scope)?;
+                        }
 
                         let stride = match indexed {
                             false => 16,
@@ -1715,6 +2078,90 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             },
                         }
                     }
+                    RenderCommand::DrawMeshTasks {
+                        task_count,
+                        first_task,
+                    } => {
+                        let scope = PassErrorScope::DrawMeshTasks {
+                            indirect: false,
+                            pipeline: state.pipeline.last_state,
+                        };
+                        check_device_features(device.features, wgt::Features::MESH_SHADER)
+                            .map_pass_err(scope)?;
+                        state.check_draw_kind(true).map_pass_err(scope)?;
+                        if validate_draws {
+                            state.is_ready().map_pass_err(scope)?;
+                        }
+
+                        unsafe {
+                            raw.draw_mesh_tasks(task_count, first_task);
+                        }
+                    }
+                    RenderCommand::MultiDrawMeshTasksIndirect {
+                        buffer_id,
+                        offset,
+                        count,
+                    } => {
+                        let scope = PassErrorScope::DrawMeshTasks {
+                            indirect: true,
+                            pipeline: state.pipeline.last_state,
+                        };
+                        check_device_features(device.features, wgt::Features::MESH_SHADER)
+                            .map_pass_err(scope)?;
+                        state.check_draw_kind(true).map_pass_err(scope)?;
+                        if validate_draws {
+                            state.is_ready().map_pass_err(scope)?;
+                        }
+
+                        // The arguments are `task_count` and `first_task`.
+                        let stride = 8;
+
+                        if count.is_some() {
+                            check_device_features(
+                                device.features,
+                                wgt::Features::MULTI_DRAW_INDIRECT,
+                            )
+                            .map_pass_err(scope)?;
+                        }
+
+                        let indirect_buffer = info
+                            .trackers
+                            .buffers
+                            .use_extend(&*buffer_guard, buffer_id, (), BufferUse::INDIRECT)
+                            .map_err(|e| RenderCommandError::Buffer(buffer_id, e))
+                            .map_pass_err(scope)?;
+                        check_buffer_usage(indirect_buffer.usage, BufferUsage::INDIRECT)
+                            .map_pass_err(scope)?;
+                        let &(ref indirect_raw, _) = indirect_buffer
+                            .raw
+                            .as_ref()
+                            .ok_or(RenderCommandError::DestroyedBuffer(buffer_id))
+                            .map_pass_err(scope)?;
+
+                        let actual_count = count.map_or(1, |c| c.get());
+
+                        let begin_offset = offset;
+                        let end_offset = offset + stride * actual_count as u64;
+                        if end_offset > indirect_buffer.size {
+                            return Err(RenderPassErrorInner::IndirectBufferOverrun {
+                                offset,
+                                count,
+                                begin_offset,
+                                end_offset,
+                                buffer_size: indirect_buffer.size,
+                            })
+                            .map_pass_err(scope);
+                        }
+
+                        unsafe {
+                            raw.draw_mesh_tasks_indirect(
+                                indirect_raw,
+                                offset,
+                                actual_count,
+                                stride as u32,
+                            );
+                        }
+                    }
                     RenderCommand::PushDebugGroup { color, len } => {
                         state.debug_scope_depth += 1;
                         let label =
@@ -1831,6 +2278,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                                 &*bind_group_guard,
                                 &*pipeline_guard,
                                 &*buffer_guard,
+                                state.stencil_reference,
                             )
                         }
                         .map_err(|e| match e {
@@ -1839,6 +2287,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             }
                         })
                         .map_pass_err(scope)?;
+                        unsafe {
+                            state.restore_dynamic_state::<B>(&mut raw);
+                        }
 
                         info.trackers
                             .merge_extend(&bundle.used)
@@ -1848,13 +2299,30 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 }
             }
 
+            // Debug groups can't cross the render pass boundaries on Vulkan.
+            if state.debug_scope_depth != 0 {
+                return Err(RenderPassErrorInner::UnbalancedDebugGroups(
+                    state.debug_scope_depth,
+                ))
+                .map_pass_err(scope);
+            }
+
             tracing::trace!("Merging {:?} with the render pass", encoder_id);
             unsafe {
                 raw.end_render_pass();
+                if base.label.is_some() {
+                    raw.end_debug_marker();
+                }
             }
 
             let (trackers, used_swapchain) = info.finish(&*texture_guard).map_pass_err(scope)?;
-            (raw, trackers, used_swapchain, query_reset_state)
+            (
+                raw,
+                trackers,
+                used_swapchain,
+                query_reset_state,
+                redundant_state,
+            )
         };
 
         let (mut cmb_guard, mut token) = hub.command_buffers.write(&mut token);
@@ -1864,6 +2332,8 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         let cmd_buf =
             CommandBuffer::get_encoder_mut(&mut *cmb_guard, encoder_id).map_pass_err(scope)?;
         cmd_buf.has_labels |= base.label.is_some();
+        cmd_buf.redundant_state.merge(&redundant_state);
+        cmd_buf.recorded_pass_commands += base.commands.len() as u64;
         cmd_buf.used_swap_chains.extend(used_swapchain);
 
         #[cfg(feature = "trace")]
@@ -1926,6 +2396,9 @@ pub mod render_ffi {
         offset_length: usize,
     ) {
         span!(_guard, DEBUG, "RenderPass::set_bind_group");
+        if !pass.budget.admit_bind_group(pass.base.commands.len()) {
+            return;
+        }
         pass.base.commands.push(RenderCommand::SetBindGroup {
             index: index.try_into().unwrap(),
             num_dynamic_offsets: offset_length.try_into().unwrap(),
@@ -1944,6 +2417,9 @@ pub mod render_ffi {
         pipeline_id: id::RenderPipelineId,
     ) {
         span!(_guard, DEBUG, "RenderPass::set_pipeline");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         pass.base
             .commands
             .push(RenderCommand::SetPipeline(pipeline_id));
@@ -1958,6 +2434,9 @@ pub mod render_ffi {
         size: Option<BufferSize>,
     ) {
         span!(_guard, DEBUG, "RenderPass::set_vertex_buffer");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         pass.base.commands.push(RenderCommand::SetVertexBuffer {
             slot,
             buffer_id,
@@ -1969,6 +2448,9 @@ pub mod render_ffi {
     #[no_mangle]
     pub extern "C" fn wgpu_render_pass_set_blend_color(pass: &mut RenderPass, color: &Color) {
         span!(_guard, DEBUG, "RenderPass::set_blend_color");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         pass.base
             .commands
             .push(RenderCommand::SetBlendColor(*color));
@@ -1977,6 +2459,9 @@ pub mod render_ffi {
     #[no_mangle]
     pub extern "C" fn wgpu_render_pass_set_stencil_reference(pass: &mut RenderPass, value: u32) {
         span!(_guard, DEBUG, "RenderPass::set_stencil_buffer");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         pass.base
             .commands
             .push(RenderCommand::SetStencilReference(value));
@@ -1993,6 +2478,9 @@ pub mod render_ffi {
         depth_max: f32,
     ) {
         span!(_guard, DEBUG, "RenderPass::set_viewport");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         pass.base.commands.push(RenderCommand::SetViewport {
             rect: Rect { x, y, w, h },
             depth_min,
@@ -2009,6 +2497,9 @@ pub mod render_ffi {
         h: u32,
     ) {
         span!(_guard, DEBUG, "RenderPass::set_scissor_rect");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         pass.base
             .commands
             .push(RenderCommand::SetScissor(Rect { x, y, w, h }));
@@ -2023,6 +2514,9 @@ pub mod render_ffi {
         data: *const u8,
     ) {
         span!(_guard, DEBUG, "RenderPass::set_push_constants");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         assert_eq!(
             offset & (wgt::PUSH_CONSTANT_ALIGNMENT - 1),
             0,
@@ -2061,6 +2555,9 @@ pub mod render_ffi {
         first_instance: u32,
     ) {
         span!(_guard, DEBUG, "RenderPass::draw");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         pass.base.commands.push(RenderCommand::Draw {
             vertex_count,
             instance_count,
@@ -2079,6 +2576,9 @@ pub mod render_ffi {
         first_instance: u32,
     ) {
         span!(_guard, DEBUG, "RenderPass::draw_indexed");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         pass.base.commands.push(RenderCommand::DrawIndexed {
             index_count,
             instance_count,
@@ -2095,6 +2595,9 @@ pub mod render_ffi {
         offset: BufferAddress,
     ) {
         span!(_guard, DEBUG, "RenderPass::draw_indirect");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         pass.base.commands.push(RenderCommand::MultiDrawIndirect {
             buffer_id,
             offset,
@@ -2110,6 +2613,9 @@ pub mod render_ffi {
         offset: BufferAddress,
     ) {
         span!(_guard, DEBUG, "RenderPass::draw_indexed_indirect");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         pass.base.commands.push(RenderCommand::MultiDrawIndirect {
             buffer_id,
             offset,
@@ -2126,6 +2632,9 @@ pub mod render_ffi {
         count: u32,
     ) {
         span!(_guard, DEBUG, "RenderPass::multi_draw_indirect");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         pass.base.commands.push(RenderCommand::MultiDrawIndirect {
             buffer_id,
             offset,
@@ -2142,6 +2651,9 @@ pub mod render_ffi {
         count: u32,
     ) {
         span!(_guard, DEBUG, "RenderPass::multi_draw_indexed_indirect");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         pass.base.commands.push(RenderCommand::MultiDrawIndirect {
             buffer_id,
             offset,
@@ -2160,6 +2672,9 @@ pub mod render_ffi {
         max_count: u32,
     ) {
         span!(_guard, DEBUG, "RenderPass::multi_draw_indirect_count");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         pass.base
             .commands
             .push(RenderCommand::MultiDrawIndirectCount {
@@ -2186,6 +2701,9 @@ pub mod render_ffi {
             DEBUG,
             "RenderPass::multi_draw_indexed_indirect_count"
         );
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         pass.base
             .commands
             .push(RenderCommand::MultiDrawIndirectCount {
@@ -2198,6 +2716,61 @@ pub mod render_ffi {
             });
     }
 
+    #[no_mangle]
+    pub extern "C" fn wgpu_render_pass_draw_mesh_tasks(
+        pass: &mut RenderPass,
+        task_count: u32,
+        first_task: u32,
+    ) {
+        span!(_guard, DEBUG, "RenderPass::draw_mesh_tasks");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
+        pass.base.commands.push(RenderCommand::DrawMeshTasks {
+            task_count,
+            first_task,
+        });
+    }
+
+    #[no_mangle]
+    pub extern "C" fn wgpu_render_pass_draw_mesh_tasks_indirect(
+        pass: &mut RenderPass,
+        buffer_id: id::BufferId,
+        offset: BufferAddress,
+    ) {
+        span!(_guard, DEBUG, "RenderPass::draw_mesh_tasks_indirect");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
+        pass.base
+            .commands
+            .push(RenderCommand::MultiDrawMeshTasksIndirect {
+                buffer_id,
+                offset,
+                count: None,
+            });
+    }
+
+    #[no_mangle]
+    pub extern "C" fn wgpu_render_pass_multi_draw_mesh_tasks_indirect(
+        pass: &mut RenderPass,
+        buffer_id: id::BufferId,
+        offset: BufferAddress,
+        count: u32,
+    ) {
+        span!(_guard, DEBUG, "RenderPass::multi_draw_mesh_tasks_indirect");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
+        pass.base
+            .commands
+            .push(RenderCommand::MultiDrawMeshTasksIndirect {
+                buffer_id,
+                offset,
+                count: NonZeroU32::new(count),
+            });
+    }
+
     #[no_mangle]
     pub unsafe extern "C" fn wgpu_render_pass_push_debug_group(
         pass: &mut RenderPass,
@@ -2205,6 +2778,9 @@ pub mod render_ffi {
         color: u32,
     ) {
         span!(_guard, DEBUG, "RenderPass::push_debug_group");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         let bytes = ffi::CStr::from_ptr(label).to_bytes();
         pass.base.string_data.extend_from_slice(bytes);
 
@@ -2217,6 +2793,9 @@ pub mod render_ffi {
     #[no_mangle]
     pub extern "C" fn wgpu_render_pass_pop_debug_group(pass: &mut RenderPass) {
         span!(_guard, DEBUG, "RenderPass::pop_debug_group");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         pass.base.commands.push(RenderCommand::PopDebugGroup);
     }
 
@@ -2227,6 +2806,9 @@ pub mod render_ffi {
         color: u32,
     ) {
         span!(_guard, DEBUG, "RenderPass::insert_debug_marker");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
         let bytes = ffi::CStr::from_ptr(label).to_bytes();
         pass.base.string_data.extend_from_slice(bytes);
 
@@ -2243,6 +2825,9 @@ pub mod render_ffi {
         query_index: u32,
     ) {
         span!(_guard, DEBUG, "RenderPass::write_timestamp");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
 
         pass.base.commands.push(RenderCommand::WriteTimestamp {
             query_set_id,
@@ -2257,6 +2842,9 @@ pub mod render_ffi {
         query_index: u32,
     ) {
         span!(_guard, DEBUG, "RenderPass::begin_pipeline_statistics query");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
 
         pass.base
             .commands
@@ -2269,6 +2857,9 @@ pub mod render_ffi {
     #[no_mangle]
     pub unsafe extern "C" fn wgpu_render_pass_end_pipeline_statistics_query(pass: &mut RenderPass) {
         span!(_guard, DEBUG, "RenderPass::end_pipeline_statistics_query");
+        if !pass.budget.admit(pass.base.commands.len()) {
+            return;
+        }
 
         pass.base
             .commands
@@ -2283,6 +2874,9 @@ pub mod render_ffi {
     ) {
         span!(_guard, DEBUG, "RenderPass::execute_bundles");
         for &bundle_id in slice::from_raw_parts(render_bundle_ids, render_bundle_ids_length) {
+            if !pass.budget.admit(pass.base.commands.len()) {
+                return;
+            }
             pass.base
                 .commands
                 .push(RenderCommand::ExecuteBundle(bundle_id));
@@ -13,6 +13,7 @@ use crate::{
     resource::{BufferUse, Texture, TextureErrorDimension, TextureUse},
     span,
     track::TextureSelector,
+    PrivateFeatures,
 };
 
 use hal::command::CommandBuffer as _;
@@ -72,7 +73,7 @@ pub enum TransferError {
     UnalignedCopyOriginX,
     #[error("copy origin's y component is not a multiple of block height")]
     UnalignedCopyOriginY,
-    #[error("bytes per row does not respect `COPY_BYTES_PER_ROW_ALIGNMENT`")]
+    #[error("bytes per row does not respect the copy alignment")]
     UnalignedBytesPerRow,
     #[error("number of rows per image is not a multiple of block height")]
     UnalignedRowsPerImage,
@@ -84,10 +85,18 @@ pub enum TransferError {
     InvalidRowsPerImage,
     #[error("source and destination layers have different aspects")]
     MismatchedAspects,
+    #[error("texture {0:?} doesn't have the {1:?} aspect")]
+    MissingTextureAspect(TextureId, wgt::TextureAspect),
     #[error("copying from textures with format {0:?} is forbidden")]
     CopyFromForbiddenTextureFormat(wgt::TextureFormat),
     #[error("copying to textures with format {0:?} is forbidden")]
     CopyToForbiddenTextureFormat(wgt::TextureFormat),
+    #[error("mipmaps can't be generated for textures with format {0:?}")]
+    UnsupportedMipmapFormat(wgt::TextureFormat),
+    #[error("mipmaps can't be generated for texture {0:?} with sample count {1}")]
+    MultisampledMipmaps(TextureId, u32),
+    #[error("copying between textures with formats {0:?} and {1:?} is forbidden")]
+    MismatchedTextureFormats(wgt::TextureFormat, wgt::TextureFormat),
 }
 
 /// Error encountered while attempting to do a copy on a command encoder.
@@ -117,6 +126,18 @@ pub(crate) fn texture_copy_view_to_hal<B: hal::Backend>(
         .get(view.texture)
         .map_err(|_| TransferError::InvalidTexture(view.texture))?;
 
+    let aspects = match view.aspect {
+        wgt::TextureAspect::All => texture.aspects,
+        wgt::TextureAspect::DepthOnly => hal::format::Aspects::DEPTH,
+        wgt::TextureAspect::StencilOnly => hal::format::Aspects::STENCIL,
+    };
+    if !texture.aspects.contains(aspects) {
+        return Err(TransferError::MissingTextureAspect(
+            view.texture,
+            view.aspect,
+        ));
+    }
+
     let level = view.mip_level as hal::image::Level;
     let (layer, layer_count, z) = match texture.dimension {
         wgt::TextureDimension::D1 | wgt::TextureDimension::D2 => (
@@ -132,7 +153,7 @@ pub(crate) fn texture_copy_view_to_hal<B: hal::Backend>(
     #[allow(clippy::range_plus_one)]
     Ok((
         hal::image::SubresourceLayers {
-            aspects: texture.aspects,
+            aspects,
             level,
             layers: layer..layer + layer_count,
         },
@@ -148,6 +169,38 @@ pub(crate) fn texture_copy_view_to_hal<B: hal::Backend>(
     ))
 }
 
+/// Returns the size of a texel block of the selected texture aspect in a buffer.
+pub(crate) fn copy_bytes_per_block(
+    format: wgt::TextureFormat,
+    aspect: wgt::TextureAspect,
+    private_features: PrivateFeatures,
+) -> u32 {
+    match aspect {
+        // Stencil is always tightly packed as 8 bits per texel in buffers.
+        wgt::TextureAspect::StencilOnly => 1,
+        _ => {
+            conv::map_texture_format(format, private_features)
+                .surface_desc()
+                .bits as u32
+                / BITS_PER_BYTE
+        }
+    }
+}
+
+/// Returns the alignment of `bytes_per_row` in buffer <-> texture copies.
+fn bytes_per_row_alignment(
+    features: wgt::Features,
+    private_features: PrivateFeatures,
+    bytes_per_block: u32,
+) -> u32 {
+    if features.contains(wgt::Features::ADAPTER_SPECIFIC_COPY_ALIGNMENT) {
+        // Both are powers of two, so the larger one is a multiple of the other.
+        private_features.copy_pitch_alignment.max(bytes_per_block)
+    } else {
+        wgt::COPY_BYTES_PER_ROW_ALIGNMENT
+    }
+}
+
 /// Function copied with minor modifications from webgpu standard https://gpuweb.github.io/gpuweb/#valid-texture-copy-range
 pub(crate) fn validate_linear_texture_data(
     layout: &wgt::TextureDataLayout,
@@ -484,11 +537,13 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         }
         let dst_barriers = dst_pending.map(|pending| pending.into_hal(dst_texture));
 
-        let bytes_per_row_alignment = wgt::COPY_BYTES_PER_ROW_ALIGNMENT;
-        let bytes_per_block = conv::map_texture_format(dst_texture.format, cmd_buf.private_features)
-            .surface_desc()
-            .bits as u32
-            / BITS_PER_BYTE;
+        let bytes_per_block = copy_bytes_per_block(
+            dst_texture.format,
+            destination.aspect,
+            cmd_buf.private_features,
+        );
+        let bytes_per_row_alignment =
+            bytes_per_row_alignment(cmd_buf.features, cmd_buf.private_features, bytes_per_block);
         let src_bytes_per_row = source.layout.bytes_per_row;
         if bytes_per_row_alignment % bytes_per_block != 0 {
             Err(TransferError::UnalignedBytesPerRow)?
@@ -513,7 +568,11 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         )?;
 
         let (block_width, _) = dst_texture.format.describe().block_dimensions;
-        if !conv::is_valid_copy_dst_texture_format(dst_texture.format) {
+        if !conv::is_valid_copy_dst_texture_format(
+            dst_texture.format,
+            destination.aspect,
+            cmd_buf.features,
+        ) {
             Err(TransferError::CopyToForbiddenTextureFormat(
                 dst_texture.format,
             ))?
@@ -624,11 +683,10 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         }
         let dst_barrier = dst_barriers.map(|pending| pending.into_hal(dst_buffer));
 
-        let bytes_per_row_alignment = wgt::COPY_BYTES_PER_ROW_ALIGNMENT;
-        let bytes_per_block = conv::map_texture_format(src_texture.format, cmd_buf.private_features)
-            .surface_desc()
-            .bits as u32
-            / BITS_PER_BYTE;
+        let bytes_per_block =
+            copy_bytes_per_block(src_texture.format, source.aspect, cmd_buf.private_features);
+        let bytes_per_row_alignment =
+            bytes_per_row_alignment(cmd_buf.features, cmd_buf.private_features, bytes_per_block);
         let dst_bytes_per_row = destination.layout.bytes_per_row;
         if bytes_per_row_alignment % bytes_per_block != 0 {
             Err(TransferError::UnalignedBytesPerRow)?
@@ -653,7 +711,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         )?;
 
         let (block_width, _) = src_texture.format.describe().block_dimensions;
-        if !conv::is_valid_copy_src_texture_format(src_texture.format) {
+        if !conv::is_valid_copy_src_texture_format(src_texture.format, source.aspect) {
             Err(TransferError::CopyFromForbiddenTextureFormat(
                 src_texture.format,
             ))?
@@ -697,6 +755,11 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         Ok(())
     }
 
+    /// Copy a region between two textures.
+    ///
+    /// The formats don't have to be identical, see `conv::is_copy_compatible_texture_format`.
+    /// When copying between a compressed and an uncompressed format, every block
+    /// maps to a single texel, and `copy_size` is expressed in texels of the source.
     pub fn command_encoder_copy_texture_to_texture<B: GfxBackend>(
         &self,
         command_encoder_id: CommandEncoderId,
@@ -786,12 +849,27 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             CopySide::Source,
             copy_size,
         )?;
+        if !conv::is_copy_compatible_texture_format(src_texture.format, dst_texture.format) {
+            Err(TransferError::MismatchedTextureFormats(
+                src_texture.format,
+                dst_texture.format,
+            ))?
+        }
+        let (src_block_width, src_block_height) = src_texture.format.describe().block_dimensions;
+        let (dst_block_width, dst_block_height) = dst_texture.format.describe().block_dimensions;
+        let (src_block_width, src_block_height) = (src_block_width as u32, src_block_height as u32);
+        let (dst_block_width, dst_block_height) = (dst_block_width as u32, dst_block_height as u32);
+        let dst_copy_size = Extent3d {
+            width: copy_size.width / src_block_width * dst_block_width,
+            height: copy_size.height / src_block_height * dst_block_height,
+            depth: copy_size.depth,
+        };
         validate_texture_copy_range(
             destination,
             dst_texture.format,
             dst_texture.kind,
             CopySide::Destination,
-            copy_size,
+            &dst_copy_size,
         )?;
 
         // WebGPU uses the physical size of the texture for copies whereas vulkan uses
@@ -800,13 +878,20 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         // the virtual size.
         let max_src_image_extent = src_texture.kind.level_extent(source.mip_level as _);
         let max_dst_image_extent = dst_texture.kind.level_extent(destination.mip_level as _);
+        // The extent is in texels of the source, so the destination one has to be
+        // converted into them for the compressed <-> uncompressed copies.
+        let max_dst_width =
+            (max_dst_image_extent.width * src_block_width + dst_block_width - 1) / dst_block_width;
+        let max_dst_height = (max_dst_image_extent.height * src_block_height + dst_block_height
+            - 1)
+            / dst_block_height;
         let image_extent = Extent3d {
             width: copy_size
                 .width
-                .min(max_src_image_extent.width.min(max_dst_image_extent.width)),
+                .min(max_src_image_extent.width.min(max_dst_width)),
             height: copy_size
                 .height
-                .min(max_src_image_extent.height.min(max_dst_image_extent.height)),
+                .min(max_src_image_extent.height.min(max_dst_height)),
             depth: copy_size.depth,
         };
 
@@ -834,4 +919,142 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         }
         Ok(())
     }
+
+    /// Fill all the mip levels of a texture from its first one.
+    ///
+    /// Each level is a linearly filtered blit of the previous one, so the texture
+    /// needs the `COPY_SRC` and `COPY_DST` usages, and a filterable color format.
+    pub fn command_encoder_generate_mipmaps<B: GfxBackend>(
+        &self,
+        command_encoder_id: CommandEncoderId,
+        texture_id: TextureId,
+    ) -> Result<(), CopyError> {
+        span!(_guard, INFO, "CommandEncoder::generate_mipmaps");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+
+        let (mut cmd_buf_guard, mut token) = hub.command_buffers.write(&mut token);
+        let cmd_buf = CommandBuffer::get_encoder_mut(&mut *cmd_buf_guard, command_encoder_id)?;
+        let (_, mut token) = hub.buffers.read(&mut token); // skip token
+        let (texture_guard, _) = hub.textures.read(&mut token);
+
+        #[cfg(feature = "trace")]
+        if let Some(ref mut list) = cmd_buf.commands {
+            list.push(TraceCommand::GenerateMipmaps(texture_id));
+        }
+
+        let texture = texture_guard
+            .get(texture_id)
+            .map_err(|_| TransferError::InvalidTexture(texture_id))?;
+        let &(ref raw, _) = texture
+            .raw
+            .as_ref()
+            .ok_or(TransferError::InvalidTexture(texture_id))?;
+        if !texture.usage.contains(TextureUsage::COPY_SRC) {
+            Err(TransferError::MissingCopySrcUsageFlag)?
+        }
+        if !texture.usage.contains(TextureUsage::COPY_DST) {
+            Err(TransferError::MissingCopyDstUsageFlag(
+                None,
+                Some(texture_id),
+            ))?
+        }
+        let sample_count = texture.kind.num_samples() as u32;
+        if sample_count != 1 {
+            Err(TransferError::MultisampledMipmaps(texture_id, sample_count))?
+        }
+        // Levels are produced with linear-filtered blits, so depth/stencil,
+        // integer and compressed formats are out.
+        let format_desc = texture.format.describe();
+        if texture.aspects != hal::format::Aspects::COLOR
+            || format_desc.block_dimensions != (1, 1)
+            || !matches!(
+                format_desc.sample_type,
+                wgt::TextureSampleType::Float { filterable: true }
+            )
+            || !texture
+                .format_features
+                .flags
+                .contains(wgt::TextureFormatFeatureFlags::FILTERABLE)
+        {
+            Err(TransferError::UnsupportedMipmapFormat(texture.format))?
+        }
+
+        let levels = texture.full_range.levels.clone();
+        let layers = texture.full_range.layers.clone();
+        let cmd_buf_raw = cmd_buf.raw.last_mut().unwrap();
+        for level in levels.start + 1..levels.end {
+            let mut barriers = Vec::new();
+            let (_, src_pending) = cmd_buf
+                .trackers
+                .textures
+                .use_replace(
+                    &*texture_guard,
+                    texture_id,
+                    TextureSelector {
+                        levels: level - 1..level,
+                        layers: layers.clone(),
+                    },
+                    TextureUse::COPY_SRC,
+                )
+                .map_err(TransferError::InvalidTexture)?;
+            barriers.extend(src_pending.map(|pending| pending.into_hal(texture)));
+            let (_, dst_pending) = cmd_buf
+                .trackers
+                .textures
+                .use_replace(
+                    &*texture_guard,
+                    texture_id,
+                    TextureSelector {
+                        levels: level..level + 1,
+                        layers: layers.clone(),
+                    },
+                    TextureUse::COPY_DST,
+                )
+                .map_err(TransferError::InvalidTexture)?;
+            barriers.extend(dst_pending.map(|pending| pending.into_hal(texture)));
+
+            let src_extent = texture.kind.level_extent(level - 1);
+            let dst_extent = texture.kind.level_extent(level);
+            let region = hal::command::ImageBlit {
+                src_subresource: hal::image::SubresourceLayers {
+                    aspects: texture.aspects,
+                    level: level - 1,
+                    layers: layers.clone(),
+                },
+                src_bounds: hal::image::Offset::ZERO..hal::image::Offset {
+                    x: src_extent.width as i32,
+                    y: src_extent.height as i32,
+                    z: src_extent.depth as i32,
+                },
+                dst_subresource: hal::image::SubresourceLayers {
+                    aspects: texture.aspects,
+                    level,
+                    layers: layers.clone(),
+                },
+                dst_bounds: hal::image::Offset::ZERO..hal::image::Offset {
+                    x: dst_extent.width as i32,
+                    y: dst_extent.height as i32,
+                    z: dst_extent.depth as i32,
+                },
+            };
+            unsafe {
+                cmd_buf_raw.pipeline_barrier(
+                    all_image_stages()..hal::pso::PipelineStage::TRANSFER,
+                    hal::memory::Dependencies::empty(),
+                    barriers,
+                );
+                cmd_buf_raw.blit_image(
+                    raw,
+                    hal::image::Layout::TransferSrcOptimal,
+                    raw,
+                    hal::image::Layout::TransferDstOptimal,
+                    hal::image::Filter::Linear,
+                    iter::once(region),
+                );
+            }
+        }
+        Ok(())
+    }
 }
@@ -27,6 +27,9 @@ pub fn map_adapter_info(
             Dt::Cpu => wgt::DeviceType::Cpu,
         },
         backend,
+        //TODO: gfx-hal doesn't report driver details yet
+        driver: None,
+        driver_info: None,
     }
 }
 
@@ -151,6 +154,12 @@ pub fn map_shader_stage_flags(shader_stage_flags: wgt::ShaderStage) -> hal::pso:
     if shader_stage_flags.contains(Ss::COMPUTE) {
         value |= H::COMPUTE;
     }
+    if shader_stage_flags.contains(Ss::TASK) {
+        value |= H::TASK;
+    }
+    if shader_stage_flags.contains(Ss::MESH) {
+        value |= H::MESH;
+    }
     value
 }
 
@@ -503,22 +512,85 @@ pub fn is_power_of_two(val: u32) -> bool {
     val != 0 && (val & (val - 1)) == 0
 }
 
-pub fn is_valid_copy_src_texture_format(format: wgt::TextureFormat) -> bool {
-    use wgt::TextureFormat as Tf;
-    match format {
-        Tf::Depth24Plus | Tf::Depth24PlusStencil8 => false,
+pub fn is_valid_copy_src_texture_format(
+    format: wgt::TextureFormat,
+    aspect: wgt::TextureAspect,
+) -> bool {
+    use wgt::{TextureAspect as Ta, TextureFormat as Tf};
+    match (format, aspect) {
+        // The layout of the depth data isn't specified for these formats.
+        (Tf::Depth24Plus, _) | (Tf::Depth24PlusStencil8, Ta::All) => false,
+        (Tf::Depth24PlusStencil8, Ta::DepthOnly) => false,
         _ => true,
     }
 }
 
-pub fn is_valid_copy_dst_texture_format(format: wgt::TextureFormat) -> bool {
-    use wgt::TextureFormat as Tf;
-    match format {
-        Tf::Depth32Float | Tf::Depth24Plus | Tf::Depth24PlusStencil8 => false,
+pub fn is_valid_copy_dst_texture_format(
+    format: wgt::TextureFormat,
+    aspect: wgt::TextureAspect,
+    features: wgt::Features,
+) -> bool {
+    use wgt::{TextureAspect as Ta, TextureFormat as Tf};
+    match (format, aspect) {
+        (Tf::Depth32Float, _) | (Tf::Depth24PlusStencil8, Ta::StencilOnly) => {
+            features.contains(wgt::Features::DEPTH_TEXTURE_COPY_DST)
+        }
+        (Tf::Depth24Plus, _) | (Tf::Depth24PlusStencil8, _) => false,
         _ => true,
     }
 }
 
+/// Returns the linear counterpart of an sRGB format, or the format itself.
+fn remove_srgb_suffix(format: wgt::TextureFormat) -> wgt::TextureFormat {
+    use wgt::TextureFormat as Tf;
+    match format {
+        Tf::Rgba8UnormSrgb => Tf::Rgba8Unorm,
+        Tf::Bgra8UnormSrgb => Tf::Bgra8Unorm,
+        Tf::Bc1RgbaUnormSrgb => Tf::Bc1RgbaUnorm,
+        Tf::Bc2RgbaUnormSrgb => Tf::Bc2RgbaUnorm,
+        Tf::Bc3RgbaUnormSrgb => Tf::Bc3RgbaUnorm,
+        Tf::Bc7RgbaUnormSrgb => Tf::Bc7RgbaUnorm,
+        Tf::Etc2RgbUnormSrgb => Tf::Etc2RgbUnorm,
+        Tf::Etc2RgbA1UnormSrgb => Tf::Etc2RgbA1Unorm,
+        Tf::Etc2RgbA8UnormSrgb => Tf::Etc2RgbA8Unorm,
+        Tf::Astc4x4RgbaUnormSrgb => Tf::Astc4x4RgbaUnorm,
+        Tf::Astc5x4RgbaUnormSrgb => Tf::Astc5x4RgbaUnorm,
+        Tf::Astc5x5RgbaUnormSrgb => Tf::Astc5x5RgbaUnorm,
+        Tf::Astc6x5RgbaUnormSrgb => Tf::Astc6x5RgbaUnorm,
+        Tf::Astc6x6RgbaUnormSrgb => Tf::Astc6x6RgbaUnorm,
+        Tf::Astc8x5RgbaUnormSrgb => Tf::Astc8x5RgbaUnorm,
+        Tf::Astc8x6RgbaUnormSrgb => Tf::Astc8x6RgbaUnorm,
+        Tf::Astc10x5RgbaUnormSrgb => Tf::Astc10x5RgbaUnorm,
+        Tf::Astc10x6RgbaUnormSrgb => Tf::Astc10x6RgbaUnorm,
+        Tf::Astc8x8RgbaUnormSrgb => Tf::Astc8x8RgbaUnorm,
+        Tf::Astc10x8RgbaUnormSrgb => Tf::Astc10x8RgbaUnorm,
+        Tf::Astc10x10RgbaUnormSrgb => Tf::Astc10x10RgbaUnorm,
+        Tf::Astc12x10RgbaUnormSrgb => Tf::Astc12x10RgbaUnorm,
+        Tf::Astc12x12RgbaUnormSrgb => Tf::Astc12x12RgbaUnorm,
+        other => other,
+    }
+}
+
+/// Checks if a texture of `src` format can be copied into a texture of `dst` format.
+///
+/// Besides identical formats, this allows copies between the sRGB and linear variants
+/// of a format, and between compressed and unsigned integer formats of the same block size,
+/// all of which Vulkan, D3D12, and Metal permit.
+pub fn is_copy_compatible_texture_format(src: wgt::TextureFormat, dst: wgt::TextureFormat) -> bool {
+    if remove_srgb_suffix(src) == remove_srgb_suffix(dst) {
+        return true;
+    }
+    let src_info = src.describe();
+    let dst_info = dst.describe();
+    let is_compressed = |info: &wgt::TextureFormatInfo| info.block_dimensions != (1, 1);
+    let is_uint = |info: &wgt::TextureFormatInfo| {
+        !is_compressed(info) && info.sample_type == wgt::TextureSampleType::Uint
+    };
+    src_info.block_size == dst_info.block_size
+        && ((is_compressed(&src_info) && is_uint(&dst_info))
+            || (is_uint(&src_info) && is_compressed(&dst_info)))
+}
+
 pub fn map_texture_dimension_size(
     dimension: wgt::TextureDimension,
     wgt::Extent3d {
@@ -585,6 +657,25 @@ pub fn map_texture_view_dimension(dimension: wgt::TextureViewDimension) -> hal::
     }
 }
 
+pub fn map_swizzle(swizzle: [wgt::ComponentSwizzle; 4]) -> hal::format::Swizzle {
+    use hal::format::Component as H;
+    use wgt::ComponentSwizzle as Cs;
+    let identity = [H::R, H::G, H::B, H::A];
+    let mut components = identity;
+    for ((component, &source), &same) in components.iter_mut().zip(&swizzle).zip(&identity) {
+        *component = match source {
+            Cs::Identity => same,
+            Cs::Zero => H::Zero,
+            Cs::One => H::One,
+            Cs::R => H::R,
+            Cs::G => H::G,
+            Cs::B => H::B,
+            Cs::A => H::A,
+        };
+    }
+    hal::format::Swizzle(components[0], components[1], components[2], components[3])
+}
+
 pub(crate) fn map_buffer_state(usage: resource::BufferUse) -> hal::buffer::State {
     use crate::resource::BufferUse as W;
     use hal::buffer::Access as A;
@@ -807,6 +898,30 @@ pub fn map_rasterization_state_descriptor(
     }
 }
 
+pub fn map_present_mode(present_mode: wgt::PresentMode) -> hal::window::PresentMode {
+    match present_mode {
+        wgt::PresentMode::Immediate | wgt::PresentMode::AutoNoVsync => {
+            hal::window::PresentMode::IMMEDIATE
+        }
+        wgt::PresentMode::Mailbox => hal::window::PresentMode::MAILBOX,
+        wgt::PresentMode::Fifo => hal::window::PresentMode::FIFO,
+        wgt::PresentMode::FifoRelaxed | wgt::PresentMode::AutoVsync => {
+            hal::window::PresentMode::RELAXED
+        }
+    }
+}
+
+pub fn map_composite_alpha_mode(
+    alpha_mode: wgt::CompositeAlphaMode,
+) -> hal::window::CompositeAlphaMode {
+    match alpha_mode {
+        wgt::CompositeAlphaMode::Opaque => hal::window::CompositeAlphaMode::OPAQUE,
+        wgt::CompositeAlphaMode::PreMultiplied => hal::window::CompositeAlphaMode::PREMULTIPLIED,
+        wgt::CompositeAlphaMode::PostMultiplied => hal::window::CompositeAlphaMode::POSTMULTIPLIED,
+        wgt::CompositeAlphaMode::Inherit => hal::window::CompositeAlphaMode::INHERIT,
+    }
+}
+
 pub fn map_index_format(index_format: wgt::IndexFormat) -> hal::IndexType {
     match index_format {
         wgt::IndexFormat::Uint16 => hal::IndexType::U16,
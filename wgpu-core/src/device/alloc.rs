@@ -72,8 +72,11 @@ impl<B: hal::Backend> MemoryAllocator<B> {
         unsafe { self.0.alloc(&MemoryDevice::<B>(device), request) }
             .map(MemoryBlock)
             .map_err(|err| match err {
+                // Running out of allocation slots is just as recoverable as running out of
+                // memory: the user can free resources and try again.
                 gpu_alloc::AllocationError::OutOfHostMemory
-                | gpu_alloc::AllocationError::OutOfDeviceMemory => DeviceError::OutOfMemory,
+                | gpu_alloc::AllocationError::OutOfDeviceMemory
+                | gpu_alloc::AllocationError::TooManyObjects => DeviceError::OutOfMemory,
                 _ => panic!("Unable to allocate memory: {:?}", err),
             })
     }
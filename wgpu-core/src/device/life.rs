@@ -8,7 +8,7 @@ use crate::{
     device::{
         alloc,
         descriptor::{DescriptorAllocator, DescriptorSet},
-        queue::TempResource,
+        queue::{SubmittedWorkDoneClosure, TempResource},
         DeviceError,
     },
     hub::{GfxBackend, GlobalIdentityHandlerFactory, Hub, Token},
@@ -20,9 +20,10 @@ use crate::{
 use copyless::VecHelper as _;
 use hal::device::Device as _;
 use parking_lot::Mutex;
+use smallvec::SmallVec;
 use thiserror::Error;
 
-use std::sync::atomic::Ordering;
+use std::sync::{atomic::Ordering, Arc};
 
 const CLEANUP_WAIT_MS: u64 = 5000;
 
@@ -96,6 +97,10 @@ struct NonReferencedResources<B: hal::Backend> {
     // Note: we keep the associated ID here in order to be able to check
     // at any point what resources are used in a submission.
     image_views: Vec<(id::Valid<id::TextureViewId>, B::ImageView)>,
+    // Views cached by textures for attaching them to render passes directly.
+    // A view still shared with a render pass being recorded is kept around
+    // until the next cleanup.
+    attachment_views: Vec<Arc<B::ImageView>>,
     samplers: Vec<B::Sampler>,
     framebuffers: Vec<B::Framebuffer>,
     desc_sets: Vec<DescriptorSet<B>>,
@@ -112,6 +117,7 @@ impl<B: hal::Backend> NonReferencedResources<B> {
             buffers: Vec::new(),
             images: Vec::new(),
             image_views: Vec::new(),
+            attachment_views: Vec::new(),
             samplers: Vec::new(),
             framebuffers: Vec::new(),
             desc_sets: Vec::new(),
@@ -127,6 +133,7 @@ impl<B: hal::Backend> NonReferencedResources<B> {
         self.buffers.extend(other.buffers);
         self.images.extend(other.images);
         self.image_views.extend(other.image_views);
+        self.attachment_views.extend(other.attachment_views);
         self.samplers.extend(other.samplers);
         self.framebuffers.extend(other.framebuffers);
         self.desc_sets.extend(other.desc_sets);
@@ -160,6 +167,14 @@ impl<B: hal::Backend> NonReferencedResources<B> {
         for (_, raw) in self.image_views.drain(..) {
             device.destroy_image_view(raw);
         }
+        let mut shared_views = Vec::new();
+        for raw in self.attachment_views.drain(..) {
+            match Arc::try_unwrap(raw) {
+                Ok(raw) => device.destroy_image_view(raw),
+                Err(raw) => shared_views.push(raw),
+            }
+        }
+        self.attachment_views = shared_views;
         for raw in self.samplers.drain(..) {
             device.destroy_sampler(raw);
         }
@@ -197,6 +212,7 @@ struct ActiveSubmission<B: hal::Backend> {
     fence: B::Fence,
     last_resources: NonReferencedResources<B>,
     mapped: Vec<id::Valid<id::BufferId>>,
+    work_done_closures: SmallVec<[SubmittedWorkDoneClosure; 1]>,
 }
 
 #[derive(Clone, Debug, Error)]
@@ -234,6 +250,8 @@ pub(crate) struct LifetimeTracker<B: hal::Backend> {
     /// actual deletion.
     free_resources: NonReferencedResources<B>,
     ready_to_map: Vec<id::Valid<id::BufferId>>,
+    /// Closures of the retired submissions, waiting to be fired.
+    work_done_closures: SmallVec<[SubmittedWorkDoneClosure; 1]>,
 }
 
 impl<B: hal::Backend> LifetimeTracker<B> {
@@ -246,6 +264,7 @@ impl<B: hal::Backend> LifetimeTracker<B> {
             active: Vec::new(),
             free_resources: NonReferencedResources::new(),
             ready_to_map: Vec::new(),
+            work_done_closures: SmallVec::new(),
         }
     }
 
@@ -281,6 +300,7 @@ impl<B: hal::Backend> LifetimeTracker<B> {
             fence,
             last_resources,
             mapped: Vec::new(),
+            work_done_closures: SmallVec::new(),
         });
     }
 
@@ -288,13 +308,23 @@ impl<B: hal::Backend> LifetimeTracker<B> {
         self.mapped.push(Stored { value, ref_count });
     }
 
-    fn wait_idle(&self, device: &B::Device) -> Result<(), WaitIdleError> {
-        if !self.active.is_empty() {
-            tracing::debug!("Waiting for IDLE...");
+    /// Waits for all the active submissions up to `last_index` inclusive.
+    fn wait_for(
+        &self,
+        device: &B::Device,
+        last_index: SubmissionIndex,
+    ) -> Result<(), WaitIdleError> {
+        let count = self
+            .active
+            .iter()
+            .take_while(|a| a.index <= last_index)
+            .count();
+        if count != 0 {
+            tracing::debug!("Waiting for submission {}...", last_index);
             let status = unsafe {
                 device
                     .wait_for_fences(
-                        self.active.iter().map(|a| &a.fence),
+                        self.active[..count].iter().map(|a| &a.fence),
                         hal::device::WaitFor::All,
                         CLEANUP_WAIT_MS * 1_000_000,
                     )
@@ -314,10 +344,12 @@ impl<B: hal::Backend> LifetimeTracker<B> {
     pub fn triage_submissions(
         &mut self,
         device: &B::Device,
-        force_wait: bool,
+        maintain: wgt::Maintain<SubmissionIndex>,
     ) -> Result<SubmissionIndex, WaitIdleError> {
-        if force_wait {
-            self.wait_idle(device)?;
+        match maintain {
+            wgt::Maintain::Poll => {}
+            wgt::Maintain::Wait => self.wait_for(device, SubmissionIndex::MAX)?,
+            wgt::Maintain::WaitForSubmission(index) => self.wait_for(device, index)?,
         }
         //TODO: enable when `is_sorted_by_key` is stable
         //debug_assert!(self.active.is_sorted_by_key(|a| a.index));
@@ -335,6 +367,7 @@ impl<B: hal::Backend> LifetimeTracker<B> {
             tracing::trace!("Active submission {} is done", a.index);
             self.free_resources.extend(a.last_resources);
             self.ready_to_map.extend(a.mapped);
+            self.work_done_closures.extend(a.work_done_closures);
             unsafe {
                 device.destroy_fence(a.fence);
             }
@@ -343,6 +376,25 @@ impl<B: hal::Backend> LifetimeTracker<B> {
         Ok(last_done)
     }
 
+    /// Attaches the closure to the latest submission in flight.
+    /// Returns it back if there is nothing in flight.
+    pub fn add_work_done_closure(
+        &mut self,
+        closure: SubmittedWorkDoneClosure,
+    ) -> Option<SubmittedWorkDoneClosure> {
+        match self.active.last_mut() {
+            Some(active) => {
+                active.work_done_closures.push(closure);
+                None
+            }
+            None => Some(closure),
+        }
+    }
+
+    pub fn take_work_done_closures(&mut self) -> SmallVec<[SubmittedWorkDoneClosure; 1]> {
+        std::mem::take(&mut self.work_done_closures)
+    }
+
     pub fn cleanup(
         &mut self,
         device: &B::Device,
@@ -372,6 +424,19 @@ impl<B: hal::Backend> LifetimeTracker<B> {
             TempResource::Image(raw) => resources.images.push((raw, memory)),
         }
     }
+
+    pub fn schedule_attachment_views_destruction(
+        &mut self,
+        views: impl IntoIterator<Item = Arc<B::ImageView>>,
+        last_submit_index: SubmissionIndex,
+    ) {
+        let resources = self
+            .active
+            .iter_mut()
+            .find(|a| a.index == last_submit_index)
+            .map_or(&mut self.free_resources, |a| &mut a.last_resources);
+        resources.attachment_views.extend(views);
+    }
 }
 
 impl<B: GfxBackend> LifetimeTracker<B> {
@@ -437,7 +502,8 @@ impl<B: GfxBackend> LifetimeTracker<B> {
                                 self.suspected_resources.textures.push(source_id.value);
                                 raw
                             }
-                            resource::TextureViewInner::SwapChain { .. } => unreachable!(),
+                            resource::TextureViewInner::SwapChain { .. }
+                            | resource::TextureViewInner::Attachment { .. } => unreachable!(),
                         };
 
                         let submit_index = res.life_guard.submission_index.load(Ordering::Acquire);
@@ -463,12 +529,18 @@ impl<B: GfxBackend> LifetimeTracker<B> {
 
                     if let Some(res) = hub.textures.unregister_locked(id.0, &mut *guard) {
                         let submit_index = res.life_guard.submission_index.load(Ordering::Acquire);
-                        self.active
+                        let resources = self
+                            .active
                             .iter_mut()
                             .find(|a| a.index == submit_index)
-                            .map_or(&mut self.free_resources, |a| &mut a.last_resources)
-                            .images
-                            .extend(res.raw);
+                            .map_or(&mut self.free_resources, |a| &mut a.last_resources);
+                        resources.images.extend(res.raw);
+                        resources.attachment_views.extend(
+                            res.attachment_views
+                                .into_inner()
+                                .into_iter()
+                                .map(|(_, raw)| raw),
+                        );
                     }
                 }
             }
@@ -6,7 +6,8 @@ use crate::{
     binding_model, command, conv,
     device::life::WaitIdleError,
     hub::{
-        GfxBackend, Global, GlobalIdentityHandlerFactory, Hub, Input, InvalidId, Storage, Token,
+        Access, GfxBackend, Global, GlobalIdentityHandlerFactory, Hub, Input, InvalidId, Registry,
+        Storage, Token,
     },
     id, instance, pipeline, resource, span, swap_chain,
     track::{BufferState, TextureSelector, TextureState, TrackerSet},
@@ -36,13 +37,13 @@ use std::{
     mem,
     ops::Range,
     ptr,
-    sync::atomic::Ordering,
+    sync::{atomic::Ordering, Arc},
 };
 
 pub mod alloc;
 pub mod descriptor;
 mod life;
-mod queue;
+pub mod queue;
 #[cfg(any(feature = "trace", feature = "replay"))]
 pub mod trace;
 
@@ -214,11 +215,80 @@ fn unmap_buffer<B: hal::Backend>(
     Ok(())
 }
 
-//Note: this logic is specifically moved out of `handle_mapping()` in order to
-// have nothing locked by the time we execute users callback code.
-fn fire_map_callbacks<I: IntoIterator<Item = BufferMapPendingCallback>>(callbacks: I) {
-    for (operation, status) in callbacks {
-        unsafe { (operation.callback)(status, operation.user_data) }
+/// Look up the entry point of a render pipeline stage that can't be validated.
+fn unvalidated_entry_point<'a, B: hal::Backend>(
+    shader_modules: &'a Storage<pipeline::ShaderModule<B>, id::ShaderModuleId>,
+    stage: &'a pipeline::ProgrammableStageDescriptor,
+    flag: wgt::ShaderStage,
+) -> Result<hal::pso::EntryPoint<'a, B>, pipeline::CreateRenderPipelineError> {
+    let shader_module = shader_modules.get(stage.module).map_err(|_| {
+        pipeline::CreateRenderPipelineError::Stage {
+            flag,
+            error: validation::StageError::InvalidModule,
+        }
+    })?;
+    Ok(hal::pso::EntryPoint {
+        entry: &stage.entry_point,
+        module: &shader_module.raw,
+        specialization: hal::pso::Specialization::EMPTY,
+    })
+}
+
+/// Stable hash of the contents of an existing pipeline layout.
+fn pipeline_layout_stable_hash<B: hal::Backend>(
+    layout_id: id::PipelineLayoutId,
+    pipeline_layout_guard: &Storage<binding_model::PipelineLayout<B>, id::PipelineLayoutId>,
+    bgl_guard: &Storage<binding_model::BindGroupLayout<B>, id::BindGroupLayoutId>,
+) -> Result<u64, pipeline::DescriptorHashError> {
+    let layout = pipeline_layout_guard
+        .get(layout_id)
+        .map_err(|_| pipeline::DescriptorHashError::InvalidLayout(layout_id))?;
+    let bgl_hashes = layout
+        .bind_group_layout_ids
+        .iter()
+        .map(|&id| binding_model::hash_bind_group_layout_entries(bgl_guard[id].entries.values()))
+        .collect::<Vec<_>>();
+    Ok(binding_model::hash_pipeline_layout(
+        &bgl_hashes,
+        &layout.push_constant_ranges,
+    ))
+}
+
+/// Stable hash of the source of an existing shader module.
+fn shader_module_stable_hash<B: hal::Backend>(
+    module_id: id::ShaderModuleId,
+    shader_module_guard: &Storage<pipeline::ShaderModule<B>, id::ShaderModuleId>,
+) -> Result<u64, pipeline::DescriptorHashError> {
+    let module = shader_module_guard
+        .get(module_id)
+        .map_err(|_| pipeline::DescriptorHashError::InvalidModule(module_id))?;
+    module
+        .source_hash
+        .ok_or(pipeline::DescriptorHashError::UnhashableModule(module_id))
+}
+
+/// User callbacks collected during device maintenance.
+#[derive(Default)]
+struct UserClosures {
+    mappings: Vec<BufferMapPendingCallback>,
+    submissions: SmallVec<[queue::SubmittedWorkDoneClosure; 1]>,
+}
+
+impl UserClosures {
+    fn extend(&mut self, other: Self) {
+        self.mappings.extend(other.mappings);
+        self.submissions.extend(other.submissions);
+    }
+
+    //Note: this logic is specifically moved out of `handle_mapping()` in order to
+    // have nothing locked by the time we execute users callback code.
+    fn fire(self) {
+        for (operation, status) in self.mappings {
+            unsafe { (operation.callback)(status, operation.user_data) }
+        }
+        for closure in self.submissions {
+            unsafe { (closure.callback)(closure.user_data) }
+        }
     }
 }
 
@@ -259,14 +329,32 @@ pub struct Device<B: hal::Backend> {
     pub(crate) private_features: PrivateFeatures,
     pub(crate) limits: wgt::Limits,
     pub(crate) features: wgt::Features,
+    pub(crate) creation_report: instance::DeviceCreationReport,
+    /// Skip the per-draw validation in render passes. Only honored in release builds.
+    pub(crate) skip_draw_validation: bool,
+    pub(crate) recording_limits: command::RecordingLimits,
     spirv_capabilities: FastHashSet<naga::back::spv::Capability>,
     //TODO: move this behind another mutex. This would allow several methods to switch
     // to borrow Device immutably, such as `write_buffer`, `write_texture`, and `buffer_unmap`.
     pending_writes: queue::PendingWrites<B>,
+    /// Only present if the application runs under RenderDoc.
+    #[cfg(feature = "capture")]
+    renderdoc: Option<Mutex<RenderDoc>>,
     #[cfg(feature = "trace")]
     pub(crate) trace: Option<Mutex<Trace>>,
 }
 
+/// Connection to the RenderDoc in-application API.
+#[cfg(feature = "capture")]
+struct RenderDoc(renderdoc::RenderDoc<renderdoc::V110>);
+
+#[cfg(feature = "capture")]
+impl std::fmt::Debug for RenderDoc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RenderDoc")
+    }
+}
+
 #[derive(Clone, Debug, Error)]
 pub enum CreateDeviceError {
     #[error("not enough memory left")]
@@ -282,6 +370,7 @@ impl<B: GfxBackend> Device<B> {
         hal_limits: hal::Limits,
         private_features: PrivateFeatures,
         desc: &DeviceDescriptor,
+        creation_report: instance::DeviceCreationReport,
         trace_path: Option<&std::path::Path>,
     ) -> Result<Self, CreateDeviceError> {
         use naga::back::spv::Capability as C;
@@ -337,8 +426,19 @@ impl<B: GfxBackend> Device<B> {
             private_features,
             limits: desc.limits.clone(),
             features: desc.features.clone(),
+            creation_report,
+            skip_draw_validation: false,
+            recording_limits: command::RecordingLimits::default(),
             spirv_capabilities,
             pending_writes: queue::PendingWrites::new(),
+            #[cfg(feature = "capture")]
+            renderdoc: match renderdoc::RenderDoc::new() {
+                Ok(rd) => Some(Mutex::new(RenderDoc(rd))),
+                Err(e) => {
+                    tracing::debug!("RenderDoc is not available: {}", e);
+                    None
+                }
+            },
         })
     }
 
@@ -364,9 +464,9 @@ impl<B: GfxBackend> Device<B> {
     fn maintain<'this, 'token: 'this, G: GlobalIdentityHandlerFactory>(
         &'this self,
         hub: &Hub<B, G>,
-        force_wait: bool,
+        maintain: wgt::Maintain<SubmissionIndex>,
         token: &mut Token<'token, Self>,
-    ) -> Result<Vec<BufferMapPendingCallback>, WaitIdleError> {
+    ) -> Result<UserClosures, WaitIdleError> {
         let mut life_tracker = self.lock_life(token);
 
         life_tracker.triage_suspected(
@@ -377,15 +477,18 @@ impl<B: GfxBackend> Device<B> {
             token,
         );
         life_tracker.triage_mapped(hub, token);
-        let last_done = life_tracker.triage_submissions(&self.raw, force_wait)?;
-        let callbacks = life_tracker.handle_mapping(hub, &self.raw, &self.trackers, token);
+        let last_done = life_tracker.triage_submissions(&self.raw, maintain)?;
+        let closures = UserClosures {
+            mappings: life_tracker.handle_mapping(hub, &self.raw, &self.trackers, token),
+            submissions: life_tracker.take_work_done_closures(),
+        };
         life_tracker.cleanup(&self.raw, &self.mem_allocator, &self.desc_allocator);
 
         self.life_guard
             .submission_index
             .store(last_done, Ordering::Release);
         self.cmd_allocator.maintain(&self.raw, last_done);
-        Ok(callbacks)
+        Ok(closures)
     }
 
     fn untrack<'this, 'token: 'this, G: GlobalIdentityHandlerFactory>(
@@ -475,6 +578,20 @@ impl<B: GfxBackend> Device<B> {
         if desc.usage.is_empty() {
             return Err(resource::CreateBufferError::EmptyUsage);
         }
+        if desc.size > self.limits.max_buffer_size {
+            return Err(resource::CreateBufferError::MaxBufferSize {
+                requested: desc.size,
+                maximum: self.limits.max_buffer_size,
+            });
+        }
+
+        // Buffers that are not host-visible are zeroed on the GPU at the first use.
+        let is_mappable = desc
+            .usage
+            .intersects(wgt::BufferUsage::MAP_READ | wgt::BufferUsage::MAP_WRITE);
+        if !is_mappable {
+            usage |= hal::buffer::Usage::TRANSFER_DST;
+        }
 
         let mem_usage = {
             use gpu_alloc::UsageFlags as Uf;
@@ -517,22 +634,46 @@ impl<B: GfxBackend> Device<B> {
             flags
         };
 
-        let mut buffer = unsafe { self.raw.create_buffer(desc.size.max(1), usage) }.map_err(
-            |err| match err {
+        // Filling with zeros works on whole words, so the raw size is aligned up.
+        let raw_size =
+            (desc.size.max(1) + wgt::COPY_BUFFER_ALIGNMENT - 1) & !(wgt::COPY_BUFFER_ALIGNMENT - 1);
+        let mut buffer =
+            unsafe { self.raw.create_buffer(raw_size, usage) }.map_err(|err| match err {
                 hal::buffer::CreationError::OutOfMemory(_) => DeviceError::OutOfMemory,
                 _ => panic!("failed to create buffer: {}", err),
-            },
-        )?;
+            })?;
         if let Some(ref label) = desc.label {
             unsafe { self.raw.set_buffer_name(&mut buffer, label) };
         }
 
+        // Allocation failures are reported to the user as recoverable errors,
+        // so make sure nothing leaks on the way out.
         let requirements = unsafe { self.raw.get_buffer_requirements(&buffer) };
-        let block = self
+        let mut block = match self
             .mem_allocator
             .lock()
-            .allocate(&self.raw, requirements, mem_usage)?;
-        block.bind_buffer(&self.raw, &mut buffer)?;
+            .allocate(&self.raw, requirements, mem_usage)
+        {
+            Ok(block) => block,
+            Err(err) => {
+                unsafe { self.raw.destroy_buffer(buffer) };
+                return Err(err.into());
+            }
+        };
+        if let Err(err) = block.bind_buffer(&self.raw, &mut buffer) {
+            self.mem_allocator.lock().free(&self.raw, block);
+            unsafe { self.raw.destroy_buffer(buffer) };
+            return Err(err.into());
+        }
+        // Mappable buffers are zeroed right away on the CPU, so that
+        // nothing unwritten can ever be observed through a mapping.
+        if is_mappable && desc.size != 0 {
+            if let Err(err) = self.zero_host_memory(&mut block, desc.size) {
+                self.mem_allocator.lock().free(&self.raw, block);
+                unsafe { self.raw.destroy_buffer(buffer) };
+                return Err(err.into());
+            }
+        }
 
         Ok(resource::Buffer {
             raw: Some((buffer, block)),
@@ -546,9 +687,26 @@ impl<B: GfxBackend> Device<B> {
             sync_mapped_writes: None,
             map_state: resource::BufferMapState::Idle,
             life_guard: LifeGuard::new(desc.label.borrow_or_default()),
+            needs_zero_init: !is_mappable,
         })
     }
 
+    fn zero_host_memory(
+        &self,
+        block: &mut alloc::MemoryBlock<B>,
+        size: BufferAddress,
+    ) -> Result<(), DeviceError> {
+        let ptr = block.map(&self.raw, 0, size)?;
+        unsafe { ptr::write_bytes(ptr.as_ptr(), 0, size as usize) };
+        let flushed = if block.is_coherent() {
+            Ok(())
+        } else {
+            block.flush_range(&self.raw, 0, None)
+        };
+        block.unmap(&self.raw);
+        flushed
+    }
+
     fn create_texture(
         &self,
         self_id: id::DeviceId,
@@ -583,6 +741,17 @@ impl<B: GfxBackend> Device<B> {
             return Err(resource::CreateTextureError::EmptyUsage);
         }
 
+        if desc.dimension == TextureDimension::D3
+            && desc.usage.contains(wgt::TextureUsage::RENDER_ATTACHMENT)
+            && !self
+                .features
+                .contains(wgt::Features::RENDER_ATTACHMENT_3D_SLICES)
+        {
+            return Err(resource::CreateTextureError::RenderAttachment3d(
+                wgt::Features::RENDER_ATTACHMENT_3D_SLICES,
+            ));
+        }
+
         let format_features = if self
             .features
             .contains(wgt::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
@@ -603,7 +772,10 @@ impl<B: GfxBackend> Device<B> {
         let kind = conv::map_texture_dimension_size(desc.dimension, desc.size, desc.sample_count)?;
         let format = conv::map_texture_format(desc.format, self.private_features);
         let aspects = format.surface_desc().aspects;
-        let usage = conv::map_texture_usage(desc.usage, aspects);
+        let mut usage = conv::map_texture_usage(desc.usage, aspects);
+        // Textures are zeroed on the GPU at the first use, either by a clear
+        // or, for compressed formats, by a copy from a zeroed staging buffer.
+        usage |= hal::image::Usage::TRANSFER_DST;
 
         let mip_level_count = desc.mip_level_count;
         if mip_level_count == 0
@@ -620,6 +792,12 @@ impl<B: GfxBackend> Device<B> {
         if desc.dimension == TextureDimension::D2 && desc.size.depth % 6 == 0 {
             view_caps |= hal::image::ViewCapabilities::KIND_CUBE;
         };
+        // 3D render attachments are rendered through 2D views of their slices
+        if desc.dimension == TextureDimension::D3
+            && desc.usage.contains(wgt::TextureUsage::RENDER_ATTACHMENT)
+        {
+            view_caps |= hal::image::ViewCapabilities::KIND_2D_ARRAY;
+        }
 
         // TODO: 2D arrays, cubemap arrays
 
@@ -645,12 +823,22 @@ impl<B: GfxBackend> Device<B> {
         };
 
         let requirements = unsafe { self.raw.get_image_requirements(&image) };
-        let block = self.mem_allocator.lock().allocate(
+        let block = match self.mem_allocator.lock().allocate(
             &self.raw,
             requirements,
             gpu_alloc::UsageFlags::FAST_DEVICE_ACCESS,
-        )?;
-        block.bind_image(&self.raw, &mut image)?;
+        ) {
+            Ok(block) => block,
+            Err(err) => {
+                unsafe { self.raw.destroy_image(image) };
+                return Err(err.into());
+            }
+        };
+        if let Err(err) = block.bind_image(&self.raw, &mut image) {
+            self.mem_allocator.lock().free(&self.raw, block);
+            unsafe { self.raw.destroy_image(image) };
+            return Err(err.into());
+        }
 
         Ok(resource::Texture {
             raw: Some((image, block)),
@@ -674,6 +862,8 @@ impl<B: GfxBackend> Device<B> {
                 layers: 0..kind.num_layers(),
             },
             life_guard: LifeGuard::new(desc.label.borrow_or_default()),
+            needs_zero_init: true,
+            attachment_views: Mutex::new(FastHashMap::default()),
         })
     }
 
@@ -794,6 +984,17 @@ impl<B: GfxBackend> Device<B> {
             });
         }
 
+        let swizzle = conv::map_swizzle(desc.swizzle);
+        if swizzle != hal::format::Swizzle::NO
+            && !self
+                .features
+                .contains(wgt::Features::TEXTURE_COMPONENT_SWIZZLE)
+        {
+            return Err(resource::CreateTextureViewError::MissingFeature(
+                wgt::Features::TEXTURE_COMPONENT_SWIZZLE,
+            ));
+        }
+
         let format = desc.format.unwrap_or(texture.format);
         let range = hal::image::SubresourceRange {
             aspects,
@@ -810,12 +1011,24 @@ impl<B: GfxBackend> Device<B> {
                     texture_raw,
                     conv::map_texture_view_dimension(view_dim),
                     conv::map_texture_format(format, self.private_features),
-                    hal::format::Swizzle::NO,
+                    swizzle,
                     range.clone(),
                 )
                 .or(Err(resource::CreateTextureViewError::OutOfMemory))?
         };
 
+        // A single mip level of a 3D render attachment is rendered into one slice
+        // at a time, picked with `depth_slice`. The 2D views of the slices are only
+        // created when a render pass uses them, see `attachment_raw_view`.
+        let depth_slice_count = if view_dim == wgt::TextureViewDimension::D3
+            && selector.levels.end - selector.levels.start == 1
+            && texture.usage.contains(wgt::TextureUsage::RENDER_ATTACHMENT)
+        {
+            hal_extent.depth
+        } else {
+            0
+        };
+
         Ok(resource::TextureView {
             inner: resource::TextureViewInner::Native {
                 raw,
@@ -834,6 +1047,8 @@ impl<B: GfxBackend> Device<B> {
             },
             samples: texture.kind.num_samples(),
             framebuffer_attachment: texture.framebuffer_attachment.clone(),
+            swizzled: swizzle != hal::format::Swizzle::NO,
+            depth_slice_count,
             // once a storage - forever a storage
             sampled_internal_use: if texture.usage.contains(wgt::TextureUsage::STORAGE) {
                 resource::TextureUse::SAMPLED | resource::TextureUse::STORAGE_LOAD
@@ -845,6 +1060,116 @@ impl<B: GfxBackend> Device<B> {
         })
     }
 
+    /// Creates a view of a single mip level and array layer of a 2D texture,
+    /// for render passes attaching the texture without an explicit view.
+    /// The raw view is cached by the texture, so only the first use creates it.
+    pub(crate) fn create_attachment_view(
+        &self,
+        texture: &resource::Texture<B>,
+        texture_id: id::TextureId,
+        mip_level: u32,
+        array_layer: u32,
+    ) -> Result<resource::TextureView<B>, resource::CreateTextureViewError> {
+        if texture.dimension != TextureDimension::D2 {
+            return Err(
+                resource::CreateTextureViewError::InvalidTextureViewDimension {
+                    view: TextureViewDimension::D2,
+                    image: texture.dimension,
+                },
+            );
+        }
+        let level_end = texture.full_range.levels.end;
+        let layer_end = texture.full_range.layers.end;
+        if mip_level >= level_end as u32 {
+            return Err(resource::CreateTextureViewError::TooManyMipLevels {
+                requested: mip_level + 1,
+                total: level_end,
+            });
+        }
+        if array_layer >= layer_end as u32 {
+            return Err(resource::CreateTextureViewError::TooManyArrayLayers {
+                requested: array_layer + 1,
+                total: layer_end,
+            });
+        }
+
+        let raw = self.attachment_raw_view(texture, mip_level, array_layer)?;
+
+        let hal_extent = texture.kind.extent().at_level(mip_level as _);
+        #[allow(clippy::range_plus_one)]
+        let selector = TextureSelector {
+            levels: mip_level as hal::image::Level..mip_level as hal::image::Level + 1,
+            layers: array_layer as hal::image::Layer..array_layer as hal::image::Layer + 1,
+        };
+        Ok(resource::TextureView {
+            inner: resource::TextureViewInner::Attachment {
+                raw,
+                source_id: Stored {
+                    value: id::Valid(texture_id),
+                    ref_count: texture.life_guard.add_ref(),
+                },
+            },
+            aspects: texture.aspects,
+            format: texture.format,
+            format_features: texture.format_features,
+            extent: wgt::Extent3d {
+                width: hal_extent.width,
+                height: hal_extent.height,
+                depth: 1,
+            },
+            samples: texture.kind.num_samples(),
+            framebuffer_attachment: texture.framebuffer_attachment.clone(),
+            swizzled: false,
+            depth_slice_count: 0,
+            sampled_internal_use: resource::TextureUse::SAMPLED,
+            selector,
+            life_guard: LifeGuard::new("<attachment>"),
+        })
+    }
+
+    /// Returns the 2D view of a mip level and array layer of a texture, creating it
+    /// on the first use. The layers of 3D textures are their depth slices.
+    ///
+    /// The views are cached by the texture, and destroyed with it.
+    pub(crate) fn attachment_raw_view(
+        &self,
+        texture: &resource::Texture<B>,
+        mip_level: u32,
+        array_layer: u32,
+    ) -> Result<Arc<B::ImageView>, resource::CreateTextureViewError> {
+        let &(ref texture_raw, _) = texture
+            .raw
+            .as_ref()
+            .ok_or(resource::CreateTextureViewError::InvalidTexture)?;
+        let raw = match texture
+            .attachment_views
+            .lock()
+            .entry((mip_level, array_layer))
+        {
+            Entry::Occupied(e) => Arc::clone(e.get()),
+            Entry::Vacant(e) => {
+                let raw = unsafe {
+                    self.raw.create_image_view(
+                        texture_raw,
+                        hal::image::ViewKind::D2,
+                        conv::map_texture_format(texture.format, self.private_features),
+                        hal::format::Swizzle::NO,
+                        hal::image::SubresourceRange {
+                            aspects: texture.aspects,
+                            level_start: mip_level as _,
+                            level_count: Some(1),
+                            layer_start: array_layer as _,
+                            layer_count: Some(1),
+                        },
+                    )
+                }
+                .or(Err(resource::CreateTextureViewError::OutOfMemory))?;
+                Arc::clone(e.insert(Arc::new(raw)))
+            }
+        };
+        Ok(raw)
+    }
+
     fn create_sampler(
         &self,
         self_id: id::DeviceId,
@@ -930,6 +1255,7 @@ impl<B: GfxBackend> Device<B> {
         desc: &pipeline::ShaderModuleDescriptor<'a>,
         source: pipeline::ShaderModuleSource<'a>,
     ) -> Result<pipeline::ShaderModule<B>, pipeline::CreateShaderModuleError> {
+        let source_hash = source.stable_hash();
         // First, try to produce a Naga module.
         let (spv, module) = match source {
             pipeline::ShaderModuleSource::SpirV(spv) => {
@@ -948,17 +1274,14 @@ impl<B: GfxBackend> Device<B> {
                 };
                 (Some(spv), module)
             }
-            pipeline::ShaderModuleSource::Wgsl(code) => {
-                // TODO: refactor the corresponding Naga error to be owned, and then
-                // display it instead of unwrapping
-                match naga::front::wgsl::parse_str(&code) {
-                    Ok(module) => (None, Some(module)),
-                    Err(err) => {
-                        tracing::error!("Failed to parse WGSL code: {}", err);
-                        return Err(pipeline::CreateShaderModuleError::Parsing);
-                    }
+            pipeline::ShaderModuleSource::Wgsl(code) => match naga::front::wgsl::parse_str(&code) {
+                Ok(module) => (None, Some(module)),
+                Err(err) => {
+                    let error = desc.parse_error(err.pos.0, err.pos.1, format!("{:?}", err.error));
+                    tracing::error!("Failed to parse WGSL code: {}", error);
+                    return Err(pipeline::CreateShaderModuleError::Parsing(error));
                 }
-            }
+            },
             pipeline::ShaderModuleSource::Naga(module) => (None, Some(module)),
         };
 
@@ -1024,7 +1347,9 @@ impl<B: GfxBackend> Device<B> {
                 }
                 Err(error) => {
                     tracing::error!("Shader error: {}", error);
-                    return Err(pipeline::CreateShaderModuleError::Parsing);
+                    return Err(pipeline::CreateShaderModuleError::Compilation(
+                        error.to_string(),
+                    ));
                 }
             },
             device_id: Stored {
@@ -1032,6 +1357,7 @@ impl<B: GfxBackend> Device<B> {
                 ref_count: self.life_guard.add_ref(),
             },
             interface,
+            source_hash,
             #[cfg(debug_assertions)]
             label: desc.label.to_string_or_default(),
         })
@@ -1109,6 +1435,15 @@ impl<B: GfxBackend> Device<B> {
         let mut desc_count = descriptor::DescriptorTotalCount::default();
         for binding in entry_map.values() {
             use wgt::BindingType as Bt;
+            if binding
+                .visibility
+                .intersects(wgt::ShaderStage::TASK | wgt::ShaderStage::MESH)
+                && !self.features.contains(wgt::Features::MESH_SHADER)
+            {
+                return Err(binding_model::CreateBindGroupLayoutError::MissingFeature(
+                    wgt::Features::MESH_SHADER,
+                ));
+            }
             let (counter, array_feature) = match binding.ty {
                 Bt::Buffer {
                     ty: wgt::BufferBindingType::Uniform,
@@ -1365,10 +1700,21 @@ impl<B: GfxBackend> Device<B> {
                         .use_extend(&*texture_view_guard, id, (), ())
                         .map_err(|_| Error::InvalidTextureView(id))?;
                     let (pub_usage, internal_use) = match decl.ty {
-                        wgt::BindingType::Texture { .. } => {
+                        wgt::BindingType::Texture { sample_type, .. } => {
+                            if view.aspects == hal::format::Aspects::STENCIL
+                                && sample_type != wgt::TextureSampleType::Uint
+                            {
+                                return Err(Error::StencilSampleType {
+                                    binding,
+                                    actual: sample_type,
+                                });
+                            }
                             (wgt::TextureUsage::SAMPLED, view.sampled_internal_use)
                         }
                         wgt::BindingType::StorageTexture { access, .. } => {
+                            if view.swizzled {
+                                return Err(Error::SwizzledStorageTexture(id));
+                            }
                             let internal_use = match access {
                                 wgt::StorageTextureAccess::ReadOnly => {
                                     resource::TextureUse::STORAGE_LOAD
@@ -1432,6 +1778,7 @@ impl<B: GfxBackend> Device<B> {
                         resource::TextureViewInner::SwapChain { .. } => {
                             return Err(Error::SwapChainImage);
                         }
+                        resource::TextureViewInner::Attachment { .. } => unreachable!(),
                     }
                 }
                 Br::TextureViewArray(ref bindings_array) => {
@@ -1496,6 +1843,7 @@ impl<B: GfxBackend> Device<B> {
                                 resource::TextureViewInner::SwapChain { .. } => {
                                     Err(Error::SwapChainImage)
                                 }
+                                resource::TextureViewInner::Attachment { .. } => unreachable!(),
                             }
                         })
                         .collect::<Result<_, _>>()?
@@ -1580,6 +1928,13 @@ impl<B: GfxBackend> Device<B> {
                 });
             }
             used_stages |= pc.stages;
+            if pc
+                .stages
+                .intersects(wgt::ShaderStage::TASK | wgt::ShaderStage::MESH)
+                && !self.features.contains(wgt::Features::MESH_SHADER)
+            {
+                return Err(Error::MissingFeature(wgt::Features::MESH_SHADER));
+            }
 
             let device_max_pc_size = self.limits.max_push_constant_size;
             if device_max_pc_size < pc.range.end {
@@ -1804,7 +2159,7 @@ impl<B: GfxBackend> Device<B> {
                     &mut derived_group_layouts,
                     &entry_point_name,
                     flag,
-                    io,
+                    Some(io),
                 )
                 .map_err(pipeline::CreateComputePipelineError::Stage)?;
         } else if desc.layout.is_none() {
@@ -1976,7 +2331,10 @@ impl<B: GfxBackend> Device<B> {
                 .map(conv::map_color_state_descriptor)
                 .collect(),
         };
+        // Backends turn on rasterizer discard when there is neither a fragment
+        // stage nor any depth/stencil test, so we leave the latter out here.
         let depth_stencil = depth_stencil_state
+            .filter(|_| !rasterization_state.discard)
             .map(conv::map_depth_stencil_state_descriptor)
             .unwrap_or_default();
 
@@ -2013,6 +2371,26 @@ impl<B: GfxBackend> Device<B> {
                 wgt::Features::NON_FILL_POLYGON_MODE,
             ));
         }
+        if rasterization_state.discard && desc.fragment_stage.is_some() {
+            return Err(pipeline::CreateRenderPipelineError::FragmentStageWithDiscard);
+        }
+        let is_mesh = desc.mesh_stages.is_some();
+        if is_mesh {
+            if !self.features.contains(wgt::Features::MESH_SHADER) {
+                return Err(pipeline::CreateRenderPipelineError::MissingFeature(
+                    wgt::Features::MESH_SHADER,
+                ));
+            }
+            if !desc.vertex_stage.entry_point.is_empty() {
+                return Err(pipeline::CreateRenderPipelineError::VertexAndMeshStages);
+            }
+            if !desc_vbs.is_empty() || desc.vertex_state.index_format.is_some() {
+                return Err(pipeline::CreateRenderPipelineError::MeshPipelineVertexInput);
+            }
+            if desc.layout.is_none() {
+                return Err(pipeline::CreateRenderPipelineError::MeshPipelineImplicitLayout);
+            }
+        }
 
         if desc.layout.is_none() {
             for _ in 0..self.limits.max_bind_groups {
@@ -2020,7 +2398,31 @@ impl<B: GfxBackend> Device<B> {
             }
         }
 
-        let (shader_module_guard, _) = hub.shader_modules.read(&mut token);
+        let (shader_module_guard, mut token) = hub.shader_modules.read(&mut token);
+        let (render_pipeline_guard, _) = hub.render_pipelines.read(&mut token);
+
+        let base_pipeline = match desc.base_pipeline {
+            Some(base_id) => {
+                let base = render_pipeline_guard.get(base_id).map_err(|_| {
+                    pipeline::CreateRenderPipelineError::InvalidBasePipeline(base_id)
+                })?;
+                if base.device_id.value.0 != self_id {
+                    return Err(pipeline::CreateRenderPipelineError::InvalidBasePipeline(
+                        base_id,
+                    ));
+                }
+                if !base
+                    .flags
+                    .contains(pipeline::PipelineFlags::ALLOWS_DERIVATIVES)
+                {
+                    return Err(
+                        pipeline::CreateRenderPipelineError::BasePipelineNotDerivable(base_id),
+                    );
+                }
+                Some(base)
+            }
+            None => None,
+        };
 
         let rp_key = RenderPassKey {
             colors: color_states
@@ -2059,45 +2461,80 @@ impl<B: GfxBackend> Device<B> {
             }),
         };
 
-        let vertex = {
-            let entry_point_name = &desc.vertex_stage.entry_point;
-            let flag = wgt::ShaderStage::VERTEX;
+        let primitive_assembler = match desc.mesh_stages {
+            None => {
+                let stage = &desc.vertex_stage;
+                let entry_point_name = &stage.entry_point;
+                let flag = wgt::ShaderStage::VERTEX;
 
-            let shader_module =
-                shader_module_guard
-                    .get(desc.vertex_stage.module)
-                    .map_err(|_| pipeline::CreateRenderPipelineError::Stage {
+                let shader_module = shader_module_guard.get(stage.module).map_err(|_| {
+                    pipeline::CreateRenderPipelineError::Stage {
                         flag,
                         error: validation::StageError::InvalidModule,
-                    })?;
+                    }
+                })?;
 
-            if let Some(ref interface) = shader_module.interface {
-                let provided_layouts = match desc.layout {
-                    Some(pipeline_layout_id) => Some(Device::get_introspection_bind_group_layouts(
-                        pipeline_layout_guard
-                            .get(pipeline_layout_id)
-                            .map_err(|_| pipeline::CreateRenderPipelineError::InvalidLayout)?,
-                        &*bgl_guard,
-                    )),
-                    None => None,
-                };
+                if let Some(ref interface) = shader_module.interface {
+                    let provided_layouts = match desc.layout {
+                        Some(pipeline_layout_id) => {
+                            Some(Device::get_introspection_bind_group_layouts(
+                                pipeline_layout_guard.get(pipeline_layout_id).map_err(|_| {
+                                    pipeline::CreateRenderPipelineError::InvalidLayout
+                                })?,
+                                &*bgl_guard,
+                            ))
+                        }
+                        None => None,
+                    };
 
-                io = interface
-                    .check_stage(
-                        provided_layouts.as_ref().map(|p| p.as_slice()),
-                        &mut derived_group_layouts,
-                        &entry_point_name,
-                        flag,
-                        io,
-                    )
-                    .map_err(|error| pipeline::CreateRenderPipelineError::Stage { flag, error })?;
-                validated_stages |= flag;
-            }
+                    io = interface
+                        .check_stage(
+                            provided_layouts.as_ref().map(|p| p.as_slice()),
+                            &mut derived_group_layouts,
+                            &entry_point_name,
+                            flag,
+                            Some(io),
+                        )
+                        .map_err(|error| pipeline::CreateRenderPipelineError::Stage {
+                            flag,
+                            error,
+                        })?;
+                    validated_stages |= flag;
+                }
 
-            hal::pso::EntryPoint::<B> {
-                entry: &entry_point_name, // TODO
-                module: &shader_module.raw,
-                specialization: hal::pso::Specialization::EMPTY,
+                let vertex = hal::pso::EntryPoint::<B> {
+                    entry: &entry_point_name, // TODO
+                    module: &shader_module.raw,
+                    specialization: hal::pso::Specialization::EMPTY,
+                };
+                hal::pso::PrimitiveAssemblerDesc::Vertex {
+                    buffers: &vertex_buffers,
+                    attributes: &attributes,
+                    input_assembler,
+                    vertex,
+                    tessellation: None,
+                    geometry: None,
+                }
+            }
+            // Naga doesn't reflect task and mesh shaders, so these stages are not
+            // validated, and mesh pipelines need an explicit layout.
+            // The vertex stage module isn't looked up.
+            Some(pipeline::MeshStagesDescriptor { ref task, ref mesh }) => {
+                hal::pso::PrimitiveAssemblerDesc::Mesh {
+                    task: match *task {
+                        Some(ref stage) => Some(unvalidated_entry_point(
+                            &*shader_module_guard,
+                            stage,
+                            wgt::ShaderStage::TASK,
+                        )?),
+                        None => None,
+                    },
+                    mesh: unvalidated_entry_point(
+                        &*shader_module_guard,
+                        mesh,
+                        wgt::ShaderStage::MESH,
+                    )?,
+                }
             }
         };
 
@@ -2123,15 +2560,18 @@ impl<B: GfxBackend> Device<B> {
                     None => None,
                 };
 
-                if validated_stages == wgt::ShaderStage::VERTEX {
+                // Mesh shaders aren't reflected, so the fragment stage of a mesh
+                // pipeline is validated without checking its inputs.
+                if is_mesh || validated_stages == wgt::ShaderStage::VERTEX {
                     if let Some(ref interface) = shader_module.interface {
+                        let inputs = if is_mesh { None } else { Some(io) };
                         io = interface
                             .check_stage(
                                 provided_layouts.as_ref().map(|p| p.as_slice()),
                                 &mut derived_group_layouts,
                                 &entry_point_name,
                                 flag,
-                                io,
+                                inputs,
                             )
                             .map_err(|error| pipeline::CreateRenderPipelineError::Stage {
                                 flag,
@@ -2185,20 +2625,18 @@ impl<B: GfxBackend> Device<B> {
             return Err(pipeline::ImplicitLayoutError::ReflectionError(last_stage).into());
         }
 
-        let primitive_assembler = hal::pso::PrimitiveAssemblerDesc::Vertex {
-            buffers: &vertex_buffers,
-            attributes: &attributes,
-            input_assembler,
-            vertex,
-            tessellation: None,
-            geometry: None,
+        let mut flags = hal::pso::PipelineCreationFlags::empty();
+        if desc.allow_derivatives {
+            flags |= hal::pso::PipelineCreationFlags::ALLOW_DERIVATIVES;
+        }
+        let parent = match base_pipeline {
+            Some(base) => {
+                flags |= hal::pso::PipelineCreationFlags::DERIVATIVE;
+                hal::pso::BasePipeline::Pipeline(&base.raw)
+            }
+            None => hal::pso::BasePipeline::None,
         };
 
-        // TODO
-        let flags = hal::pso::PipelineCreationFlags::empty();
-        // TODO
-        let parent = hal::pso::BasePipeline::None;
-
         let (pipeline_layout_id, derived_bind_group_count) = match desc.layout {
             Some(id) => (id, 0),
             None => self.derive_pipeline_layout(
@@ -2262,12 +2700,18 @@ impl<B: GfxBackend> Device<B> {
         };
 
         let mut flags = pipeline::PipelineFlags::empty();
+        if desc.allow_derivatives {
+            flags |= pipeline::PipelineFlags::ALLOWS_DERIVATIVES;
+        }
+        if is_mesh {
+            flags |= pipeline::PipelineFlags::MESH_SHADING;
+        }
         for state in color_states.iter() {
             if state.color_blend.uses_color() | state.alpha_blend.uses_color() {
                 flags |= pipeline::PipelineFlags::BLEND_COLOR;
             }
         }
-        if let Some(ds) = depth_stencil_state.as_ref() {
+        if let Some(ds) = depth_stencil_state.filter(|_| !rasterization_state.discard) {
             if ds.stencil.is_enabled() && ds.stencil.needs_ref_value() {
                 flags |= pipeline::PipelineFlags::STENCIL_REFERENCE;
             }
@@ -2303,7 +2747,10 @@ impl<B: GfxBackend> Device<B> {
         if self.last_completed_submission_index() <= submission_index {
             tracing::info!("Waiting for submission {:?}", submission_index);
             self.lock_life(token)
-                .triage_submissions(&self.raw, true)
+                .triage_submissions(
+                    &self.raw,
+                    wgt::Maintain::WaitForSubmission(submission_index),
+                )
                 .map(|_| ())
         } else {
             Ok(())
@@ -2328,6 +2775,14 @@ impl<B: hal::Backend> Device<B> {
     }
 
     pub(crate) fn destroy_texture(&self, texture: resource::Texture<B>) {
+        for (_, raw) in texture.attachment_views.into_inner() {
+            // The device is idle, so no pass can still share the view.
+            if let Ok(raw) = Arc::try_unwrap(raw) {
+                unsafe {
+                    self.raw.destroy_image_view(raw);
+                }
+            }
+        }
         if let Some((raw, memory)) = texture.raw {
             unsafe {
                 self.mem_allocator.lock().free(&self.raw, memory);
@@ -2339,7 +2794,7 @@ impl<B: hal::Backend> Device<B> {
     /// Wait for idle and remove resources that we can, before we die.
     pub(crate) fn prepare_to_die(&mut self) {
         let mut life_tracker = self.life_tracker.lock();
-        if let Err(error) = life_tracker.triage_submissions(&self.raw, true) {
+        if let Err(error) = life_tracker.triage_submissions(&self.raw, wgt::Maintain::Wait) {
             tracing::error!("failed to triage submissions: {}", error);
         }
         life_tracker.cleanup(&self.raw, &self.mem_allocator, &self.desc_allocator);
@@ -2421,6 +2876,41 @@ pub struct ImplicitPipelineIds<'a, G: GlobalIdentityHandlerFactory> {
     pub group_ids: &'a [Input<G, id::BindGroupLayoutId>],
 }
 
+/// Updates the label of a resource, and the name of its backend object.
+///
+/// `name_raw` names the backend object, if it can, and returns the life guard
+/// holding the label. Labels are only kept in debug builds.
+fn set_resource_label<B, T, I, G>(
+    devices: &Registry<Device<B>, id::DeviceId, G>,
+    resources: &Registry<T, I, G>,
+    id: I,
+    label: &str,
+    name_raw: impl for<'a> FnOnce(&Storage<Device<B>, id::DeviceId>, &'a mut T) -> &'a mut LifeGuard,
+) -> Result<(), resource::SetLabelError>
+where
+    B: GfxBackend,
+    T: crate::hub::Resource,
+    I: id::TypedId,
+    G: GlobalIdentityHandlerFactory + crate::hub::IdentityHandlerFactory<I>,
+    Device<B>: Access<T>,
+{
+    let mut token = Token::root();
+    let (device_guard, mut token) = devices.read(&mut token);
+    let (mut resource_guard, _) = resources.write(&mut token);
+    let resource = resource_guard
+        .get_mut(id)
+        .map_err(|_| resource::SetLabelError::Invalid)?;
+    let _life_guard = name_raw(&*device_guard, resource);
+
+    #[cfg(debug_assertions)]
+    {
+        _life_guard.label = label.to_string();
+    }
+    #[cfg(not(debug_assertions))]
+    let _ = label;
+    Ok(())
+}
+
 impl<G: GlobalIdentityHandlerFactory> Global<G> {
     pub fn adapter_get_swap_chain_preferred_format<B: GfxBackend>(
         &self,
@@ -2444,6 +2934,94 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         adapter.get_swap_chain_preferred_format(surface)
     }
 
+    pub fn adapter_is_surface_supported<B: GfxBackend>(
+        &self,
+        adapter_id: id::AdapterId,
+        surface_id: id::SurfaceId,
+    ) -> Result<bool, instance::GetSurfaceCapabilitiesError> {
+        span!(_guard, INFO, "Adapter::is_surface_supported");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+
+        let (surface_guard, mut token) = self.surfaces.read(&mut token);
+        let (adapter_guard, mut _token) = hub.adapters.read(&mut token);
+        let adapter = adapter_guard
+            .get(adapter_id)
+            .map_err(|_| instance::GetSurfaceCapabilitiesError::InvalidAdapter)?;
+        let surface = surface_guard
+            .get(surface_id)
+            .map_err(|_| instance::GetSurfaceCapabilitiesError::InvalidSurface)?;
+
+        Ok(adapter.is_surface_supported(surface))
+    }
+
+    pub fn adapter_get_surface_formats<B: GfxBackend>(
+        &self,
+        adapter_id: id::AdapterId,
+        surface_id: id::SurfaceId,
+    ) -> Result<Vec<TextureFormat>, instance::GetSurfaceCapabilitiesError> {
+        span!(_guard, INFO, "Adapter::get_surface_formats");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+
+        let (mut surface_guard, mut token) = self.surfaces.write(&mut token);
+        let (adapter_guard, mut _token) = hub.adapters.read(&mut token);
+        let adapter = adapter_guard
+            .get(adapter_id)
+            .map_err(|_| instance::GetSurfaceCapabilitiesError::InvalidAdapter)?;
+        let surface = surface_guard
+            .get_mut(surface_id)
+            .map_err(|_| instance::GetSurfaceCapabilitiesError::InvalidSurface)?;
+
+        adapter.get_surface_formats(surface)
+    }
+
+    pub fn adapter_get_surface_present_modes<B: GfxBackend>(
+        &self,
+        adapter_id: id::AdapterId,
+        surface_id: id::SurfaceId,
+    ) -> Result<Vec<wgt::PresentMode>, instance::GetSurfaceCapabilitiesError> {
+        span!(_guard, INFO, "Adapter::get_surface_present_modes");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+
+        let (mut surface_guard, mut token) = self.surfaces.write(&mut token);
+        let (adapter_guard, mut _token) = hub.adapters.read(&mut token);
+        let adapter = adapter_guard
+            .get(adapter_id)
+            .map_err(|_| instance::GetSurfaceCapabilitiesError::InvalidAdapter)?;
+        let surface = surface_guard
+            .get_mut(surface_id)
+            .map_err(|_| instance::GetSurfaceCapabilitiesError::InvalidSurface)?;
+
+        adapter.get_surface_present_modes(surface)
+    }
+
+    pub fn adapter_get_surface_alpha_modes<B: GfxBackend>(
+        &self,
+        adapter_id: id::AdapterId,
+        surface_id: id::SurfaceId,
+    ) -> Result<Vec<wgt::CompositeAlphaMode>, instance::GetSurfaceCapabilitiesError> {
+        span!(_guard, INFO, "Adapter::get_surface_alpha_modes");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+
+        let (mut surface_guard, mut token) = self.surfaces.write(&mut token);
+        let (adapter_guard, mut _token) = hub.adapters.read(&mut token);
+        let adapter = adapter_guard
+            .get(adapter_id)
+            .map_err(|_| instance::GetSurfaceCapabilitiesError::InvalidAdapter)?;
+        let surface = surface_guard
+            .get_mut(surface_id)
+            .map_err(|_| instance::GetSurfaceCapabilitiesError::InvalidSurface)?;
+
+        adapter.get_surface_alpha_modes(surface)
+    }
+
     pub fn device_features<B: GfxBackend>(
         &self,
         device_id: id::DeviceId,
@@ -2472,11 +3050,491 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         Ok(device.limits.clone())
     }
 
-    pub fn device_create_buffer<B: GfxBackend>(
+    pub fn device_creation_report<B: GfxBackend>(
         &self,
         device_id: id::DeviceId,
-        desc: &resource::BufferDescriptor,
-        id_in: Input<G, id::BufferId>,
+    ) -> Result<instance::DeviceCreationReport, InvalidDevice> {
+        span!(_guard, INFO, "Device::creation_report");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (device_guard, _) = hub.devices.read(&mut token);
+        let device = device_guard.get(device_id).map_err(|_| InvalidDevice)?;
+
+        Ok(device.creation_report.clone())
+    }
+
+    /// Start a frame capture in RenderDoc.
+    ///
+    /// Does nothing unless the `capture` feature is enabled and the application
+    /// runs under RenderDoc. Only one capture can be active at a time.
+    pub fn device_start_capture<B: GfxBackend>(
+        &self,
+        device_id: id::DeviceId,
+    ) -> Result<(), InvalidDevice> {
+        span!(_guard, INFO, "Device::start_capture");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (device_guard, _) = hub.devices.read(&mut token);
+        let _device = device_guard.get(device_id).map_err(|_| InvalidDevice)?;
+
+        #[cfg(feature = "capture")]
+        match _device.renderdoc {
+            Some(ref rd) => rd.lock().0.start_frame_capture(ptr::null(), ptr::null()),
+            None => tracing::warn!("Unable to start a capture: RenderDoc is not attached"),
+        }
+        Ok(())
+    }
+
+    /// Finish the capture started with [`Global::device_start_capture`].
+    pub fn device_stop_capture<B: GfxBackend>(
+        &self,
+        device_id: id::DeviceId,
+    ) -> Result<(), InvalidDevice> {
+        span!(_guard, INFO, "Device::stop_capture");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (device_guard, _) = hub.devices.read(&mut token);
+        let _device = device_guard.get(device_id).map_err(|_| InvalidDevice)?;
+
+        #[cfg(feature = "capture")]
+        if let Some(ref rd) = _device.renderdoc {
+            rd.lock().0.end_frame_capture(ptr::null(), ptr::null());
+        }
+        Ok(())
+    }
+
+    /// Give temporary access to the backend device.
+    ///
+    /// The callback gets `None` if the device is invalid.
+    ///
+    /// The callback runs while the device storage is locked for reading. It must
+    /// not call back into this `Global`: that panics in debug builds, and can
+    /// deadlock otherwise, e.g. when creating a resource on another thread.
+    ///
+    /// # Safety
+    ///
+    /// The backend object must not be destroyed, and any state changed through it
+    /// has to be restored before the callback returns, since wgpu keeps using it.
+    pub unsafe fn device_as_hal<B: GfxBackend, F: FnOnce(Option<&B::Device>) -> R, R>(
+        &self,
+        device_id: id::DeviceId,
+        hal_device_callback: F,
+    ) -> R {
+        span!(_guard, INFO, "Device::as_hal");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (device_guard, _) = hub.devices.read(&mut token);
+        let hal_device = device_guard.get(device_id).ok().map(|device| &device.raw);
+
+        hal_device_callback(hal_device)
+    }
+
+    /// Give temporary access to the backend buffer.
+    ///
+    /// The callback gets `None` if the buffer is invalid or destroyed.
+    ///
+    /// The buffer storage stays locked for reading during the callback, so the
+    /// same restriction as in [`Global::device_as_hal`] applies: no calls into
+    /// this `Global` from the callback.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Global::device_as_hal`]. The usage tracking of wgpu doesn't
+    /// know about the commands recorded on the buffer outside of it.
+    pub unsafe fn buffer_as_hal<B: GfxBackend, F: FnOnce(Option<&B::Buffer>) -> R, R>(
+        &self,
+        buffer_id: id::BufferId,
+        hal_buffer_callback: F,
+    ) -> R {
+        span!(_guard, INFO, "Buffer::as_hal");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (buffer_guard, _) = hub.buffers.read(&mut token);
+        let hal_buffer = buffer_guard
+            .get(buffer_id)
+            .ok()
+            .and_then(|buffer| buffer.raw.as_ref())
+            .map(|&(ref raw, _)| raw);
+
+        hal_buffer_callback(hal_buffer)
+    }
+
+    /// Give temporary access to the backend image of a texture.
+    ///
+    /// The callback gets `None` if the texture is invalid or destroyed.
+    ///
+    /// The callback runs under the read lock of the texture storage, and must not
+    /// call into this `Global`, see [`Global::device_as_hal`].
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Global::buffer_as_hal`]. The image layout has to be restored as well.
+    pub unsafe fn texture_as_hal<B: GfxBackend, F: FnOnce(Option<&B::Image>) -> R, R>(
+        &self,
+        texture_id: id::TextureId,
+        hal_texture_callback: F,
+    ) -> R {
+        span!(_guard, INFO, "Texture::as_hal");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (texture_guard, _) = hub.textures.read(&mut token);
+        let hal_texture = texture_guard
+            .get(texture_id)
+            .ok()
+            .and_then(|texture| texture.raw.as_ref())
+            .map(|&(ref raw, _)| raw);
+
+        hal_texture_callback(hal_texture)
+    }
+
+    /// Give temporary access to the backend image view of a texture view.
+    ///
+    /// The callback gets `None` if the view is invalid, or belongs to a swap chain.
+    ///
+    /// The callback runs under the read lock of the texture view storage, and must
+    /// not call into this `Global`, see [`Global::device_as_hal`].
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Global::device_as_hal`].
+    pub unsafe fn texture_view_as_hal<B: GfxBackend, F: FnOnce(Option<&B::ImageView>) -> R, R>(
+        &self,
+        texture_view_id: id::TextureViewId,
+        hal_texture_view_callback: F,
+    ) -> R {
+        span!(_guard, INFO, "TextureView::as_hal");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (texture_view_guard, _) = hub.texture_views.read(&mut token);
+        let hal_texture_view = texture_view_guard
+            .get(texture_view_id)
+            .ok()
+            .and_then(|view| match view.inner {
+                resource::TextureViewInner::Native { ref raw, .. } => Some(raw),
+                resource::TextureViewInner::SwapChain { .. }
+                | resource::TextureViewInner::Attachment { .. } => None,
+            });
+
+        hal_texture_view_callback(hal_texture_view)
+    }
+
+    /// Give temporary access to the backend sampler.
+    ///
+    /// The callback gets `None` if the sampler is invalid.
+    ///
+    /// The callback runs under the read lock of the sampler storage, and must not
+    /// call into this `Global`, see [`Global::device_as_hal`].
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Global::device_as_hal`].
+    pub unsafe fn sampler_as_hal<B: GfxBackend, F: FnOnce(Option<&B::Sampler>) -> R, R>(
+        &self,
+        sampler_id: id::SamplerId,
+        hal_sampler_callback: F,
+    ) -> R {
+        span!(_guard, INFO, "Sampler::as_hal");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (sampler_guard, _) = hub.samplers.read(&mut token);
+        let hal_sampler = sampler_guard
+            .get(sampler_id)
+            .ok()
+            .map(|sampler| &sampler.raw);
+
+        hal_sampler_callback(hal_sampler)
+    }
+
+    /// Give temporary access to the backend descriptor set layout of a bind group layout.
+    ///
+    /// The callback gets `None` if the bind group layout is invalid.
+    ///
+    /// The callback runs under the read lock of the bind group layout storage, and must not
+    /// call into this `Global`, see [`Global::device_as_hal`].
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Global::device_as_hal`].
+    pub unsafe fn bind_group_layout_as_hal<
+        B: GfxBackend,
+        F: FnOnce(Option<&B::DescriptorSetLayout>) -> R,
+        R,
+    >(
+        &self,
+        bind_group_layout_id: id::BindGroupLayoutId,
+        hal_bind_group_layout_callback: F,
+    ) -> R {
+        span!(_guard, INFO, "BindGroupLayout::as_hal");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (bind_group_layout_guard, _) = hub.bind_group_layouts.read(&mut token);
+        let hal_bind_group_layout = bind_group_layout_guard
+            .get(bind_group_layout_id)
+            .ok()
+            .map(|layout| &layout.raw);
+
+        hal_bind_group_layout_callback(hal_bind_group_layout)
+    }
+
+    /// Give temporary access to the backend pipeline layout.
+    ///
+    /// The callback gets `None` if the pipeline layout is invalid.
+    ///
+    /// The callback runs under the read lock of the pipeline layout storage, and must not
+    /// call into this `Global`, see [`Global::device_as_hal`].
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Global::device_as_hal`].
+    pub unsafe fn pipeline_layout_as_hal<
+        B: GfxBackend,
+        F: FnOnce(Option<&B::PipelineLayout>) -> R,
+        R,
+    >(
+        &self,
+        pipeline_layout_id: id::PipelineLayoutId,
+        hal_pipeline_layout_callback: F,
+    ) -> R {
+        span!(_guard, INFO, "PipelineLayout::as_hal");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (pipeline_layout_guard, _) = hub.pipeline_layouts.read(&mut token);
+        let hal_pipeline_layout = pipeline_layout_guard
+            .get(pipeline_layout_id)
+            .ok()
+            .map(|layout| &layout.raw);
+
+        hal_pipeline_layout_callback(hal_pipeline_layout)
+    }
+
+    /// Give temporary access to the backend descriptor set of a bind group.
+    ///
+    /// The callback gets `None` if the bind group is invalid.
+    ///
+    /// The callback runs under the read lock of the bind group storage, and must not
+    /// call into this `Global`, see [`Global::device_as_hal`].
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Global::device_as_hal`].
+    /// The descriptor set must not be updated, since wgpu tracks the resources bound to it.
+    pub unsafe fn bind_group_as_hal<B: GfxBackend, F: FnOnce(Option<&B::DescriptorSet>) -> R, R>(
+        &self,
+        bind_group_id: id::BindGroupId,
+        hal_bind_group_callback: F,
+    ) -> R {
+        span!(_guard, INFO, "BindGroup::as_hal");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (bind_group_guard, _) = hub.bind_groups.read(&mut token);
+        let hal_bind_group = bind_group_guard
+            .get(bind_group_id)
+            .ok()
+            .map(|bind_group| bind_group.raw.raw());
+
+        hal_bind_group_callback(hal_bind_group)
+    }
+
+    /// Give temporary access to the backend shader module.
+    ///
+    /// The callback gets `None` if the shader module is invalid.
+    ///
+    /// The callback runs under the read lock of the shader module storage, and must not
+    /// call into this `Global`, see [`Global::device_as_hal`].
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Global::device_as_hal`].
+    pub unsafe fn shader_module_as_hal<
+        B: GfxBackend,
+        F: FnOnce(Option<&B::ShaderModule>) -> R,
+        R,
+    >(
+        &self,
+        shader_module_id: id::ShaderModuleId,
+        hal_shader_module_callback: F,
+    ) -> R {
+        span!(_guard, INFO, "ShaderModule::as_hal");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (shader_module_guard, _) = hub.shader_modules.read(&mut token);
+        let hal_shader_module = shader_module_guard
+            .get(shader_module_id)
+            .ok()
+            .map(|module| &module.raw);
+
+        hal_shader_module_callback(hal_shader_module)
+    }
+
+    /// Give temporary access to the backend graphics pipeline of a render pipeline.
+    ///
+    /// The callback gets `None` if the render pipeline is invalid.
+    ///
+    /// The callback runs under the read lock of the render pipeline storage, and must not
+    /// call into this `Global`, see [`Global::device_as_hal`].
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Global::device_as_hal`].
+    pub unsafe fn render_pipeline_as_hal<
+        B: GfxBackend,
+        F: FnOnce(Option<&B::GraphicsPipeline>) -> R,
+        R,
+    >(
+        &self,
+        render_pipeline_id: id::RenderPipelineId,
+        hal_render_pipeline_callback: F,
+    ) -> R {
+        span!(_guard, INFO, "RenderPipeline::as_hal");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (render_pipeline_guard, _) = hub.render_pipelines.read(&mut token);
+        let hal_render_pipeline = render_pipeline_guard
+            .get(render_pipeline_id)
+            .ok()
+            .map(|pipeline| &pipeline.raw);
+
+        hal_render_pipeline_callback(hal_render_pipeline)
+    }
+
+    /// Give temporary access to the backend compute pipeline.
+    ///
+    /// The callback gets `None` if the compute pipeline is invalid.
+    ///
+    /// The callback runs under the read lock of the compute pipeline storage, and must not
+    /// call into this `Global`, see [`Global::device_as_hal`].
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Global::device_as_hal`].
+    pub unsafe fn compute_pipeline_as_hal<
+        B: GfxBackend,
+        F: FnOnce(Option<&B::ComputePipeline>) -> R,
+        R,
+    >(
+        &self,
+        compute_pipeline_id: id::ComputePipelineId,
+        hal_compute_pipeline_callback: F,
+    ) -> R {
+        span!(_guard, INFO, "ComputePipeline::as_hal");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (compute_pipeline_guard, _) = hub.compute_pipelines.read(&mut token);
+        let hal_compute_pipeline = compute_pipeline_guard
+            .get(compute_pipeline_id)
+            .ok()
+            .map(|pipeline| &pipeline.raw);
+
+        hal_compute_pipeline_callback(hal_compute_pipeline)
+    }
+
+    /// Give temporary access to the backend query pool of a query set.
+    ///
+    /// The callback gets `None` if the query set is invalid.
+    ///
+    /// The callback runs under the read lock of the query set storage, and must not
+    /// call into this `Global`, see [`Global::device_as_hal`].
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Global::device_as_hal`].
+    pub unsafe fn query_set_as_hal<B: GfxBackend, F: FnOnce(Option<&B::QueryPool>) -> R, R>(
+        &self,
+        query_set_id: id::QuerySetId,
+        hal_query_set_callback: F,
+    ) -> R {
+        span!(_guard, INFO, "QuerySet::as_hal");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (query_set_guard, _) = hub.query_sets.read(&mut token);
+        let hal_query_set = query_set_guard
+            .get(query_set_id)
+            .ok()
+            .map(|query_set| &query_set.raw);
+
+        hal_query_set_callback(hal_query_set)
+    }
+
+    /// Enables or disables the per-draw validation of render passes recorded on this device.
+    ///
+    /// Validation is enabled by default. The switch is ignored in debug builds,
+    /// where everything is always validated.
+    ///
+    /// # Safety
+    ///
+    /// With validation disabled, draws without a pipeline, with missing vertex buffers
+    /// or bind groups, or outside of the bound buffer ranges reach the driver as is,
+    /// which is undefined behavior. Only use this for trusted content.
+    pub unsafe fn device_set_draw_validation<B: GfxBackend>(
+        &self,
+        device_id: id::DeviceId,
+        enabled: bool,
+    ) -> Result<(), InvalidDevice> {
+        span!(_guard, INFO, "Device::set_draw_validation");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (mut device_guard, _) = hub.devices.write(&mut token);
+        let device = device_guard.get_mut(device_id).map_err(|_| InvalidDevice)?;
+        device.skip_draw_validation = !enabled;
+        Ok(())
+    }
+
+    /// Sets the bounds on the work recorded into the command encoders and render bundles
+    /// created on this device from now on.
+    pub fn device_set_recording_limits<B: GfxBackend>(
+        &self,
+        device_id: id::DeviceId,
+        limits: command::RecordingLimits,
+    ) -> Result<(), InvalidDevice> {
+        span!(_guard, INFO, "Device::set_recording_limits");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (mut device_guard, _) = hub.devices.write(&mut token);
+        let device = device_guard.get_mut(device_id).map_err(|_| InvalidDevice)?;
+        device.recording_limits = limits;
+        Ok(())
+    }
+
+    /// Returns the bounds on the work recorded on this device.
+    ///
+    /// Passes and render bundle encoders are created without a device, so the
+    /// limits have to be handed to their `set_recording_limits` for the commands
+    /// to be bounded while they are recorded, not only when they are run.
+    pub fn device_recording_limits<B: GfxBackend>(
+        &self,
+        device_id: id::DeviceId,
+    ) -> Result<command::RecordingLimits, InvalidDevice> {
+        span!(_guard, INFO, "Device::recording_limits");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (device_guard, _) = hub.devices.read(&mut token);
+        let device = device_guard.get(device_id).map_err(|_| InvalidDevice)?;
+        Ok(device.recording_limits.clone())
+    }
+
+    pub fn device_create_buffer<B: GfxBackend>(
+        &self,
+        device_id: id::DeviceId,
+        desc: &resource::BufferDescriptor,
+        id_in: Input<G, id::BufferId>,
     ) -> (id::BufferId, Option<resource::CreateBufferError>) {
         span!(_guard, INFO, "Device::create_buffer");
 
@@ -2565,6 +3623,8 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     stage_buffer,
                     stage_memory,
                 };
+                // the whole buffer gets copied from the zeroed stage on unmap
+                buffer.needs_zero_init = false;
                 resource::BufferUse::COPY_DST
             };
 
@@ -2692,6 +3752,28 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         B::hub(self).buffers.label_for_resource(id)
     }
 
+    pub fn buffer_set_label<B: GfxBackend>(
+        &self,
+        buffer_id: id::BufferId,
+        label: &str,
+    ) -> Result<(), resource::SetLabelError> {
+        span!(_guard, INFO, "Buffer::set_label");
+
+        let hub = B::hub(self);
+        set_resource_label(
+            &hub.devices,
+            &hub.buffers,
+            buffer_id,
+            |device_guard, buffer| {
+                if let Some((ref mut raw, _)) = buffer.raw {
+                    let device = &device_guard[buffer.device_id.value];
+                    unsafe { device.raw.set_buffer_name(raw, label) };
+                }
+                &mut buffer.life_guard
+            },
+        )
+    }
+
     pub fn buffer_destroy<B: GfxBackend>(
         &self,
         buffer_id: id::BufferId,
@@ -2842,6 +3924,43 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         B::hub(self).textures.label_for_resource(id)
     }
 
+    pub fn texture_set_label<B: GfxBackend>(
+        &self,
+        texture_id: id::TextureId,
+        label: &str,
+    ) -> Result<(), resource::SetLabelError> {
+        span!(_guard, INFO, "Texture::set_label");
+
+        let hub = B::hub(self);
+        set_resource_label(
+            &hub.devices,
+            &hub.textures,
+            texture_id,
+            |device_guard, texture| {
+                if let Some((ref mut raw, _)) = texture.raw {
+                    let device = &device_guard[texture.device_id.value];
+                    unsafe { device.raw.set_image_name(raw, label) };
+                }
+                &mut texture.life_guard
+            },
+        )
+    }
+
+    /// Returns the number of views cached by a texture for the subresources
+    /// attached to render passes. They are released when it's destroyed.
+    pub fn texture_attachment_view_count<B: GfxBackend>(
+        &self,
+        texture_id: id::TextureId,
+    ) -> Result<usize, resource::InvalidTexture> {
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (texture_guard, _) = hub.textures.read(&mut token);
+        let texture = texture_guard
+            .get(texture_id)
+            .map_err(|_| resource::InvalidTexture)?;
+        Ok(texture.attachment_views.lock().len())
+    }
+
     pub fn texture_destroy<B: GfxBackend>(
         &self,
         texture_id: id::TextureId,
@@ -2872,18 +3991,20 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             .take()
             .ok_or(resource::DestroyError::AlreadyDestroyed)?;
         let temp = queue::TempResource::Image(raw);
+        let attachment_views = mem::take(texture.attachment_views.get_mut());
+        let last_submit_index = texture.life_guard.submission_index.load(Ordering::Acquire);
+        drop(texture_guard);
 
+        let mut life_lock = Device::lock_life_internal(&device.life_tracker, &mut token);
         if device.pending_writes.dst_textures.contains(&texture_id) {
             device.pending_writes.temp_resources.push((temp, memory));
         } else {
-            let last_submit_index = texture.life_guard.submission_index.load(Ordering::Acquire);
-            drop(texture_guard);
-            device.lock_life(&mut token).schedule_resource_destruction(
-                temp,
-                memory,
-                last_submit_index,
-            );
+            life_lock.schedule_resource_destruction(temp, memory, last_submit_index);
         }
+        life_lock.schedule_attachment_views_destruction(
+            attachment_views.into_iter().map(|(_, raw)| raw),
+            last_submit_index,
+        );
 
         Ok(())
     }
@@ -2992,6 +4113,27 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         B::hub(self).texture_views.label_for_resource(id)
     }
 
+    /// Labels are only kept in debug builds. Since image views can't be named in
+    /// gfx-hal, this has no effect in release builds.
+    pub fn texture_view_set_label<B: GfxBackend>(
+        &self,
+        texture_view_id: id::TextureViewId,
+        label: &str,
+    ) -> Result<(), resource::SetLabelError> {
+        span!(_guard, INFO, "TextureView::set_label");
+
+        let hub = B::hub(self);
+        set_resource_label(
+            &hub.devices,
+            &hub.texture_views,
+            texture_view_id,
+            |_, texture_view| {
+                //TODO: gfx-hal can't name image views, only the label used by wgpu is updated.
+                &mut texture_view.life_guard
+            },
+        )
+    }
+
     pub fn texture_view_drop<B: GfxBackend>(
         &self,
         texture_view_id: id::TextureViewId,
@@ -3018,6 +4160,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         resource::TextureViewInner::SwapChain { .. } => {
                             return Err(resource::TextureViewDestroyError::SwapChainImage)
                         }
+                        resource::TextureViewInner::Attachment { .. } => unreachable!(),
                     };
                     (last_submit_index, device_id)
                 }
@@ -3280,6 +4423,33 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         (id, Some(error))
     }
 
+    /// Stable hash of a pipeline layout descriptor, to key pipeline caches.
+    ///
+    /// Bind group layouts are hashed by their entries rather than by ID, so the
+    /// same layout hashes the same in every process.
+    pub fn pipeline_layout_stable_hash<B: GfxBackend>(
+        &self,
+        desc: &binding_model::PipelineLayoutDescriptor,
+    ) -> Result<u64, pipeline::DescriptorHashError> {
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (bgl_guard, _) = hub.bind_group_layouts.read(&mut token);
+        let bgl_hashes = desc
+            .bind_group_layouts
+            .iter()
+            .map(|&id| {
+                bgl_guard
+                    .get(id)
+                    .map(|bgl| binding_model::hash_bind_group_layout_entries(bgl.entries.values()))
+                    .map_err(|_| pipeline::DescriptorHashError::InvalidBindGroupLayout(id))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(binding_model::hash_pipeline_layout(
+            &bgl_hashes,
+            &desc.push_constant_ranges,
+        ))
+    }
+
     pub fn pipeline_layout_label<B: GfxBackend>(&self, id: id::PipelineLayoutId) -> String {
         B::hub(self).pipeline_layouts.label_for_resource(id)
     }
@@ -3385,6 +4555,30 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         B::hub(self).bind_groups.label_for_resource(id)
     }
 
+    pub fn bind_group_set_label<B: GfxBackend>(
+        &self,
+        bind_group_id: id::BindGroupId,
+        label: &str,
+    ) -> Result<(), resource::SetLabelError> {
+        span!(_guard, INFO, "BindGroup::set_label");
+
+        let hub = B::hub(self);
+        set_resource_label(
+            &hub.devices,
+            &hub.bind_groups,
+            bind_group_id,
+            |device_guard, bind_group| {
+                let device = &device_guard[bind_group.device_id.value];
+                unsafe {
+                    device
+                        .raw
+                        .set_descriptor_set_name(bind_group.raw.raw_mut(), label)
+                };
+                &mut bind_group.life_guard
+            },
+        )
+    }
+
     pub fn bind_group_drop<B: GfxBackend>(&self, bind_group_id: id::BindGroupId) {
         span!(_guard, INFO, "BindGroup::drop");
 
@@ -3538,7 +4732,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 dev_stored,
                 &device.raw,
                 device.limits.clone(),
+                device.features,
                 device.private_features,
+                device.recording_limits.clone(),
                 &desc.label,
                 #[cfg(feature = "trace")]
                 device.trace.is_some(),
@@ -3925,10 +5121,57 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         (id, Some(error))
     }
 
+    /// Stable hash of a render pipeline descriptor, to key pipeline caches.
+    ///
+    /// The layout and the shader modules are hashed by contents rather than by ID,
+    /// so the same pipeline hashes the same in every process. Modules created from
+    /// a Naga module can't be hashed.
+    pub fn render_pipeline_stable_hash<B: GfxBackend>(
+        &self,
+        desc: &pipeline::RenderPipelineDescriptor,
+    ) -> Result<u64, pipeline::DescriptorHashError> {
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (pipeline_layout_guard, mut token) = hub.pipeline_layouts.read(&mut token);
+        let (bgl_guard, mut token) = hub.bind_group_layouts.read(&mut token);
+        let (shader_module_guard, _) = hub.shader_modules.read(&mut token);
+        let layout_hash = desc
+            .layout
+            .map(|id| pipeline_layout_stable_hash(id, &*pipeline_layout_guard, &*bgl_guard))
+            .transpose()?;
+        desc.hash_contents(layout_hash, |id| {
+            shader_module_stable_hash(id, &*shader_module_guard)
+        })
+    }
+
     pub fn render_pipeline_label<B: GfxBackend>(&self, id: id::RenderPipelineId) -> String {
         B::hub(self).render_pipelines.label_for_resource(id)
     }
 
+    pub fn render_pipeline_set_label<B: GfxBackend>(
+        &self,
+        render_pipeline_id: id::RenderPipelineId,
+        label: &str,
+    ) -> Result<(), resource::SetLabelError> {
+        span!(_guard, INFO, "RenderPipeline::set_label");
+
+        let hub = B::hub(self);
+        set_resource_label(
+            &hub.devices,
+            &hub.render_pipelines,
+            render_pipeline_id,
+            |device_guard, render_pipeline| {
+                let device = &device_guard[render_pipeline.device_id.value];
+                unsafe {
+                    device
+                        .raw
+                        .set_graphics_pipeline_name(&mut render_pipeline.raw, label)
+                };
+                &mut render_pipeline.life_guard
+            },
+        )
+    }
+
     pub fn render_pipeline_drop<B: GfxBackend>(&self, render_pipeline_id: id::RenderPipelineId) {
         span!(_guard, INFO, "RenderPipeline::drop");
         let hub = B::hub(self);
@@ -4056,10 +5299,55 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         (id, Some(error))
     }
 
+    /// Stable hash of a compute pipeline descriptor, to key pipeline caches.
+    ///
+    /// See [`Global::render_pipeline_stable_hash`].
+    pub fn compute_pipeline_stable_hash<B: GfxBackend>(
+        &self,
+        desc: &pipeline::ComputePipelineDescriptor,
+    ) -> Result<u64, pipeline::DescriptorHashError> {
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (pipeline_layout_guard, mut token) = hub.pipeline_layouts.read(&mut token);
+        let (bgl_guard, mut token) = hub.bind_group_layouts.read(&mut token);
+        let (shader_module_guard, _) = hub.shader_modules.read(&mut token);
+        let layout_hash = desc
+            .layout
+            .map(|id| pipeline_layout_stable_hash(id, &*pipeline_layout_guard, &*bgl_guard))
+            .transpose()?;
+        desc.hash_contents(layout_hash, |id| {
+            shader_module_stable_hash(id, &*shader_module_guard)
+        })
+    }
+
     pub fn compute_pipeline_label<B: GfxBackend>(&self, id: id::ComputePipelineId) -> String {
         B::hub(self).compute_pipelines.label_for_resource(id)
     }
 
+    pub fn compute_pipeline_set_label<B: GfxBackend>(
+        &self,
+        compute_pipeline_id: id::ComputePipelineId,
+        label: &str,
+    ) -> Result<(), resource::SetLabelError> {
+        span!(_guard, INFO, "ComputePipeline::set_label");
+
+        let hub = B::hub(self);
+        set_resource_label(
+            &hub.devices,
+            &hub.compute_pipelines,
+            compute_pipeline_id,
+            |device_guard, compute_pipeline| {
+                let device = &device_guard[compute_pipeline.device_id.value];
+                unsafe {
+                    device
+                        .raw
+                        .set_compute_pipeline_name(&mut compute_pipeline.raw, label)
+                };
+                &mut compute_pipeline.life_guard
+            },
+        )
+    }
+
     pub fn compute_pipeline_drop<B: GfxBackend>(&self, compute_pipeline_id: id::ComputePipelineId) {
         span!(_guard, INFO, "ComputePipeline::drop");
         let hub = B::hub(self);
@@ -4103,6 +5391,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         fn validate_swap_chain_descriptor(
             config: &mut hal::window::SwapchainConfig,
             caps: &hal::window::SurfaceCapabilities,
+            present_mode: wgt::PresentMode,
         ) -> Result<(), swap_chain::CreateSwapChainError> {
             let width = config.extent.width;
             let height = config.extent.height;
@@ -4119,20 +5408,60 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 );
             }
             if !caps.present_modes.contains(config.present_mode) {
-                tracing::warn!(
-                    "Surface does not support present mode: {:?}, falling back to {:?}",
-                    config.present_mode,
-                    hal::window::PresentMode::FIFO
-                );
-                config.present_mode = hal::window::PresentMode::FIFO;
+                let fallback = match present_mode {
+                    wgt::PresentMode::AutoNoVsync
+                        if caps
+                            .present_modes
+                            .contains(hal::window::PresentMode::MAILBOX) =>
+                    {
+                        hal::window::PresentMode::MAILBOX
+                    }
+                    _ => hal::window::PresentMode::FIFO,
+                };
+                match present_mode {
+                    wgt::PresentMode::AutoVsync | wgt::PresentMode::AutoNoVsync => {}
+                    _ => tracing::warn!(
+                        "Surface does not support present mode: {:?}, falling back to {:?}",
+                        config.present_mode,
+                        fallback
+                    ),
+                }
+                config.present_mode = fallback;
             }
             if width == 0 || height == 0 {
                 return Err(swap_chain::CreateSwapChainError::ZeroArea);
             }
+            if !caps
+                .composite_alpha_modes
+                .contains(config.composite_alpha_mode)
+            {
+                return Err(swap_chain::CreateSwapChainError::UnsupportedAlphaMode {
+                    requested: config.composite_alpha_mode,
+                    available: caps.composite_alpha_modes,
+                });
+            }
+            if !caps.usage.contains(config.image_usage) {
+                return Err(swap_chain::CreateSwapChainError::UnsupportedUsage {
+                    requested: config.image_usage,
+                    available: caps.usage,
+                });
+            }
             Ok(())
         }
 
         tracing::info!("creating swap chain {:?}", desc);
+        if !desc.usage.contains(wgt::TextureUsage::RENDER_ATTACHMENT) {
+            return Err(swap_chain::CreateSwapChainError::MissingRenderAttachmentUsage(desc.usage));
+        }
+        // Frames are only exposed as views, which can't be copied from and are
+        // rejected by bind groups, so any other usage couldn't be exercised.
+        let unusable = desc.usage - wgt::TextureUsage::RENDER_ATTACHMENT;
+        if !unusable.is_empty() {
+            return Err(swap_chain::CreateSwapChainError::UnusableFrameUsage(
+                unusable,
+            ));
+        }
+
         let hub = B::hub(self);
         let mut token = Token::root();
 
@@ -4158,7 +5487,11 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             let caps = surface.capabilities(&adapter.raw.physical_device);
             (caps, formats)
         };
-        let num_frames = swap_chain::DESIRED_NUM_FRAMES
+        // One frame is being rendered while the others wait for presentation.
+        let num_frames = desc
+            .desired_maximum_frame_latency
+            .max(1)
+            .saturating_add(1)
             .max(*caps.image_count.start())
             .min(*caps.image_count.end());
         let mut config =
@@ -4171,7 +5504,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 });
             }
         }
-        validate_swap_chain_descriptor(&mut config, &caps)?;
+        validate_swap_chain_descriptor(&mut config, &caps, desc.present_mode)?;
         let framebuffer_attachment = config.framebuffer_attachment();
 
         unsafe {
@@ -4215,6 +5548,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             acquired_view_id: None,
             active_submission_index: 0,
             framebuffer_attachment,
+            invalidated: false,
         };
         swap_chain_guard.insert(sc_id, swap_chain);
         Ok(sc_id)
@@ -4244,7 +5578,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
     pub fn device_poll<B: GfxBackend>(
         &self,
         device_id: id::DeviceId,
-        force_wait: bool,
+        maintain: wgt::Maintain<SubmissionIndex>,
     ) -> Result<(), WaitIdleError> {
         span!(_guard, INFO, "Device::poll");
 
@@ -4255,16 +5589,16 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             device_guard
                 .get(device_id)
                 .map_err(|_| DeviceError::Invalid)?
-                .maintain(&hub, force_wait, &mut token)?
+                .maintain(&hub, maintain, &mut token)?
         };
-        fire_map_callbacks(callbacks);
+        callbacks.fire();
         Ok(())
     }
 
     fn poll_devices<B: GfxBackend>(
         &self,
-        force_wait: bool,
-        callbacks: &mut Vec<BufferMapPendingCallback>,
+        maintain: wgt::Maintain<SubmissionIndex>,
+        callbacks: &mut UserClosures,
     ) -> Result<(), WaitIdleError> {
         span!(_guard, INFO, "Device::poll_devices");
 
@@ -4272,34 +5606,41 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         let mut token = Token::root();
         let (device_guard, mut token) = hub.devices.read(&mut token);
         for (_, device) in device_guard.iter(B::VARIANT) {
-            let cbs = device.maintain(&hub, force_wait, &mut token)?;
+            let cbs = device.maintain(&hub, maintain, &mut token)?;
             callbacks.extend(cbs);
         }
         Ok(())
     }
 
-    pub fn poll_all_devices(&self, force_wait: bool) -> Result<(), WaitIdleError> {
+    /// Polls every device of every backend, see [`Global::device_poll`].
+    ///
+    /// Submission indices are counted per device, so `WaitForSubmission(index)`
+    /// waits for the submission with that index on each device that has one.
+    pub fn poll_all_devices(
+        &self,
+        maintain: wgt::Maintain<SubmissionIndex>,
+    ) -> Result<(), WaitIdleError> {
         use crate::backend;
-        let mut callbacks = Vec::new();
+        let mut callbacks = UserClosures::default();
 
         #[cfg(vulkan)]
         {
-            self.poll_devices::<backend::Vulkan>(force_wait, &mut callbacks)?;
+            self.poll_devices::<backend::Vulkan>(maintain, &mut callbacks)?;
         }
         #[cfg(metal)]
         {
-            self.poll_devices::<backend::Metal>(force_wait, &mut callbacks)?;
+            self.poll_devices::<backend::Metal>(maintain, &mut callbacks)?;
         }
         #[cfg(dx12)]
         {
-            self.poll_devices::<backend::Dx12>(force_wait, &mut callbacks)?;
+            self.poll_devices::<backend::Dx12>(maintain, &mut callbacks)?;
         }
         #[cfg(dx11)]
         {
-            self.poll_devices::<backend::Dx11>(force_wait, &mut callbacks)?;
+            self.poll_devices::<backend::Dx11>(maintain, &mut callbacks)?;
         }
 
-        fire_map_callbacks(callbacks);
+        callbacks.fire();
 
         Ok(())
     }
@@ -6,20 +6,23 @@
 use crate::device::trace::Action;
 use crate::{
     command::{
-        texture_copy_view_to_hal, validate_linear_texture_data, validate_texture_copy_range,
-        CommandAllocator, CommandBuffer, CopySide, TextureCopyView, TransferError, BITS_PER_BYTE,
+        copy_bytes_per_block, texture_copy_view_to_hal, validate_linear_texture_data,
+        validate_texture_copy_range, CommandAllocator, CommandBuffer, CopySide, TextureCopyView,
+        TransferError,
     },
     conv,
     device::{alloc, DeviceError, WaitIdleError},
-    hub::{GfxBackend, Global, GlobalIdentityHandlerFactory, Token},
+    hub::{GfxBackend, Global, GlobalIdentityHandlerFactory, Hub, Storage, Token},
     id,
-    resource::{BufferAccessError, BufferMapState, BufferUse, TextureUse},
-    span, FastHashSet,
+    resource::{Buffer, BufferAccessError, BufferMapState, BufferUse, Texture, TextureUse},
+    span,
+    track::TrackerSet,
+    FastHashSet, SubmissionIndex,
 };
 
 use hal::{command::CommandBuffer as _, device::Device as _, queue::CommandQueue as _};
 use smallvec::SmallVec;
-use std::{iter, ptr};
+use std::{iter, mem, ptr};
 use thiserror::Error;
 
 struct StagingData<B: hal::Backend> {
@@ -28,6 +31,94 @@ struct StagingData<B: hal::Backend> {
     cmdbuf: B::CommandBuffer,
 }
 
+/// Staging buffer filled with zeros, large enough for any subresource of a texture.
+/// Used to initialize the textures that can't be cleared, i.e. compressed ones.
+struct ZeroStage<B: hal::Backend> {
+    buffer: B::Buffer,
+    memory: alloc::MemoryBlock<B>,
+    /// Row length of the buffer, in texels.
+    buffer_width: u32,
+    /// Number of rows in a single image of the buffer, in texels.
+    buffer_height: u32,
+}
+
+/// Mapped staging buffer handed to the user by `queue_write_buffer_with`.
+///
+/// If the write doesn't reach the point where the buffer is staged, e.g. because
+/// the filling closure panics, the buffer is unmapped and freed on drop.
+struct MappedStage<'a, B: hal::Backend, G: GlobalIdentityHandlerFactory> {
+    hub: &'a Hub<B, G>,
+    device_id: id::DeviceId,
+    stage: Option<(B::Buffer, alloc::MemoryBlock<B>)>,
+}
+
+impl<B: hal::Backend, G: GlobalIdentityHandlerFactory> MappedStage<'_, B, G> {
+    fn take(mut self) -> (B::Buffer, alloc::MemoryBlock<B>) {
+        self.stage.take().unwrap()
+    }
+}
+
+impl<B: hal::Backend, G: GlobalIdentityHandlerFactory> Drop for MappedStage<'_, B, G> {
+    fn drop(&mut self) {
+        if let Some((buffer, mut memory)) = self.stage.take() {
+            let mut token = Token::root();
+            let (device_guard, _) = self.hub.devices.read(&mut token);
+            // If the device is gone, its memory went away with it.
+            if let Ok(device) = device_guard.get(self.device_id) {
+                memory.unmap(&device.raw);
+                device.free_staging_buffer(buffer, memory);
+            }
+        }
+    }
+}
+
+pub type SubmittedWorkDoneCallback = unsafe extern "C" fn(user_data: *mut u8);
+
+/// A callback to be fired once all the work submitted to the queue
+/// before it was registered is done on the GPU.
+#[repr(C)]
+#[derive(Debug)]
+pub struct SubmittedWorkDoneClosure {
+    pub callback: SubmittedWorkDoneCallback,
+    pub user_data: *mut u8,
+}
+
+unsafe impl Send for SubmittedWorkDoneClosure {}
+unsafe impl Sync for SubmittedWorkDoneClosure {}
+
+pub type UploadBudgetCallback =
+    unsafe extern "C" fn(bytes_uploaded: wgt::BufferAddress, user_data: *mut u8);
+
+/// A soft limit on the amount of data staged by `write_buffer` and `write_texture`
+/// between two submissions of the queue.
+///
+/// Writes are never rejected. The callback is fired once per submission, right after
+/// the write that crossed the budget, so that the user can back off.
+#[repr(C)]
+#[derive(Debug)]
+pub struct UploadBudget {
+    pub bytes_per_submission: wgt::BufferAddress,
+    pub callback: UploadBudgetCallback,
+    pub user_data: *mut u8,
+}
+
+unsafe impl Send for UploadBudget {}
+unsafe impl Sync for UploadBudget {}
+
+/// An upload budget callback that is ready to be fired.
+struct UploadBudgetExceeded {
+    callback: UploadBudgetCallback,
+    user_data: *mut u8,
+    bytes_uploaded: wgt::BufferAddress,
+}
+
+impl UploadBudgetExceeded {
+    //Note: has to be called with nothing locked.
+    fn fire(self) {
+        unsafe { (self.callback)(self.bytes_uploaded, self.user_data) }
+    }
+}
+
 #[derive(Debug)]
 pub enum TempResource<B: hal::Backend> {
     Buffer(B::Buffer),
@@ -40,6 +131,9 @@ pub(crate) struct PendingWrites<B: hal::Backend> {
     pub temp_resources: Vec<(TempResource<B>, alloc::MemoryBlock<B>)>,
     pub dst_buffers: FastHashSet<id::BufferId>,
     pub dst_textures: FastHashSet<id::TextureId>,
+    pub upload_budget: Option<UploadBudget>,
+    /// Number of bytes staged since the last submission.
+    bytes_uploaded: wgt::BufferAddress,
 }
 
 impl<B: hal::Backend> PendingWrites<B> {
@@ -49,6 +143,8 @@ impl<B: hal::Backend> PendingWrites<B> {
             temp_resources: Vec::new(),
             dst_buffers: FastHashSet::default(),
             dst_textures: FastHashSet::default(),
+            upload_budget: None,
+            bytes_uploaded: 0,
         }
     }
 
@@ -78,16 +174,49 @@ impl<B: hal::Backend> PendingWrites<B> {
         self.temp_resources.push((resource, memory));
     }
 
+    pub fn borrow_cmd_buf(&mut self, cmd_allocator: &CommandAllocator<B>) -> &mut B::CommandBuffer {
+        if self.command_buffer.is_none() {
+            let mut cmdbuf = cmd_allocator.allocate_internal();
+            unsafe {
+                cmdbuf.begin_primary(hal::command::CommandBufferFlags::ONE_TIME_SUBMIT);
+            }
+            self.command_buffer = Some(cmdbuf);
+        }
+        self.command_buffer.as_mut().unwrap()
+    }
+
     fn consume(&mut self, stage: StagingData<B>) {
         self.temp_resources
             .push((TempResource::Buffer(stage.buffer), stage.memory));
         self.command_buffer = Some(stage.cmdbuf);
     }
 
+    /// Accounts for `size` more bytes being uploaded.
+    /// Returns the budget callback if this upload crossed the budget.
+    #[must_use]
+    fn track_upload(&mut self, size: wgt::BufferAddress) -> Option<UploadBudgetExceeded> {
+        let before = self.bytes_uploaded;
+        self.bytes_uploaded += size;
+        match self.upload_budget {
+            Some(ref budget)
+                if before <= budget.bytes_per_submission
+                    && self.bytes_uploaded > budget.bytes_per_submission =>
+            {
+                Some(UploadBudgetExceeded {
+                    callback: budget.callback,
+                    user_data: budget.user_data,
+                    bytes_uploaded: self.bytes_uploaded,
+                })
+            }
+            _ => None,
+        }
+    }
+
     #[must_use]
     fn finish(&mut self) -> Option<B::CommandBuffer> {
         self.dst_buffers.clear();
         self.dst_textures.clear();
+        self.bytes_uploaded = 0;
         self.command_buffer.take().map(|mut cmd_buf| unsafe {
             cmd_buf.finish();
             cmd_buf
@@ -97,17 +226,13 @@ impl<B: hal::Backend> PendingWrites<B> {
 
 impl<B: hal::Backend> super::Device<B> {
     pub fn borrow_pending_writes(&mut self) -> &mut B::CommandBuffer {
-        if self.pending_writes.command_buffer.is_none() {
-            let mut cmdbuf = self.cmd_allocator.allocate_internal();
-            unsafe {
-                cmdbuf.begin_primary(hal::command::CommandBufferFlags::ONE_TIME_SUBMIT);
-            }
-            self.pending_writes.command_buffer = Some(cmdbuf);
-        }
-        self.pending_writes.command_buffer.as_mut().unwrap()
+        self.pending_writes.borrow_cmd_buf(&self.cmd_allocator)
     }
 
-    fn prepare_stage(&mut self, size: wgt::BufferAddress) -> Result<StagingData<B>, DeviceError> {
+    fn prepare_staging_buffer(
+        &self,
+        size: wgt::BufferAddress,
+    ) -> Result<(B::Buffer, alloc::MemoryBlock<B>), DeviceError> {
         let mut buffer = unsafe {
             self.raw
                 .create_buffer(size, hal::buffer::Usage::TRANSFER_SRC)
@@ -128,7 +253,10 @@ impl<B: hal::Backend> super::Device<B> {
             gpu_alloc::UsageFlags::UPLOAD | gpu_alloc::UsageFlags::TRANSIENT,
         )?;
         block.bind_buffer(&self.raw, &mut buffer)?;
+        Ok((buffer, block))
+    }
 
+    fn stage_with(&mut self, buffer: B::Buffer, memory: alloc::MemoryBlock<B>) -> StagingData<B> {
         let cmdbuf = match self.pending_writes.command_buffer.take() {
             Some(cmdbuf) => cmdbuf,
             None => {
@@ -139,14 +267,308 @@ impl<B: hal::Backend> super::Device<B> {
                 cmdbuf
             }
         };
-        Ok(StagingData {
+        StagingData {
             buffer,
-            memory: block,
+            memory,
             cmdbuf,
-        })
+        }
+    }
+
+    fn prepare_stage(&mut self, size: wgt::BufferAddress) -> Result<StagingData<B>, DeviceError> {
+        let (buffer, memory) = self.prepare_staging_buffer(size)?;
+        Ok(self.stage_with(buffer, memory))
+    }
+
+    fn free_staging_buffer(&self, buffer: B::Buffer, memory: alloc::MemoryBlock<B>) {
+        self.mem_allocator.lock().free(&self.raw, memory);
+        unsafe {
+            self.raw.destroy_buffer(buffer);
+        }
+    }
+
+    /// Prepares the zeroed staging buffer needed to initialize the texture,
+    /// if it can't be cleared directly.
+    fn prepare_zero_stage(
+        &self,
+        texture: &Texture<B>,
+    ) -> Result<Option<ZeroStage<B>>, DeviceError> {
+        let format_desc = texture.format.describe();
+        if format_desc.block_dimensions == (1, 1) {
+            return Ok(None);
+        }
+        let block_width = format_desc.block_dimensions.0 as u32;
+        let block_height = format_desc.block_dimensions.1 as u32;
+        let bytes_per_block = format_desc.block_size as u32;
+        // The first level is the largest one.
+        let extent = texture.kind.extent();
+        let width_blocks = (extent.width + block_width - 1) / block_width;
+        let height_blocks = (extent.height + block_height - 1) / block_height;
+        let bytes_per_row_alignment = get_lowest_common_denom(
+            self.hal_limits.optimal_buffer_copy_pitch_alignment as u32,
+            bytes_per_block,
+        );
+        let bytes_per_row = align_to(bytes_per_block * width_blocks, bytes_per_row_alignment);
+        let size = bytes_per_row as wgt::BufferAddress
+            * height_blocks as wgt::BufferAddress
+            * extent.depth as wgt::BufferAddress;
+
+        let (buffer, mut memory) = self.prepare_staging_buffer(size)?;
+        let result = memory.map(&self.raw, 0, size).and_then(|ptr| {
+            unsafe { ptr::write_bytes(ptr.as_ptr(), 0, size as usize) };
+            memory.unmap(&self.raw);
+            if memory.is_coherent() {
+                Ok(())
+            } else {
+                memory.flush_range(&self.raw, 0, None)
+            }
+        });
+        if let Err(err) = result {
+            self.free_staging_buffer(buffer, memory);
+            return Err(err);
+        }
+        Ok(Some(ZeroStage {
+            buffer,
+            memory,
+            buffer_width: bytes_per_row / bytes_per_block * block_width,
+            buffer_height: height_blocks * block_height,
+        }))
+    }
+
+    /// Releases the staging data of a write that didn't go through.
+    fn discard_stage(&mut self, stage: StagingData<B>) {
+        self.pending_writes.command_buffer = Some(stage.cmdbuf);
+        self.free_staging_buffer(stage.buffer, stage.memory);
+    }
+
+    /// Checks that `data_size` bytes can be written into the buffer at `buffer_offset`.
+    fn validate_write_buffer(
+        buffer_guard: &Storage<Buffer<B>, id::BufferId>,
+        buffer_id: id::BufferId,
+        buffer_offset: wgt::BufferAddress,
+        data_size: wgt::BufferAddress,
+    ) -> Result<(), TransferError> {
+        let dst = buffer_guard
+            .get(buffer_id)
+            .map_err(|_| TransferError::InvalidBuffer(buffer_id))?;
+        if dst.raw.is_none() {
+            return Err(TransferError::InvalidBuffer(buffer_id));
+        }
+        if !dst.usage.contains(wgt::BufferUsage::COPY_DST) {
+            return Err(TransferError::MissingCopyDstUsageFlag(
+                Some(buffer_id),
+                None,
+            ));
+        }
+        if data_size % wgt::COPY_BUFFER_ALIGNMENT != 0 {
+            return Err(TransferError::UnalignedCopySize(data_size));
+        }
+        if buffer_offset % wgt::COPY_BUFFER_ALIGNMENT != 0 {
+            return Err(TransferError::UnalignedBufferOffset(buffer_offset));
+        }
+        if buffer_offset + data_size > dst.size {
+            return Err(TransferError::BufferOverrun {
+                start_offset: buffer_offset,
+                end_offset: buffer_offset + data_size,
+                buffer_size: dst.size,
+                side: CopySide::Destination,
+            });
+        }
+        Ok(())
+    }
+
+    /// Records a copy of the filled staging data into the buffer.
+    /// Returns the upload budget callback to fire, if any.
+    ///
+    /// The staging data is released if the write is invalid.
+    fn write_staged_buffer(
+        &mut self,
+        mut stage: StagingData<B>,
+        buffer_guard: &mut Storage<Buffer<B>, id::BufferId>,
+        buffer_id: id::BufferId,
+        buffer_offset: wgt::BufferAddress,
+        data_size: wgt::BufferAddress,
+    ) -> Result<Option<UploadBudgetExceeded>, QueueWriteError> {
+        if let Err(err) =
+            Self::validate_write_buffer(buffer_guard, buffer_id, buffer_offset, data_size)
+        {
+            self.discard_stage(stage);
+            return Err(err.into());
+        }
+
+        let mut trackers = self.trackers.lock();
+        let buffer = buffer_guard.get_mut(buffer_id).unwrap();
+        if mem::replace(&mut buffer.needs_zero_init, false) {
+            zero_init_buffer(&mut stage.cmdbuf, &mut *trackers, buffer_guard, buffer_id);
+        }
+        // The buffer has been validated above.
+        let (dst, transition) = trackers
+            .buffers
+            .use_replace(buffer_guard, buffer_id, (), BufferUse::COPY_DST)
+            .unwrap();
+        let &(ref dst_raw, _) = dst.raw.as_ref().unwrap();
+        dst.life_guard.use_at(self.active_submission_index + 1);
+
+        let region = hal::command::BufferCopy {
+            src: 0,
+            dst: buffer_offset,
+            size: data_size,
+        };
+        unsafe {
+            stage.cmdbuf.pipeline_barrier(
+                super::all_buffer_stages()..hal::pso::PipelineStage::TRANSFER,
+                hal::memory::Dependencies::empty(),
+                iter::once(hal::memory::Barrier::Buffer {
+                    states: hal::buffer::Access::HOST_WRITE..hal::buffer::Access::TRANSFER_READ,
+                    target: &stage.buffer,
+                    range: hal::buffer::SubRange::WHOLE,
+                    families: None,
+                })
+                .chain(transition.map(|pending| pending.into_hal(dst))),
+            );
+            stage
+                .cmdbuf
+                .copy_buffer(&stage.buffer, dst_raw, iter::once(region));
+        }
+
+        self.pending_writes.consume(stage);
+        self.pending_writes.dst_buffers.insert(buffer_id);
+        Ok(self.pending_writes.track_upload(data_size))
+    }
+}
+
+/// Records clearing of the whole buffer to zero.
+/// The caller is responsible for checking and resetting `needs_zero_init`.
+fn zero_init_buffer<B: hal::Backend>(
+    cmdbuf: &mut B::CommandBuffer,
+    trackers: &mut TrackerSet<B>,
+    buffer_guard: &Storage<Buffer<B>, id::BufferId>,
+    buffer_id: id::BufferId,
+) {
+    tracing::trace!("Zero-initializing buffer {:?}", buffer_id);
+    let (buffer, transition) = trackers
+        .buffers
+        .use_replace(buffer_guard, buffer_id, (), BufferUse::COPY_DST)
+        .unwrap();
+    let &(ref raw, _) = buffer.raw.as_ref().unwrap();
+    unsafe {
+        cmdbuf.pipeline_barrier(
+            super::all_buffer_stages()..hal::pso::PipelineStage::TRANSFER,
+            hal::memory::Dependencies::empty(),
+            transition.map(|pending| pending.into_hal(buffer)),
+        );
+        cmdbuf.fill_buffer(raw, hal::buffer::SubRange::WHOLE, 0);
+        // The tracker considers the following copies to be in the same state,
+        // so make them wait for the fill explicitly.
+        cmdbuf.pipeline_barrier(
+            hal::pso::PipelineStage::TRANSFER..hal::pso::PipelineStage::TRANSFER,
+            hal::memory::Dependencies::empty(),
+            iter::once(hal::memory::Barrier::Buffer {
+                states: hal::buffer::Access::TRANSFER_WRITE..hal::buffer::Access::TRANSFER_WRITE,
+                target: raw,
+                range: hal::buffer::SubRange::WHOLE,
+                families: None,
+            }),
+        );
+    }
+}
+
+/// Records clearing of all the subresources of the texture to zero.
+/// Textures that can't be cleared are copied into from `zero_stage` instead.
+/// The caller is responsible for checking and resetting `needs_zero_init`.
+fn zero_init_texture<B: hal::Backend>(
+    cmdbuf: &mut B::CommandBuffer,
+    trackers: &mut TrackerSet<B>,
+    texture_guard: &Storage<Texture<B>, id::TextureId>,
+    texture_id: id::TextureId,
+    zero_stage: Option<&ZeroStage<B>>,
+) {
+    tracing::trace!("Zero-initializing texture {:?}", texture_id);
+    let full_range = texture_guard[id::Valid(texture_id)].full_range.clone();
+    let (texture, transition) = trackers
+        .textures
+        .use_replace(texture_guard, texture_id, full_range, TextureUse::COPY_DST)
+        .unwrap();
+    let &(ref raw, _) = texture.raw.as_ref().unwrap();
+    let range = hal::image::SubresourceRange {
+        aspects: texture.aspects,
+        level_start: 0,
+        level_count: None,
+        layer_start: 0,
+        layer_count: None,
+    };
+    let value = if texture.aspects.contains(hal::format::Aspects::COLOR) {
+        hal::command::ClearValue {
+            color: hal::command::ClearColor { uint32: [0; 4] },
+        }
+    } else {
+        hal::command::ClearValue {
+            depth_stencil: hal::command::ClearDepthStencil {
+                depth: 0.0,
+                stencil: 0,
+            },
+        }
+    };
+    let layout = hal::image::Layout::TransferDstOptimal;
+    unsafe {
+        cmdbuf.pipeline_barrier(
+            super::all_image_stages()..hal::pso::PipelineStage::TRANSFER,
+            hal::memory::Dependencies::empty(),
+            transition.map(|pending| pending.into_hal(texture)),
+        );
+        match zero_stage {
+            Some(stage) => {
+                cmdbuf.pipeline_barrier(
+                    hal::pso::PipelineStage::HOST..hal::pso::PipelineStage::TRANSFER,
+                    hal::memory::Dependencies::empty(),
+                    iter::once(hal::memory::Barrier::Buffer {
+                        states: hal::buffer::Access::HOST_WRITE..hal::buffer::Access::TRANSFER_READ,
+                        target: &stage.buffer,
+                        range: hal::buffer::SubRange::WHOLE,
+                        families: None,
+                    }),
+                );
+                // Every subresource is copied from the start of the same zeroed buffer.
+                let layers = texture.full_range.layers.clone();
+                let regions = texture.full_range.levels.clone().flat_map(|level| {
+                    layers
+                        .clone()
+                        .map(move |layer| hal::command::BufferImageCopy {
+                            buffer_offset: 0,
+                            buffer_width: stage.buffer_width,
+                            buffer_height: stage.buffer_height,
+                            image_layers: hal::image::SubresourceLayers {
+                                aspects: texture.aspects,
+                                level,
+                                layers: layer..layer + 1,
+                            },
+                            image_offset: hal::image::Offset::ZERO,
+                            image_extent: texture.kind.level_extent(level),
+                        })
+                });
+                cmdbuf.copy_buffer_to_image(&stage.buffer, raw, layout, regions);
+            }
+            None => cmdbuf.clear_image(raw, layout, value, iter::once(&range)),
+        }
+        // The tracker considers the following copies to be in the same state,
+        // so make them wait for the clear explicitly.
+        cmdbuf.pipeline_barrier(
+            hal::pso::PipelineStage::TRANSFER..hal::pso::PipelineStage::TRANSFER,
+            hal::memory::Dependencies::empty(),
+            iter::once(hal::memory::Barrier::Image {
+                states: (hal::image::Access::TRANSFER_WRITE, layout)
+                    ..(hal::image::Access::TRANSFER_WRITE, layout),
+                target: raw,
+                range: range.clone(),
+                families: None,
+            }),
+        );
     }
 }
 
+#[error("queue is invalid")]
+#[derive(Clone, Debug, Error)]
+pub struct InvalidQueue;
+
 #[derive(Clone, Debug, Error)]
 pub enum QueueWriteError {
     #[error(transparent)]
@@ -191,7 +613,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         let device = device_guard
             .get_mut(queue_id)
             .map_err(|_| DeviceError::Invalid)?;
-        let (buffer_guard, _) = hub.buffers.read(&mut token);
+        let (mut buffer_guard, _) = hub.buffers.write(&mut token);
 
         #[cfg(feature = "trace")]
         if let Some(ref trace) = device.trace {
@@ -211,65 +633,123 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             return Ok(());
         }
 
+        super::Device::validate_write_buffer(&*buffer_guard, buffer_id, buffer_offset, data_size)?;
         let mut stage = device.prepare_stage(data_size)?;
-        stage.memory.write_bytes(&device.raw, 0, data)?;
-
-        let mut trackers = device.trackers.lock();
-        let (dst, transition) = trackers
-            .buffers
-            .use_replace(&*buffer_guard, buffer_id, (), BufferUse::COPY_DST)
-            .map_err(TransferError::InvalidBuffer)?;
-        let &(ref dst_raw, _) = dst
-            .raw
-            .as_ref()
-            .ok_or(TransferError::InvalidBuffer(buffer_id))?;
-        if !dst.usage.contains(wgt::BufferUsage::COPY_DST) {
-            Err(TransferError::MissingCopyDstUsageFlag(
-                Some(buffer_id),
-                None,
-            ))?;
+        if let Err(err) = stage.memory.write_bytes(&device.raw, 0, data) {
+            device.discard_stage(stage);
+            return Err(err.into());
         }
-        dst.life_guard.use_at(device.active_submission_index + 1);
+        let exceeded = device.write_staged_buffer(
+            stage,
+            &mut *buffer_guard,
+            buffer_id,
+            buffer_offset,
+            data_size,
+        )?;
 
-        if data_size % wgt::COPY_BUFFER_ALIGNMENT != 0 {
-            Err(TransferError::UnalignedCopySize(data_size))?
-        }
-        if buffer_offset % wgt::COPY_BUFFER_ALIGNMENT != 0 {
-            Err(TransferError::UnalignedBufferOffset(buffer_offset))?
-        }
-        if buffer_offset + data_size > dst.size {
-            Err(TransferError::BufferOverrun {
-                start_offset: buffer_offset,
-                end_offset: buffer_offset + data_size,
-                buffer_size: dst.size,
-                side: CopySide::Destination,
-            })?
+        drop(buffer_guard);
+        drop(device_guard);
+        if let Some(exceeded) = exceeded {
+            exceeded.fire();
         }
 
-        let region = hal::command::BufferCopy {
-            src: 0,
-            dst: buffer_offset,
-            size: data.len() as _,
+        Ok(())
+    }
+
+    /// Like `queue_write_buffer`, but lets `fill` write the data directly
+    /// into the staging memory, saving a copy.
+    ///
+    /// No locks are held while `fill` is running.
+    pub fn queue_write_buffer_with<B: GfxBackend>(
+        &self,
+        queue_id: id::QueueId,
+        buffer_id: id::BufferId,
+        buffer_offset: wgt::BufferAddress,
+        size: wgt::BufferSize,
+        fill: impl FnOnce(&mut [u8]),
+    ) -> Result<(), QueueWriteError> {
+        span!(_guard, INFO, "Queue::write_buffer_with");
+
+        let hub = B::hub(self);
+        let data_size = size.get();
+
+        let (stage, ptr) = {
+            let mut token = Token::root();
+            let (device_guard, mut token) = hub.devices.read(&mut token);
+            let device = device_guard
+                .get(queue_id)
+                .map_err(|_| DeviceError::Invalid)?;
+            let (buffer_guard, _) = hub.buffers.read(&mut token);
+            // Validate before handing any memory to `fill`. The buffer is validated
+            // again when recording the copy, since it may be destroyed in between.
+            super::Device::validate_write_buffer(
+                &*buffer_guard,
+                buffer_id,
+                buffer_offset,
+                data_size,
+            )?;
+            let (buffer, mut memory) = device.prepare_staging_buffer(data_size)?;
+            match memory.map(&device.raw, 0, data_size) {
+                Ok(ptr) => {
+                    let stage = MappedStage {
+                        hub,
+                        device_id: queue_id,
+                        stage: Some((buffer, memory)),
+                    };
+                    (stage, ptr)
+                }
+                Err(err) => {
+                    device.free_staging_buffer(buffer, memory);
+                    return Err(err.into());
+                }
+            }
         };
-        unsafe {
-            stage.cmdbuf.pipeline_barrier(
-                super::all_buffer_stages()..hal::pso::PipelineStage::TRANSFER,
-                hal::memory::Dependencies::empty(),
-                iter::once(hal::memory::Barrier::Buffer {
-                    states: hal::buffer::Access::HOST_WRITE..hal::buffer::Access::TRANSFER_READ,
-                    target: &stage.buffer,
-                    range: hal::buffer::SubRange::WHOLE,
-                    families: None,
-                })
-                .chain(transition.map(|pending| pending.into_hal(dst))),
-            );
-            stage
-                .cmdbuf
-                .copy_buffer(&stage.buffer, dst_raw, iter::once(region));
+        // The staging memory is owned by us and stays mapped until we are done.
+        let data = unsafe { std::slice::from_raw_parts_mut(ptr.as_ptr(), data_size as usize) };
+        fill(data);
+
+        let mut token = Token::root();
+        let (mut device_guard, mut token) = hub.devices.write(&mut token);
+        let device = device_guard
+            .get_mut(queue_id)
+            .map_err(|_| DeviceError::Invalid)?;
+        let (mut buffer_guard, _) = hub.buffers.write(&mut token);
+        let (stage_buffer, mut stage_memory) = stage.take();
+
+        #[cfg(feature = "trace")]
+        if let Some(ref trace) = device.trace {
+            let mut trace = trace.lock();
+            let data_path = trace.make_binary("bin", data);
+            trace.add(Action::WriteBuffer {
+                id: buffer_id,
+                data: data_path,
+                range: buffer_offset..buffer_offset + data_size,
+                queued: true,
+            });
         }
 
-        device.pending_writes.consume(stage);
-        device.pending_writes.dst_buffers.insert(buffer_id);
+        if !stage_memory.is_coherent() {
+            if let Err(err) = stage_memory.flush_range(&device.raw, 0, None) {
+                stage_memory.unmap(&device.raw);
+                device.free_staging_buffer(stage_buffer, stage_memory);
+                return Err(err.into());
+            }
+        }
+        stage_memory.unmap(&device.raw);
+        let stage = device.stage_with(stage_buffer, stage_memory);
+        let exceeded = device.write_staged_buffer(
+            stage,
+            &mut *buffer_guard,
+            buffer_id,
+            buffer_offset,
+            data_size,
+        )?;
+
+        drop(buffer_guard);
+        drop(device_guard);
+        if let Some(exceeded) = exceeded {
+            exceeded.fire();
+        }
 
         Ok(())
     }
@@ -290,7 +770,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         let device = device_guard
             .get_mut(queue_id)
             .map_err(|_| DeviceError::Invalid)?;
-        let (texture_guard, _) = hub.textures.read(&mut token);
+        let (mut texture_guard, _) = hub.textures.write(&mut token);
         let (image_layers, image_range, image_offset) =
             texture_copy_view_to_hal(destination, size, &*texture_guard)?;
 
@@ -312,10 +792,8 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         }
 
         let texture_format = texture_guard.get(destination.texture).unwrap().format;
-        let bytes_per_block = conv::map_texture_format(texture_format, device.private_features)
-            .surface_desc()
-            .bits as u32
-            / BITS_PER_BYTE;
+        let bytes_per_block =
+            copy_bytes_per_block(texture_format, destination.aspect, device.private_features);
         validate_linear_texture_data(
             data_layout,
             texture_format,
@@ -329,7 +807,11 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         let block_width = block_width as u32;
         let block_height = block_height as u32;
 
-        if !conv::is_valid_copy_dst_texture_format(texture_format) {
+        if !conv::is_valid_copy_dst_texture_format(
+            texture_format,
+            destination.aspect,
+            device.features,
+        ) {
             Err(TransferError::CopyToForbiddenTextureFormat(texture_format))?
         }
         let width_blocks = size.width / block_width;
@@ -347,8 +829,34 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         let block_rows_in_copy = (size.depth - 1) * block_rows_per_image + height_blocks;
         let stage_size = stage_bytes_per_row as u64 * block_rows_in_copy as u64;
         let mut stage = device.prepare_stage(stage_size)?;
+        let zero_stage = match texture_guard.get(destination.texture) {
+            Ok(texture) if texture.needs_zero_init => match device.prepare_zero_stage(texture) {
+                Ok(zero_stage) => zero_stage,
+                Err(err) => {
+                    device.discard_stage(stage);
+                    return Err(err.into());
+                }
+            },
+            _ => None,
+        };
 
         let mut trackers = device.trackers.lock();
+        if let Ok(texture) = texture_guard.get_mut(destination.texture) {
+            if mem::replace(&mut texture.needs_zero_init, false) {
+                zero_init_texture(
+                    &mut stage.cmdbuf,
+                    &mut *trackers,
+                    &*texture_guard,
+                    destination.texture,
+                    zero_stage.as_ref(),
+                );
+            }
+        }
+        if let Some(zero_stage) = zero_stage {
+            device
+                .pending_writes
+                .consume_temp(TempResource::Buffer(zero_stage.buffer), zero_stage.memory);
+        }
         let (dst, transition) = trackers
             .textures
             .use_replace(
@@ -454,6 +962,14 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             .pending_writes
             .dst_textures
             .insert(destination.texture);
+        let exceeded = device.pending_writes.track_upload(stage_size);
+
+        drop(trackers);
+        drop(texture_guard);
+        drop(device_guard);
+        if let Some(exceeded) = exceeded {
+            exceeded.fire();
+        }
 
         Ok(())
     }
@@ -462,18 +978,17 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         &self,
         queue_id: id::QueueId,
         command_buffer_ids: &[id::CommandBufferId],
-    ) -> Result<(), QueueSubmitError> {
+    ) -> Result<SubmissionIndex, QueueSubmitError> {
         span!(_guard, INFO, "Queue::submit");
 
         let hub = B::hub(self);
 
-        let callbacks = {
+        let (submit_index, callbacks) = {
             let mut token = Token::root();
             let (mut device_guard, mut token) = hub.devices.write(&mut token);
             let device = device_guard
                 .get_mut(queue_id)
                 .map_err(|_| DeviceError::Invalid)?;
-            let pending_write_command_buffer = device.pending_writes.finish();
             device.temp_suspected.clear();
             device.active_submission_index += 1;
             let submit_index = device.active_submission_index;
@@ -490,7 +1005,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     let (compute_pipe_guard, mut token) = hub.compute_pipelines.read(&mut token);
                     let (render_pipe_guard, mut token) = hub.render_pipelines.read(&mut token);
                     let (mut buffer_guard, mut token) = hub.buffers.write(&mut token);
-                    let (texture_guard, mut token) = hub.textures.read(&mut token);
+                    let (mut texture_guard, mut token) = hub.textures.write(&mut token);
                     let (texture_view_guard, mut token) = hub.texture_views.read(&mut token);
                     let (sampler_guard, _) = hub.samplers.read(&mut token);
 
@@ -506,6 +1021,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         let cmdbuf = command_buffer_guard
                             .get_mut(cmb_id)
                             .map_err(|_| QueueSubmitError::InvalidCommandBuffer(cmb_id))?;
+                        if !cmdbuf.is_finished() {
+                            return Err(QueueSubmitError::InvalidCommandBuffer(cmb_id));
+                        }
                         #[cfg(feature = "trace")]
                         if let Some(ref trace) = device.trace {
                             trace.lock().add(Action::Submit(
@@ -589,6 +1107,43 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             }
                         }
 
+                        // zero-initialize the resources on their first use,
+                        // the pending writes are executed before everything else
+                        for id in cmdbuf.trackers.buffers.used() {
+                            if mem::replace(&mut buffer_guard[id].needs_zero_init, false) {
+                                let pending_cmdbuf =
+                                    device.pending_writes.borrow_cmd_buf(&device.cmd_allocator);
+                                zero_init_buffer(
+                                    pending_cmdbuf,
+                                    &mut *trackers,
+                                    &*buffer_guard,
+                                    id.0,
+                                );
+                                device.pending_writes.dst_buffers.insert(id.0);
+                            }
+                        }
+                        for id in cmdbuf.trackers.textures.used() {
+                            if mem::replace(&mut texture_guard[id].needs_zero_init, false) {
+                                let zero_stage = device.prepare_zero_stage(&texture_guard[id])?;
+                                let pending_cmdbuf =
+                                    device.pending_writes.borrow_cmd_buf(&device.cmd_allocator);
+                                zero_init_texture(
+                                    pending_cmdbuf,
+                                    &mut *trackers,
+                                    &*texture_guard,
+                                    id.0,
+                                    zero_stage.as_ref(),
+                                );
+                                if let Some(zero_stage) = zero_stage {
+                                    device.pending_writes.consume_temp(
+                                        TempResource::Buffer(zero_stage.buffer),
+                                        zero_stage.memory,
+                                    );
+                                }
+                                device.pending_writes.dst_textures.insert(id.0);
+                            }
+                        }
+
                         // execute resource transitions
                         let mut transit = device.cmd_allocator.extend(cmdbuf);
                         unsafe {
@@ -613,6 +1168,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
 
                     tracing::trace!("Device after submission {}: {:#?}", submit_index, trackers);
                 }
+                let pending_write_command_buffer = device.pending_writes.finish();
 
                 // now prepare the GPU submission
                 let mut fence = device
@@ -648,8 +1204,8 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     .after_submit_internal(comb_raw, submit_index);
             }
 
-            let callbacks = match device.maintain(&hub, false, &mut token) {
-                Ok(callbacks) => callbacks,
+            let closures = match device.maintain(&hub, wgt::Maintain::Poll, &mut token) {
+                Ok(closures) => closures,
                 Err(WaitIdleError::Device(err)) => return Err(QueueSubmitError::Queue(err)),
                 Err(WaitIdleError::StuckGpu) => return Err(QueueSubmitError::StuckGpu),
             };
@@ -669,11 +1225,73 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 }
             }
 
-            callbacks
+            (submit_index, closures)
         };
 
-        super::fire_map_callbacks(callbacks);
+        callbacks.fire();
+
+        Ok(submit_index)
+    }
+
+    /// Blocks until all the work submitted to the queue is done.
+    ///
+    /// The callbacks of the completed work are fired before returning,
+    /// as if the device was polled with `Maintain::Wait`.
+    pub fn queue_wait_idle<B: GfxBackend>(
+        &self,
+        queue_id: id::QueueId,
+    ) -> Result<(), WaitIdleError> {
+        span!(_guard, INFO, "Queue::wait_idle");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let callbacks = {
+            let (device_guard, mut token) = hub.devices.read(&mut token);
+            device_guard
+                .get(queue_id)
+                .map_err(|_| DeviceError::Invalid)?
+                .maintain(&hub, wgt::Maintain::Wait, &mut token)?
+        };
+        callbacks.fire();
+        Ok(())
+    }
 
+    pub fn queue_set_upload_budget<B: GfxBackend>(
+        &self,
+        queue_id: id::QueueId,
+        budget: Option<UploadBudget>,
+    ) -> Result<(), InvalidQueue> {
+        span!(_guard, INFO, "Queue::set_upload_budget");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (mut device_guard, _) = hub.devices.write(&mut token);
+        let device = device_guard.get_mut(queue_id).map_err(|_| InvalidQueue)?;
+        device.pending_writes.upload_budget = budget;
+        Ok(())
+    }
+
+    pub fn queue_on_submitted_work_done<B: GfxBackend>(
+        &self,
+        queue_id: id::QueueId,
+        closure: SubmittedWorkDoneClosure,
+    ) -> Result<(), InvalidQueue> {
+        span!(_guard, INFO, "Queue::on_submitted_work_done");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let ready_closure = {
+            let (device_guard, mut token) = hub.devices.read(&mut token);
+            match device_guard.get(queue_id) {
+                Ok(device) => device.lock_life(&mut token).add_work_done_closure(closure),
+                Err(_) => return Err(InvalidQueue),
+            }
+        };
+        // Nothing is in flight, so the closure is fired right away,
+        // with no locks held.
+        if let Some(closure) = ready_closure {
+            unsafe { (closure.callback)(closure.user_data) };
+        }
         Ok(())
     }
 }
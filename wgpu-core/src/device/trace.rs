@@ -13,6 +13,89 @@ type FileName = String;
 
 pub const FILE_NAME: &str = "trace.ron";
 
+/// Suffix of the data files compressed with zstd.
+pub const COMPRESSED_SUFFIX: &str = ".zst";
+#[cfg(feature = "trace-compression")]
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Name of the trace file written in the binary format.
+///
+/// It starts with [`BINARY_MAGIC`] and the [`BINARY_VERSION`] as a little-endian `u32`,
+/// followed by the actions. Each action is encoded with `bincode`, prefixed by its
+/// size as a little-endian `u64`.
+pub const BINARY_FILE_NAME: &str = "trace.bin";
+pub const BINARY_MAGIC: [u8; 8] = *b"WGPUTRAC";
+/// Version of the binary format. It's bumped whenever the encoding of actions changes.
+pub const BINARY_VERSION: u32 = 1;
+
+#[cfg(feature = "binary-trace")]
+#[derive(Debug, thiserror::Error)]
+pub enum BinaryTraceError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("not a binary trace")]
+    InvalidHeader,
+    #[error(
+        "binary trace version {0} is not supported, expected {}",
+        BINARY_VERSION
+    )]
+    UnsupportedVersion(u32),
+    #[error("action {index} is truncated, expected {expected} bytes, found {found}")]
+    Truncated {
+        index: usize,
+        expected: u64,
+        found: u64,
+    },
+    #[error(transparent)]
+    Decode(#[from] bincode::Error),
+}
+
+/// Read all the actions of a binary trace.
+///
+/// The size prefixes aren't trusted: the memory used for an action is bounded by
+/// what's left to read, so a corrupted size can't make it allocate past that.
+#[cfg(all(feature = "binary-trace", feature = "replay"))]
+pub fn read_binary(
+    mut reader: impl std::io::Read,
+) -> Result<Vec<Action<'static>>, BinaryTraceError> {
+    use std::io::Read as _;
+
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    if magic != BINARY_MAGIC {
+        return Err(BinaryTraceError::InvalidHeader);
+    }
+    let mut version = [0u8; 4];
+    reader.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != BINARY_VERSION {
+        return Err(BinaryTraceError::UnsupportedVersion(version));
+    }
+
+    let mut actions = Vec::new();
+    let mut size = [0u8; 8];
+    let mut data = Vec::new();
+    loop {
+        match reader.read_exact(&mut size) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+        let expected = u64::from_le_bytes(size);
+        data.clear();
+        let found = (&mut reader).take(expected).read_to_end(&mut data)? as u64;
+        if found != expected {
+            return Err(BinaryTraceError::Truncated {
+                index: actions.len(),
+                expected,
+                found,
+            });
+        }
+        actions.push(bincode::deserialize(&data)?);
+    }
+    Ok(actions)
+}
+
 #[cfg(feature = "trace")]
 pub(crate) fn new_render_bundle_encoder_descriptor<'a>(
     label: Option<&'a str>,
@@ -111,7 +194,7 @@ pub enum Action<'a> {
     Submit(crate::SubmissionIndex, Vec<Command>),
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 #[cfg_attr(feature = "trace", derive(serde::Serialize))]
 #[cfg_attr(feature = "replay", derive(serde::Deserialize))]
 pub enum Command {
@@ -137,6 +220,7 @@ pub enum Command {
         dst: crate::command::TextureCopyView,
         size: wgt::Extent3d,
     },
+    GenerateMipmaps(id::TextureId),
     WriteTimestamp {
         query_set_id: id::QuerySetId,
         query_index: u32,
@@ -148,6 +232,9 @@ pub enum Command {
         destination: id::BufferId,
         destination_offset: wgt::BufferAddress,
     },
+    PushDebugGroup(String),
+    PopDebugGroup,
+    InsertDebugMarker(String),
     RunComputePass {
         base: crate::command::BasePass<crate::command::ComputeCommand>,
     },
@@ -163,6 +250,7 @@ pub enum Command {
 pub struct Trace {
     path: std::path::PathBuf,
     file: std::fs::File,
+    #[cfg(not(feature = "binary-trace"))]
     config: ron::ser::PrettyConfig,
     binary_id: usize,
 }
@@ -171,16 +259,29 @@ pub struct Trace {
 impl Trace {
     pub fn new(path: &std::path::Path) -> Result<Self, std::io::Error> {
         tracing::info!("Tracing into '{:?}'", path);
-        let mut file = std::fs::File::create(path.join(FILE_NAME))?;
-        file.write_all(b"[\n")?;
+        #[cfg(not(feature = "binary-trace"))]
+        let file = {
+            let mut file = std::fs::File::create(path.join(FILE_NAME))?;
+            file.write_all(b"[\n")?;
+            file
+        };
+        #[cfg(feature = "binary-trace")]
+        let file = {
+            let mut file = std::fs::File::create(path.join(BINARY_FILE_NAME))?;
+            file.write_all(&BINARY_MAGIC)?;
+            file.write_all(&BINARY_VERSION.to_le_bytes())?;
+            file
+        };
         Ok(Self {
             path: path.to_path_buf(),
             file,
+            #[cfg(not(feature = "binary-trace"))]
             config: ron::ser::PrettyConfig::default(),
             binary_id: 0,
         })
     }
 
+    #[cfg(not(feature = "trace-compression"))]
     pub fn make_binary(&mut self, kind: &str, data: &[u8]) -> String {
         self.binary_id += 1;
         let name = format!("data{}.{}", self.binary_id, kind);
@@ -188,6 +289,37 @@ impl Trace {
         name
     }
 
+    #[cfg(feature = "trace-compression")]
+    pub fn make_binary(&mut self, kind: &str, data: &[u8]) -> String {
+        self.binary_id += 1;
+        let name = format!("data{}.{}{}", self.binary_id, kind, COMPRESSED_SUFFIX);
+        match zstd::encode_all(data, COMPRESSION_LEVEL) {
+            Ok(compressed) => {
+                let _ = std::fs::write(self.path.join(&name), compressed);
+            }
+            Err(e) => {
+                tracing::warn!("Compression failure of {}: {:?}", name, e);
+            }
+        }
+        name
+    }
+
+    #[cfg(feature = "binary-trace")]
+    pub(crate) fn add(&mut self, action: Action) {
+        match bincode::serialize(&action) {
+            Ok(data) => {
+                let _ = self
+                    .file
+                    .write_all(&(data.len() as u64).to_le_bytes())
+                    .and_then(|()| self.file.write_all(&data));
+            }
+            Err(e) => {
+                tracing::warn!("Binary serialization failure: {:?}", e);
+            }
+        }
+    }
+
+    #[cfg(not(feature = "binary-trace"))]
     pub(crate) fn add(&mut self, action: Action) {
         match ron::ser::to_string_pretty(&action, self.config.clone()) {
             Ok(string) => {
@@ -203,6 +335,48 @@ impl Trace {
 #[cfg(feature = "trace")]
 impl Drop for Trace {
     fn drop(&mut self) {
-        let _ = self.file.write_all(b"]");
+        if cfg!(not(feature = "binary-trace")) {
+            let _ = self.file.write_all(b"]");
+        }
+    }
+}
+
+#[cfg(all(test, feature = "binary-trace", feature = "trace", feature = "replay"))]
+#[test]
+fn test_binary_round_trip() {
+    use id::TypedId as _;
+
+    let dir = std::env::temp_dir().join(format!("wgpu-trace-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let buffer_id = id::Id::zip(1, 2, wgt::Backend::Vulkan);
+    let sampler_id = id::Id::zip(3, 4, wgt::Backend::Vulkan);
+    {
+        let mut trace = Trace::new(&dir).unwrap();
+        trace.add(Action::DestroyBuffer(buffer_id));
+        trace.add(Action::DestroySampler(sampler_id));
+    }
+    let bytes = std::fs::read(dir.join(BINARY_FILE_NAME)).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let actions = read_binary(&bytes[..]).unwrap();
+    match actions.as_slice() {
+        [Action::DestroyBuffer(b), Action::DestroySampler(s)] => {
+            assert_eq!(*b, buffer_id);
+            assert_eq!(*s, sampler_id);
+        }
+        other => panic!("unexpected actions {:?}", other),
+    }
+
+    // A size prefix past the end of the data is reported, not allocated.
+    let mut corrupted = bytes[..BINARY_MAGIC.len() + 4].to_vec();
+    corrupted.extend_from_slice(&u64::MAX.to_le_bytes());
+    corrupted.extend_from_slice(&[0; 4]);
+    match read_binary(&corrupted[..]) {
+        Err(BinaryTraceError::Truncated {
+            index: 0,
+            expected: u64::MAX,
+            found: 4,
+        }) => {}
+        other => panic!("unexpected result {:?}", other),
     }
 }
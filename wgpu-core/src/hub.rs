@@ -34,6 +34,7 @@ use std::{fmt::Debug, marker::PhantomData, ops, thread};
 pub struct IdentityManager {
     free: Vec<Index>,
     epochs: Vec<Epoch>,
+    reuse_freed: bool,
 }
 
 impl Default for IdentityManager {
@@ -41,6 +42,7 @@ impl Default for IdentityManager {
         Self {
             free: Default::default(),
             epochs: Default::default(),
+            reuse_freed: true,
         }
     }
 }
@@ -50,6 +52,18 @@ impl IdentityManager {
         Self {
             free: (0..min_index).collect(),
             epochs: vec![1; min_index as usize],
+            reuse_freed: true,
+        }
+    }
+
+    /// Create a manager that never hands out the indices of freed objects again.
+    ///
+    /// The resulting IDs only depend on the order of allocation, and not on
+    /// the moment the objects are freed, which varies with the GPU progress.
+    pub fn deterministic(min_index: u32) -> Self {
+        Self {
+            reuse_freed: false,
+            ..Self::from_index(min_index)
         }
     }
 
@@ -74,7 +88,9 @@ impl IdentityManager {
         let pe = &mut self.epochs[index as usize];
         assert_eq!(*pe, epoch);
         *pe += 1;
-        self.free.push(index);
+        if self.reuse_freed {
+            self.free.push(index);
+        }
     }
 }
 
@@ -266,6 +282,7 @@ impl<B: hal::Backend> Access<ComputePipeline<B>> for BindGroup<B> {}
 impl<B: hal::Backend> Access<RenderPipeline<B>> for Device<B> {}
 impl<B: hal::Backend> Access<RenderPipeline<B>> for BindGroup<B> {}
 impl<B: hal::Backend> Access<RenderPipeline<B>> for ComputePipeline<B> {}
+impl<B: hal::Backend> Access<RenderPipeline<B>> for ShaderModule<B> {}
 impl<B: hal::Backend> Access<QuerySet<B>> for Root {}
 impl<B: hal::Backend> Access<QuerySet<B>> for Device<B> {}
 impl<B: hal::Backend> Access<QuerySet<B>> for CommandBuffer<B> {}
@@ -393,6 +410,23 @@ pub trait GlobalIdentityHandlerFactory:
 
 impl GlobalIdentityHandlerFactory for IdentityManagerFactory {}
 
+/// Identity factory producing IDs that are stable across runs.
+///
+/// Freed indices are never reused, so a program creating its objects in the
+/// same order always gets the same IDs. This makes the recorded traces
+/// byte-stable, at the cost of growing the storages for every new object.
+#[derive(Debug)]
+pub struct DeterministicIdentityManagerFactory;
+
+impl<I: TypedId + Debug> IdentityHandlerFactory<I> for DeterministicIdentityManagerFactory {
+    type Filter = Mutex<IdentityManager>;
+    fn spawn(&self, min_index: Index) -> Self::Filter {
+        Mutex::new(IdentityManager::deterministic(min_index))
+    }
+}
+
+impl GlobalIdentityHandlerFactory for DeterministicIdentityManagerFactory {}
+
 pub type Input<G, I> = <<G as IdentityHandlerFactory<I>>::Filter as IdentityHandler<I>>::Input;
 
 pub trait Resource {
@@ -622,6 +656,7 @@ impl<B: GfxBackend, F: GlobalIdentityHandlerFactory> Hub<B, F> {
                             }
                         }
                         TextureViewInner::SwapChain { .. } => {} //TODO
+                        TextureViewInner::Attachment { .. } => unreachable!(),
                     }
                 }
             }
@@ -763,6 +798,19 @@ impl<F: GlobalIdentityHandlerFactory> Hubs<F> {
     }
 }
 
+/// The entry point of the API.
+///
+/// # Thread safety
+///
+/// `Global` is `Send` and `Sync`, and all its methods can be called concurrently.
+/// Internally, every method acquires the storage locks in the order defined by the
+/// `Access` implementations, which is checked by the type system through `Token`.
+/// The user callbacks (buffer mapping, submitted work done, upload budget) are always
+/// fired with no locks held, so it's safe to call back into `Global` from them.
+///
+/// All the IDs and descriptors are `Send` and `Sync`. So are `ComputePass`, `RenderPass`,
+/// and `RenderBundleEncoder`: they only record commands on the CPU, without touching
+/// `Global`, so they can be recorded on worker threads and ended on any thread.
 #[derive(Debug)]
 pub struct Global<G: GlobalIdentityHandlerFactory> {
     pub instance: Instance,
@@ -829,6 +877,7 @@ impl<G: GlobalIdentityHandlerFactory> Drop for Global<G> {
 pub trait GfxBackend: hal::Backend {
     const VARIANT: Backend;
     fn hub<G: GlobalIdentityHandlerFactory>(global: &Global<G>) -> &Hub<Self, G>;
+    fn get_surface(surface: &Surface) -> Option<&Self::Surface>;
     fn get_surface_mut(surface: &mut Surface) -> &mut Self::Surface;
 }
 
@@ -838,6 +887,9 @@ impl GfxBackend for backend::Vulkan {
     fn hub<G: GlobalIdentityHandlerFactory>(global: &Global<G>) -> &Hub<Self, G> {
         &global.hubs.vulkan
     }
+    fn get_surface(surface: &Surface) -> Option<&Self::Surface> {
+        surface.vulkan.as_ref()
+    }
     fn get_surface_mut(surface: &mut Surface) -> &mut Self::Surface {
         surface.vulkan.as_mut().unwrap()
     }
@@ -849,6 +901,9 @@ impl GfxBackend for backend::Metal {
     fn hub<G: GlobalIdentityHandlerFactory>(global: &Global<G>) -> &Hub<Self, G> {
         &global.hubs.metal
     }
+    fn get_surface(surface: &Surface) -> Option<&Self::Surface> {
+        surface.metal.as_ref()
+    }
     fn get_surface_mut(surface: &mut Surface) -> &mut Self::Surface {
         surface.metal.as_mut().unwrap()
     }
@@ -860,6 +915,9 @@ impl GfxBackend for backend::Dx12 {
     fn hub<G: GlobalIdentityHandlerFactory>(global: &Global<G>) -> &Hub<Self, G> {
         &global.hubs.dx12
     }
+    fn get_surface(surface: &Surface) -> Option<&Self::Surface> {
+        surface.dx12.as_ref()
+    }
     fn get_surface_mut(surface: &mut Surface) -> &mut Self::Surface {
         surface.dx12.as_mut().unwrap()
     }
@@ -871,6 +929,9 @@ impl GfxBackend for backend::Dx11 {
     fn hub<G: GlobalIdentityHandlerFactory>(global: &Global<G>) -> &Hub<Self, G> {
         &global.hubs.dx11
     }
+    fn get_surface(surface: &Surface) -> Option<&Self::Surface> {
+        surface.dx11.as_ref()
+    }
     fn get_surface_mut(surface: &mut Surface) -> &mut Self::Surface {
         surface.dx11.as_mut().unwrap()
     }
@@ -882,6 +943,9 @@ impl GfxBackend for backend::Gl {
     fn hub<G: GlobalIdentityHandlerFactory>(global: &Global<G>) -> &Hub<Self, G> {
         &global.hubs.gl
     }
+    fn get_surface(surface: &Surface) -> Option<&Self::Surface> {
+        surface.gl.as_ref()
+    }
     fn get_surface_mut(surface: &mut Surface) -> &mut Self::Surface {
         surface.gl.as_mut().unwrap()
     }
@@ -890,5 +954,27 @@ impl GfxBackend for backend::Gl {
 #[cfg(test)]
 fn _test_send_sync(global: &Global<IdentityManagerFactory>) {
     fn test_internal<T: Send + Sync>(_: T) {}
-    test_internal(global)
+    fn test_type<T: Send + Sync>() {}
+    test_internal(global);
+
+    test_type::<DeviceId>();
+    test_type::<BufferId>();
+    test_type::<TextureId>();
+    test_type::<CommandBufferId>();
+    test_type::<RenderBundleId>();
+    test_type::<crate::command::ComputePass>();
+    test_type::<crate::command::RenderPass>();
+    test_type::<crate::command::RenderBundleEncoder>();
+    test_type::<crate::resource::BufferDescriptor<'static>>();
+    test_type::<crate::device::queue::SubmittedWorkDoneClosure>();
+}
+
+#[test]
+fn test_deterministic_identity() {
+    let mut manager = IdentityManager::deterministic(0);
+    let first: BufferId = manager.alloc(Backend::Empty);
+    manager.free(first);
+    let second: BufferId = manager.alloc(Backend::Empty);
+    assert_eq!(first.unzip().0, 0);
+    assert_eq!(second.unzip().0, 1);
 }
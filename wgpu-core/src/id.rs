@@ -25,7 +25,9 @@ type Dummy = crate::backend::Empty;
     all(feature = "serde", not(feature = "replay")),
     derive(serde::Deserialize)
 )]
-pub struct Id<T>(NonZeroU64, PhantomData<T>);
+// Using `fn() -> T` keeps the IDs `Send` and `Sync` regardless of the resource
+// type, which may contain raw backend handles.
+pub struct Id<T>(NonZeroU64, PhantomData<fn() -> T>);
 
 // This type represents Id in a more readable (and editable) way.
 #[allow(dead_code)]
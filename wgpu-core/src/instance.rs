@@ -6,7 +6,7 @@ use crate::{
     backend, conv,
     device::{Device, DeviceDescriptor},
     hub::{GfxBackend, Global, GlobalIdentityHandlerFactory, Input, Token},
-    id::{AdapterId, DeviceId, SurfaceId, Valid},
+    id::{AdapterId, DeviceId, SurfaceId, SwapChainId, Valid},
     span, LabelHelpers, LifeGuard, PrivateFeatures, Stored, MAX_BIND_GROUPS,
 };
 
@@ -23,6 +23,16 @@ use thiserror::Error;
 /// provide a push-constant size limit.
 const MIN_PUSH_CONSTANT_SIZE: u32 = 128;
 
+/// Formats that can be reported for swap chains, in the order of preference.
+const SWAP_CHAIN_FORMATS: [wgt::TextureFormat; 6] = [
+    wgt::TextureFormat::Bgra8UnormSrgb,
+    wgt::TextureFormat::Rgba8UnormSrgb,
+    wgt::TextureFormat::Bgra8Unorm,
+    wgt::TextureFormat::Rgba8Unorm,
+    wgt::TextureFormat::Rgb10a2Unorm,
+    wgt::TextureFormat::Rgba16Float,
+];
+
 pub type RequestAdapterOptions = wgt::RequestAdapterOptions<SurfaceId>;
 
 #[derive(Debug)]
@@ -44,7 +54,15 @@ impl Instance {
         backends_map! {
             let map = |(backend, backend_create)| {
                 if backends.contains(backend.into()) {
-                    backend_create(name, version).ok()
+                    // A missing backend must not prevent the others from being used,
+                    // e.g. Vulkan compute on a server without any window system.
+                    match backend_create(name, version) {
+                        Ok(instance) => Some(instance),
+                        Err(err) => {
+                            tracing::warn!("Unable to create the {:?} instance: {:?}", backend, err);
+                            None
+                        }
+                    }
                 } else {
                     None
                 }
@@ -64,6 +82,37 @@ impl Instance {
         }
     }
 
+    #[cfg(feature = "raw-window-handle")]
+    pub(crate) fn create_surface(
+        &self,
+        handle: &impl raw_window_handle::HasRawWindowHandle,
+    ) -> Surface {
+        unsafe {
+            backends_map! {
+                let map = |inst| {
+                    inst
+                    .as_ref()
+                    .and_then(|inst| inst.create_surface(handle).map_err(|e| {
+                        tracing::warn!("Error: {:?}", e);
+                    }).ok())
+                };
+
+                Surface {
+                    #[cfg(vulkan)]
+                    vulkan: map(&self.vulkan),
+                    #[cfg(metal)]
+                    metal: map(&self.metal),
+                    #[cfg(dx12)]
+                    dx12: map(&self.dx12),
+                    #[cfg(dx11)]
+                    dx11: map(&self.dx11),
+                    #[cfg(gl)]
+                    gl: map(&self.gl),
+                }
+            }
+        }
+    }
+
     pub(crate) fn destroy_surface(&self, surface: Surface) {
         backends_map! {
             let map = |(surface_backend, self_backend)| {
@@ -116,12 +165,43 @@ impl crate::hub::Resource for Surface {
     }
 }
 
+/// Machine-readable description of an adapter, meant to be attached to bug reports.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "trace", derive(serde::Serialize))]
+#[cfg_attr(feature = "replay", derive(serde::Deserialize))]
+pub struct AdapterReport {
+    pub info: wgt::AdapterInfo,
+    pub features: wgt::Features,
+    pub limits: wgt::Limits,
+    pub driver_workarounds: wgt::DriverWorkarounds,
+    pub copy_alignments: wgt::CopyAlignments,
+    /// Features of every texture format, in the order of [`wgt::TextureFormat::ALL`].
+    pub texture_format_features: Vec<(wgt::TextureFormat, wgt::TextureFormatFeatures)>,
+}
+
+/// Outcome of the feature and limit negotiation at device creation.
+///
+/// It's logged when the device is created, and can be retrieved with
+/// `device_creation_report` to be attached to bug reports. The device is granted
+/// exactly the requested features and limits, which the adapter has to support.
+#[derive(Clone, Debug)]
+pub struct DeviceCreationReport {
+    pub adapter: wgt::AdapterInfo,
+    pub requested_features: wgt::Features,
+    pub adapter_features: wgt::Features,
+    pub requested_limits: wgt::Limits,
+    pub adapter_limits: wgt::Limits,
+    /// Backend features wgpu relies on internally, but the adapter doesn't have.
+    pub missing_internal_features: hal::Features,
+}
+
 #[derive(Debug)]
 pub struct Adapter<B: hal::Backend> {
     pub(crate) raw: hal::adapter::Adapter<B>,
     features: wgt::Features,
     pub(crate) private_features: PrivateFeatures,
     limits: wgt::Limits,
+    workarounds: wgt::DriverWorkarounds,
     life_guard: LifeGuard,
 }
 
@@ -188,9 +268,30 @@ impl<B: GfxBackend> Adapter<B> {
             wgt::Features::PIPELINE_STATISTICS_QUERY,
             adapter_features.contains(hal::Features::PIPELINE_STATISTICS_QUERY),
         );
-        #[cfg(not(target_os = "ios"))]
+        features.set(
+            wgt::Features::MESH_SHADER,
+            adapter_features.contains(hal::Features::TASK_SHADER | hal::Features::MESH_SHADER),
+        );
+
+        features.set(
+            wgt::Features::TEXTURE_COMPONENT_SWIZZLE,
+            B::VARIANT == wgt::Backend::Vulkan || B::VARIANT == wgt::Backend::Dx12,
+        );
+        features.set(
+            wgt::Features::RENDER_ATTACHMENT_3D_SLICES,
+            B::VARIANT == wgt::Backend::Vulkan,
+        );
+
+        let mut workarounds = wgt::DriverWorkarounds::empty();
         //TODO: https://github.com/gfx-rs/gfx/issues/3346
-        features.set(wgt::Features::ADDRESS_MODE_CLAMP_TO_BORDER, true);
+        workarounds.set(
+            wgt::DriverWorkarounds::NO_CLAMP_TO_BORDER,
+            cfg!(target_os = "ios"),
+        );
+        features.set(
+            wgt::Features::ADDRESS_MODE_CLAMP_TO_BORDER,
+            !workarounds.contains(wgt::DriverWorkarounds::NO_CLAMP_TO_BORDER),
+        );
 
         let private_features = PrivateFeatures {
             anisotropic_filtering: adapter_features.contains(hal::Features::SAMPLER_ANISOTROPY),
@@ -205,48 +306,94 @@ impl<B: GfxBackend> Adapter<B> {
                 .optimal_tiling
                 .contains(hal::format::ImageFeature::DEPTH_STENCIL_ATTACHMENT),
             timestamp_period: adapter_limits.timestamp_period,
+            copy_pitch_alignment: (adapter_limits.optimal_buffer_copy_pitch_alignment as u32)
+                .max(1),
         };
+        // The feature unlocks both formats at once, so it's only exposed if both support
+        // being a copy destination, see the documentation of the feature.
+        features.set(
+            wgt::Features::DEPTH_TEXTURE_COPY_DST,
+            B::VARIANT != wgt::Backend::Gl
+                && [
+                    wgt::TextureFormat::Depth32Float,
+                    wgt::TextureFormat::Depth24PlusStencil8,
+                ]
+                .iter()
+                .all(|&format| {
+                    raw.physical_device
+                        .format_properties(Some(conv::map_texture_format(format, private_features)))
+                        .optimal_tiling
+                        .contains(hal::format::ImageFeature::TRANSFER_DST)
+                }),
+        );
+        features.set(
+            wgt::Features::ADAPTER_SPECIFIC_COPY_ALIGNMENT,
+            B::VARIANT != wgt::Backend::Gl
+                && private_features.copy_pitch_alignment < wgt::COPY_BYTES_PER_ROW_ALIGNMENT,
+        );
 
         let default_limits = wgt::Limits::default();
+        let max_heap_size = raw
+            .physical_device
+            .memory_properties()
+            .memory_heaps
+            .iter()
+            .map(|heap| heap.size)
+            .max()
+            .unwrap_or(0);
+        if max_heap_size < default_limits.max_buffer_size {
+            workarounds |= wgt::DriverWorkarounds::LIMITS_BELOW_DEFAULTS;
+        }
+        let mut raise_to_default = |reported: u32, default: u32| {
+            if reported < default {
+                workarounds |= wgt::DriverWorkarounds::LIMITS_BELOW_DEFAULTS;
+            }
+            reported.max(default)
+        };
 
         // All these casts to u32 are safe as the underlying vulkan types are u32s.
         // If another backend provides larger limits than u32, we need to clamp them to u32::MAX.
-        // TODO: fix all gfx-hal backends to produce limits we care about, and remove .max
+        // TODO: fix all gfx-hal backends to produce limits we care about, and remove the raising
         let limits = wgt::Limits {
-            max_bind_groups: (adapter_limits.max_bound_descriptor_sets as u32)
-                .min(MAX_BIND_GROUPS as u32)
-                .max(default_limits.max_bind_groups),
-            max_dynamic_uniform_buffers_per_pipeline_layout: (adapter_limits
-                .max_descriptor_set_uniform_buffers_dynamic
-                as u32)
-                .max(default_limits.max_dynamic_uniform_buffers_per_pipeline_layout),
-            max_dynamic_storage_buffers_per_pipeline_layout: (adapter_limits
-                .max_descriptor_set_storage_buffers_dynamic
-                as u32)
-                .max(default_limits.max_dynamic_storage_buffers_per_pipeline_layout),
-            max_sampled_textures_per_shader_stage: (adapter_limits
-                .max_per_stage_descriptor_sampled_images
-                as u32)
-                .max(default_limits.max_sampled_textures_per_shader_stage),
-            max_samplers_per_shader_stage: (adapter_limits.max_per_stage_descriptor_samplers
-                as u32)
-                .max(default_limits.max_samplers_per_shader_stage),
-            max_storage_buffers_per_shader_stage: (adapter_limits
-                .max_per_stage_descriptor_storage_buffers
-                as u32)
-                .max(default_limits.max_storage_buffers_per_shader_stage),
-            max_storage_textures_per_shader_stage: (adapter_limits
-                .max_per_stage_descriptor_storage_images
-                as u32)
-                .max(default_limits.max_storage_textures_per_shader_stage),
-            max_uniform_buffers_per_shader_stage: (adapter_limits
-                .max_per_stage_descriptor_uniform_buffers
-                as u32)
-                .max(default_limits.max_uniform_buffers_per_shader_stage),
-            max_uniform_buffer_binding_size: (adapter_limits.max_uniform_buffer_range as u32)
-                .max(default_limits.max_uniform_buffer_binding_size),
+            max_bind_groups: raise_to_default(
+                (adapter_limits.max_bound_descriptor_sets as u32).min(MAX_BIND_GROUPS as u32),
+                default_limits.max_bind_groups,
+            ),
+            max_dynamic_uniform_buffers_per_pipeline_layout: raise_to_default(
+                adapter_limits.max_descriptor_set_uniform_buffers_dynamic as u32,
+                default_limits.max_dynamic_uniform_buffers_per_pipeline_layout,
+            ),
+            max_dynamic_storage_buffers_per_pipeline_layout: raise_to_default(
+                adapter_limits.max_descriptor_set_storage_buffers_dynamic as u32,
+                default_limits.max_dynamic_storage_buffers_per_pipeline_layout,
+            ),
+            max_sampled_textures_per_shader_stage: raise_to_default(
+                adapter_limits.max_per_stage_descriptor_sampled_images as u32,
+                default_limits.max_sampled_textures_per_shader_stage,
+            ),
+            max_samplers_per_shader_stage: raise_to_default(
+                adapter_limits.max_per_stage_descriptor_samplers as u32,
+                default_limits.max_samplers_per_shader_stage,
+            ),
+            max_storage_buffers_per_shader_stage: raise_to_default(
+                adapter_limits.max_per_stage_descriptor_storage_buffers as u32,
+                default_limits.max_storage_buffers_per_shader_stage,
+            ),
+            max_storage_textures_per_shader_stage: raise_to_default(
+                adapter_limits.max_per_stage_descriptor_storage_images as u32,
+                default_limits.max_storage_textures_per_shader_stage,
+            ),
+            max_uniform_buffers_per_shader_stage: raise_to_default(
+                adapter_limits.max_per_stage_descriptor_uniform_buffers as u32,
+                default_limits.max_uniform_buffers_per_shader_stage,
+            ),
+            max_uniform_buffer_binding_size: raise_to_default(
+                adapter_limits.max_uniform_buffer_range as u32,
+                default_limits.max_uniform_buffer_binding_size,
+            ),
             max_push_constant_size: (adapter_limits.max_push_constants_size as u32)
                 .max(MIN_PUSH_CONSTANT_SIZE), // As an extension, the default is always 0, so define a separate minimum.
+            max_buffer_size: max_heap_size.max(default_limits.max_buffer_size),
         };
 
         Self {
@@ -254,6 +401,7 @@ impl<B: GfxBackend> Adapter<B> {
             features,
             private_features,
             limits,
+            workarounds,
             life_guard: LifeGuard::new("<Adapter>"),
         }
     }
@@ -296,6 +444,99 @@ impl<B: GfxBackend> Adapter<B> {
         Ok(wgt::TextureFormat::Bgra8UnormSrgb)
     }
 
+    fn check_surface_queue_family(
+        &self,
+        surface: &GfxSurface<B>,
+    ) -> Result<(), GetSurfaceCapabilitiesError> {
+        if surface.supports_queue_family(&self.raw.queue_families[0]) {
+            Ok(())
+        } else {
+            Err(GetSurfaceCapabilitiesError::UnsupportedQueueFamily)
+        }
+    }
+
+    /// Check if a device created from this adapter can present to `surface`.
+    ///
+    /// Unlike the other surface queries, this doesn't fail when the surface
+    /// couldn't be created for this backend.
+    pub fn is_surface_supported(&self, surface: &Surface) -> bool {
+        B::get_surface(surface).map_or(false, |surface| {
+            self.check_surface_queue_family(surface).is_ok()
+        })
+    }
+
+    /// Formats a swap chain on `surface` can be created with, the preferred ones first.
+    pub fn get_surface_formats(
+        &self,
+        surface: &mut Surface,
+    ) -> Result<Vec<wgt::TextureFormat>, GetSurfaceCapabilitiesError> {
+        span!(_guard, INFO, "Adapter::get_surface_formats");
+
+        let surface = B::get_surface_mut(surface);
+        self.check_surface_queue_family(surface)?;
+        let formats = match surface.supported_formats(&self.raw.physical_device) {
+            Some(formats) => SWAP_CHAIN_FORMATS
+                .iter()
+                .cloned()
+                .filter(|&format| {
+                    formats.contains(&conv::map_texture_format(format, self.private_features))
+                })
+                .collect(),
+            // If no formats were returned, the surface accepts any of them.
+            None => SWAP_CHAIN_FORMATS.to_vec(),
+        };
+        Ok(formats)
+    }
+
+    /// Present modes a swap chain on `surface` can be created with.
+    pub fn get_surface_present_modes(
+        &self,
+        surface: &mut Surface,
+    ) -> Result<Vec<wgt::PresentMode>, GetSurfaceCapabilitiesError> {
+        span!(_guard, INFO, "Adapter::get_surface_present_modes");
+
+        let surface = B::get_surface_mut(surface);
+        self.check_surface_queue_family(surface)?;
+        let caps = surface.capabilities(&self.raw.physical_device);
+        let modes = [
+            wgt::PresentMode::Fifo,
+            wgt::PresentMode::FifoRelaxed,
+            wgt::PresentMode::Mailbox,
+            wgt::PresentMode::Immediate,
+        ]
+        .iter()
+        .cloned()
+        .filter(|&mode| caps.present_modes.contains(conv::map_present_mode(mode)))
+        .collect();
+        Ok(modes)
+    }
+
+    /// Composite alpha modes a swap chain on `surface` can be created with.
+    pub fn get_surface_alpha_modes(
+        &self,
+        surface: &mut Surface,
+    ) -> Result<Vec<wgt::CompositeAlphaMode>, GetSurfaceCapabilitiesError> {
+        span!(_guard, INFO, "Adapter::get_surface_alpha_modes");
+
+        let surface = B::get_surface_mut(surface);
+        self.check_surface_queue_family(surface)?;
+        let caps = surface.capabilities(&self.raw.physical_device);
+        let modes = [
+            wgt::CompositeAlphaMode::Opaque,
+            wgt::CompositeAlphaMode::PreMultiplied,
+            wgt::CompositeAlphaMode::PostMultiplied,
+            wgt::CompositeAlphaMode::Inherit,
+        ]
+        .iter()
+        .cloned()
+        .filter(|&mode| {
+            caps.composite_alpha_modes
+                .contains(conv::map_composite_alpha_mode(mode))
+        })
+        .collect();
+        Ok(modes)
+    }
+
     pub(crate) fn get_texture_format_features(
         &self,
         format: wgt::TextureFormat,
@@ -330,6 +571,33 @@ impl<B: GfxBackend> Adapter<B> {
         if texture_format_properties.contains(hal::format::ImageFeature::STORAGE_READ_WRITE) {
             flags |= wgt::TextureFormatFeatureFlags::STORAGE_READ_WRITE;
         }
+        if texture_format_properties.contains(hal::format::ImageFeature::SAMPLED_LINEAR) {
+            flags |= wgt::TextureFormatFeatureFlags::FILTERABLE;
+        }
+        if texture_format_properties.contains(hal::format::ImageFeature::COLOR_ATTACHMENT_BLEND) {
+            flags |= wgt::TextureFormatFeatureFlags::BLENDABLE;
+        }
+
+        let limits = self.raw.physical_device.limits();
+        let sample_counts = if texture_format_properties
+            .contains(hal::format::ImageFeature::DEPTH_STENCIL_ATTACHMENT)
+        {
+            limits.framebuffer_depth_sample_counts & limits.framebuffer_stencil_sample_counts
+        } else if texture_format_properties.contains(hal::format::ImageFeature::COLOR_ATTACHMENT) {
+            limits.framebuffer_color_sample_counts
+        } else {
+            0
+        };
+        for &(count, flag) in &[
+            (2, wgt::TextureFormatFeatureFlags::MULTISAMPLE_X2),
+            (4, wgt::TextureFormatFeatureFlags::MULTISAMPLE_X4),
+            (8, wgt::TextureFormatFeatureFlags::MULTISAMPLE_X8),
+            (16, wgt::TextureFormatFeatureFlags::MULTISAMPLE_X16),
+        ] {
+            if sample_counts & count != 0 {
+                flags |= flag;
+            }
+        }
 
         wgt::TextureFormatFeatures {
             allowed_usages,
@@ -371,11 +639,9 @@ impl<B: GfxBackend> Adapter<B> {
             | hal::Features::SAMPLER_ANISOTROPY
             | hal::Features::IMAGE_CUBE_ARRAY;
         let mut enabled_features = available_features & wishful_features;
-        if enabled_features != wishful_features {
-            tracing::warn!(
-                "Missing internal features: {:?}",
-                wishful_features - enabled_features
-            );
+        let missing_internal_features = wishful_features - enabled_features;
+        if !missing_internal_features.is_empty() {
+            tracing::warn!("Missing internal features: {:?}", missing_internal_features);
         }
 
         // Features
@@ -435,6 +701,10 @@ impl<B: GfxBackend> Adapter<B> {
             desc.features
                 .contains(wgt::Features::PIPELINE_STATISTICS_QUERY),
         );
+        enabled_features.set(
+            hal::Features::TASK_SHADER | hal::Features::MESH_SHADER,
+            desc.features.contains(wgt::Features::MESH_SHADER),
+        );
 
         let family = self
             .raw
@@ -469,12 +739,30 @@ impl<B: GfxBackend> Adapter<B> {
             BIND_BUFFER_ALIGNMENT % limits.min_uniform_buffer_offset_alignment,
             "Adapter uniform buffer offset alignment not compatible with WGPU"
         );
-        if self.limits < desc.limits {
+        if !desc.limits.check_limits(&self.limits) {
             return Err(RequestDeviceError::LimitsExceeded);
         }
 
         let mem_props = phd.memory_properties();
 
+        let report = DeviceCreationReport {
+            adapter: conv::map_adapter_info(self.raw.info.clone(), B::VARIANT),
+            requested_features: desc.features,
+            adapter_features: self.features,
+            requested_limits: desc.limits.clone(),
+            adapter_limits: self.limits.clone(),
+            missing_internal_features,
+        };
+        tracing::info!(
+            adapter = ?report.adapter,
+            requested_features = ?report.requested_features,
+            adapter_features = ?report.adapter_features,
+            requested_limits = ?report.requested_limits,
+            adapter_limits = ?report.adapter_limits,
+            missing_internal_features = ?report.missing_internal_features,
+            "Creating device"
+        );
+
         Device::new(
             gpu.device,
             Stored {
@@ -486,10 +774,50 @@ impl<B: GfxBackend> Adapter<B> {
             limits,
             self.private_features,
             desc,
+            report,
             trace_path,
         )
         .or(Err(RequestDeviceError::OutOfMemory))
     }
+
+    fn copy_alignments(&self) -> wgt::CopyAlignments {
+        let limits = self.raw.physical_device.limits();
+        wgt::CopyAlignments {
+            bytes_per_row: self.private_features.copy_pitch_alignment,
+            buffer_offset: limits.optimal_buffer_copy_offset_alignment.max(1),
+        }
+    }
+
+    fn power_hints(&self) -> wgt::PowerHints {
+        wgt::PowerHints {
+            on_battery: query_on_battery(),
+            //TODO: gfx-hal doesn't expose the throttling state of the physical device
+            throttled: None,
+        }
+    }
+}
+
+/// Checks the power supplies reported by sysfs: a discharging battery means we are
+/// running on battery, an online mains (or USB) supply means we are not.
+#[cfg(target_os = "linux")]
+fn query_on_battery() -> Option<bool> {
+    let mut on_battery = None;
+    for entry in std::fs::read_dir("/sys/class/power_supply").ok()?.flatten() {
+        let path = entry.path();
+        let read = |name: &str| std::fs::read_to_string(path.join(name)).unwrap_or_default();
+        match read("type").trim() {
+            "Battery" if read("status").trim() == "Discharging" => return Some(true),
+            "Mains" | "USB" if read("online").trim() == "1" => on_battery = Some(false),
+            _ => {}
+        }
+    }
+    on_battery
+}
+
+#[cfg(not(target_os = "linux"))]
+fn query_on_battery() -> Option<bool> {
+    //TODO: GetSystemPowerStatus on Windows, IOPSCopyPowerSourcesInfo on Apple platforms
+    None
 }
 
 impl<B: hal::Backend> crate::hub::Resource for Adapter<B> {
@@ -500,6 +828,24 @@ impl<B: hal::Backend> crate::hub::Resource for Adapter<B> {
     }
 }
 
+#[derive(Clone, Debug, Error)]
+pub enum RecreateSurfaceError {
+    #[error("invalid surface")]
+    InvalidSurface,
+    #[error("swap chain {0:?} of the surface has to be invalidated first")]
+    SwapChainNotInvalidated(SwapChainId),
+}
+
+#[derive(Clone, Debug, Error)]
+pub enum GetSurfaceCapabilitiesError {
+    #[error("invalid adapter")]
+    InvalidAdapter,
+    #[error("invalid surface")]
+    InvalidSurface,
+    #[error("surface does not support the adapter's queue family")]
+    UnsupportedQueueFamily,
+}
+
 #[derive(Clone, Debug, Error)]
 pub enum GetSwapChainPreferredFormatError {
     #[error("no suitable format found")]
@@ -551,6 +897,33 @@ impl<I: Clone> AdapterInputs<'_, I> {
     }
 }
 
+/// Window handle assembled from raw platform pointers.
+#[cfg(all(feature = "raw-window-handle", not(wasm), not(apple)))]
+struct RawHandle(raw_window_handle::RawWindowHandle);
+
+#[cfg(all(feature = "raw-window-handle", not(wasm), not(apple)))]
+unsafe impl raw_window_handle::HasRawWindowHandle for RawHandle {
+    fn raw_window_handle(&self) -> raw_window_handle::RawWindowHandle {
+        self.0
+    }
+}
+
+/// Backend forced by the `WGPU_BACKEND` environment variable, if any.
+fn backend_from_env() -> Option<Backend> {
+    let name = std::env::var("WGPU_BACKEND").ok()?;
+    match name.to_lowercase().as_str() {
+        "vulkan" | "vk" => Some(Backend::Vulkan),
+        "metal" | "mtl" => Some(Backend::Metal),
+        "dx12" | "d3d12" => Some(Backend::Dx12),
+        "dx11" | "d3d11" => Some(Backend::Dx11),
+        "gl" | "opengl" => Some(Backend::Gl),
+        other => {
+            tracing::warn!("Ignoring unknown backend {:?} in WGPU_BACKEND", other);
+            None
+        }
+    }
+}
+
 #[error("adapter is invalid")]
 #[derive(Clone, Debug, Error)]
 pub struct InvalidAdapter;
@@ -572,29 +945,189 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
     ) -> SurfaceId {
         span!(_guard, INFO, "Instance::create_surface");
 
-        let surface = unsafe {
-            backends_map! {
-                let map = |inst| {
-                    inst
-                    .as_ref()
-                    .and_then(|inst| inst.create_surface(handle).map_err(|e| {
-                        tracing::warn!("Error: {:?}", e);
-                    }).ok())
-                };
+        let surface = self.instance.create_surface(handle);
+        let mut token = Token::root();
+        let id = self.surfaces.register_identity(id_in, surface, &mut token);
+        id.0
+    }
 
-                Surface {
-                    #[cfg(vulkan)]
-                    vulkan: map(&self.instance.vulkan),
-                    #[cfg(metal)]
-                    metal: map(&self.instance.metal),
-                    #[cfg(dx12)]
-                    dx12: map(&self.instance.dx12),
-                    #[cfg(dx11)]
-                    dx11: map(&self.instance.dx11),
-                    #[cfg(gl)]
-                    gl: map(&self.instance.gl),
+    /// Replace the surface with a new one for `handle`, keeping its ID.
+    ///
+    /// This is used when the native window is re-created, e.g. when an Android
+    /// application is resumed. The swap chains of the surface must have been
+    /// invalidated beforehand with `swap_chain_invalidate`, or this fails with
+    /// `SwapChainNotInvalidated`. They need to be created again afterwards.
+    #[cfg(feature = "raw-window-handle")]
+    pub fn surface_recreate(
+        &self,
+        id: SurfaceId,
+        handle: &impl raw_window_handle::HasRawWindowHandle,
+    ) -> Result<(), RecreateSurfaceError> {
+        span!(_guard, INFO, "Surface::recreate");
+
+        let mut token = Token::root();
+        let (mut surface_guard, mut token) = self.surfaces.write(&mut token);
+        let surface = surface_guard
+            .get_mut(id)
+            .map_err(|_| RecreateSurfaceError::InvalidSurface)?;
+
+        // The swap chain images still refer to the old surface.
+        backends_map! {
+            let map = |(backend, backend_hub)| {
+                let hub = backend_hub(self);
+                let (_, mut token) = hub.devices.read(&mut token);
+                let (swap_chain_guard, _) = hub.swap_chains.read(&mut token);
+                let sc_id = id.to_swap_chain_id(backend);
+                if let Ok(sc) = swap_chain_guard.get(sc_id) {
+                    if !sc.invalidated {
+                        return Err(RecreateSurfaceError::SwapChainNotInvalidated(sc_id));
+                    }
                 }
-            }
+            };
+
+            #[cfg(vulkan)]
+            map((Backend::Vulkan, backend::Vulkan::hub)),
+            #[cfg(metal)]
+            map((Backend::Metal, backend::Metal::hub)),
+            #[cfg(dx12)]
+            map((Backend::Dx12, backend::Dx12::hub)),
+            #[cfg(dx11)]
+            map((Backend::Dx11, backend::Dx11::hub)),
+            #[cfg(gl)]
+            map((Backend::Gl, backend::Gl::hub)),
+        }
+
+        let old = std::mem::replace(surface, self.instance.create_surface(handle));
+        self.instance.destroy_surface(old);
+        Ok(())
+    }
+
+    /// Create a surface for an X11 window.
+    ///
+    /// # Safety
+    ///
+    /// `display` must be a valid Xlib `Display`, and `window` a window of it that
+    /// outlives the surface.
+    #[cfg(all(
+        feature = "raw-window-handle",
+        any(
+            target_os = "linux",
+            target_os = "dragonfly",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd"
+        )
+    ))]
+    pub unsafe fn instance_create_surface_from_xlib(
+        &self,
+        display: *mut std::ffi::c_void,
+        window: std::os::raw::c_ulong,
+        id_in: Input<G, SurfaceId>,
+    ) -> SurfaceId {
+        let handle =
+            raw_window_handle::RawWindowHandle::Xlib(raw_window_handle::unix::XlibHandle {
+                window,
+                display,
+                ..raw_window_handle::unix::XlibHandle::empty()
+            });
+        self.instance_create_surface(&RawHandle(handle), id_in)
+    }
+
+    /// Create a surface for a Wayland surface.
+    ///
+    /// # Safety
+    ///
+    /// `display` must be a valid `wl_display`, and `surface` a `wl_surface` of it that
+    /// outlives the created surface.
+    #[cfg(all(
+        feature = "raw-window-handle",
+        any(
+            target_os = "linux",
+            target_os = "dragonfly",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd"
+        )
+    ))]
+    pub unsafe fn instance_create_surface_from_wayland(
+        &self,
+        display: *mut std::ffi::c_void,
+        surface: *mut std::ffi::c_void,
+        id_in: Input<G, SurfaceId>,
+    ) -> SurfaceId {
+        let handle =
+            raw_window_handle::RawWindowHandle::Wayland(raw_window_handle::unix::WaylandHandle {
+                surface,
+                display,
+                ..raw_window_handle::unix::WaylandHandle::empty()
+            });
+        self.instance_create_surface(&RawHandle(handle), id_in)
+    }
+
+    /// Create a surface for a Win32 window.
+    ///
+    /// # Safety
+    ///
+    /// `hwnd` must be a valid window handle, created from the module `hinstance`,
+    /// that outlives the surface.
+    #[cfg(all(feature = "raw-window-handle", windows))]
+    pub unsafe fn instance_create_surface_from_win32_hwnd(
+        &self,
+        hinstance: *mut std::ffi::c_void,
+        hwnd: *mut std::ffi::c_void,
+        id_in: Input<G, SurfaceId>,
+    ) -> SurfaceId {
+        let handle = raw_window_handle::RawWindowHandle::Windows(
+            raw_window_handle::windows::WindowsHandle {
+                hinstance,
+                hwnd,
+                ..raw_window_handle::windows::WindowsHandle::empty()
+            },
+        );
+        self.instance_create_surface(&RawHandle(handle), id_in)
+    }
+
+    /// Create a surface for an Android native window.
+    ///
+    /// # Safety
+    ///
+    /// `a_native_window` must be a valid `ANativeWindow` that outlives the surface.
+    #[cfg(all(feature = "raw-window-handle", target_os = "android"))]
+    pub unsafe fn instance_create_surface_from_android_ndk(
+        &self,
+        a_native_window: *mut std::ffi::c_void,
+        id_in: Input<G, SurfaceId>,
+    ) -> SurfaceId {
+        let handle = raw_window_handle::RawWindowHandle::Android(
+            raw_window_handle::android::AndroidHandle {
+                a_native_window,
+                ..raw_window_handle::android::AndroidHandle::empty()
+            },
+        );
+        self.instance_create_surface(&RawHandle(handle), id_in)
+    }
+
+    /// Create a surface rendering into an existing `CAMetalLayer`.
+    ///
+    /// # Safety
+    ///
+    /// `layer` must point to a valid `CAMetalLayer` that outlives the surface.
+    #[cfg(metal)]
+    pub unsafe fn instance_create_surface_from_metal_layer(
+        &self,
+        layer: *mut std::ffi::c_void,
+        id_in: Input<G, SurfaceId>,
+    ) -> SurfaceId {
+        span!(_guard, INFO, "Instance::create_surface_from_metal_layer");
+
+        let surface = Surface {
+            #[cfg(vulkan)]
+            vulkan: None,
+            metal: self
+                .instance
+                .metal
+                .as_ref()
+                .map(|inst| inst.create_surface_from_layer(&*(layer as *const _))),
         };
 
         let mut token = Token::root();
@@ -650,6 +1183,12 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         adapters
     }
 
+    /// Pick an adapter matching `desc` among the backends present in `inputs`.
+    ///
+    /// The choice can be overridden without recompiling the application:
+    /// `WGPU_BACKEND` restricts the search to a single backend (`vulkan`, `metal`,
+    /// `dx12`, `dx11` or `gl`), and `WGPU_ADAPTER_NAME` only keeps the adapters
+    /// whose name contains the given string, ignoring case.
     pub fn request_adapter(
         &self,
         desc: &RequestAdapterOptions,
@@ -670,11 +1209,23 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             .transpose()?;
         let mut device_types = Vec::new();
 
-        let mut id_vulkan = inputs.find(Backend::Vulkan);
-        let mut id_metal = inputs.find(Backend::Metal);
-        let mut id_dx12 = inputs.find(Backend::Dx12);
-        let mut id_dx11 = inputs.find(Backend::Dx11);
-        let mut id_gl = inputs.find(Backend::Gl);
+        let backend_override = backend_from_env();
+        if let Some(backend) = backend_override {
+            tracing::info!("Backend is forced to {:?} by WGPU_BACKEND", backend);
+        }
+        let adapter_name = std::env::var("WGPU_ADAPTER_NAME")
+            .ok()
+            .map(|name| name.to_lowercase());
+        let find = |backend| match backend_override {
+            Some(forced) if forced != backend => None,
+            _ => inputs.find(backend),
+        };
+
+        let mut id_vulkan = find(Backend::Vulkan);
+        let mut id_metal = find(Backend::Metal);
+        let mut id_dx12 = find(Backend::Dx12);
+        let mut id_dx11 = find(Backend::Dx11);
+        let mut id_gl = find(Backend::Gl);
 
         backends_map! {
             let map = |(instance_backend, id_backend, surface_backend)| {
@@ -689,6 +1240,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                                     .map_or(false, |qf| surface_backend.supports_queue_family(qf))
                             });
                         }
+                        if let Some(ref name) = adapter_name {
+                            adapters.retain(|a| a.info.name.to_lowercase().contains(name.as_str()));
+                        }
                         device_types.extend(adapters.iter().map(|ad| ad.info.device_type.clone()));
                         adapters
                     }
@@ -823,6 +1377,31 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             .map_err(|_| InvalidAdapter)
     }
 
+    pub fn adapter_get_report<B: GfxBackend>(
+        &self,
+        adapter_id: AdapterId,
+    ) -> Result<AdapterReport, InvalidAdapter> {
+        span!(_guard, INFO, "Adapter::get_report");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (adapter_guard, _) = hub.adapters.read(&mut token);
+        adapter_guard
+            .get(adapter_id)
+            .map(|adapter| AdapterReport {
+                info: conv::map_adapter_info(adapter.raw.info.clone(), adapter_id.backend()),
+                features: adapter.features,
+                limits: adapter.limits.clone(),
+                driver_workarounds: adapter.workarounds,
+                copy_alignments: adapter.copy_alignments(),
+                texture_format_features: wgt::TextureFormat::ALL
+                    .iter()
+                    .map(|&format| (format, adapter.get_texture_format_features(format)))
+                    .collect(),
+            })
+            .map_err(|_| InvalidAdapter)
+    }
+
     pub fn adapter_get_texture_format_features<B: GfxBackend>(
         &self,
         adapter_id: AdapterId,
@@ -869,6 +1448,51 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             .map_err(|_| InvalidAdapter)
     }
 
+    pub fn adapter_driver_workarounds<B: GfxBackend>(
+        &self,
+        adapter_id: AdapterId,
+    ) -> Result<wgt::DriverWorkarounds, InvalidAdapter> {
+        span!(_guard, INFO, "Adapter::driver_workarounds");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (adapter_guard, _) = hub.adapters.read(&mut token);
+        adapter_guard
+            .get(adapter_id)
+            .map(|adapter| adapter.workarounds)
+            .map_err(|_| InvalidAdapter)
+    }
+
+    pub fn adapter_copy_alignments<B: GfxBackend>(
+        &self,
+        adapter_id: AdapterId,
+    ) -> Result<wgt::CopyAlignments, InvalidAdapter> {
+        span!(_guard, INFO, "Adapter::copy_alignments");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (adapter_guard, _) = hub.adapters.read(&mut token);
+        adapter_guard
+            .get(adapter_id)
+            .map(|adapter| adapter.copy_alignments())
+            .map_err(|_| InvalidAdapter)
+    }
+
+    pub fn adapter_power_hints<B: GfxBackend>(
+        &self,
+        adapter_id: AdapterId,
+    ) -> Result<wgt::PowerHints, InvalidAdapter> {
+        span!(_guard, INFO, "Adapter::power_hints");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (adapter_guard, _) = hub.adapters.read(&mut token);
+        adapter_guard
+            .get(adapter_id)
+            .map(|adapter| adapter.power_hints())
+            .map_err(|_| InvalidAdapter)
+    }
+
     pub fn adapter_get_timestamp_period<B: GfxBackend>(
         &self,
         adapter_id: AdapterId,
@@ -885,6 +1509,67 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             .map_err(|_| InvalidAdapter)
     }
 
+    /// Give temporary access to the backend adapter.
+    ///
+    /// The callback gets `None` if the adapter is invalid.
+    ///
+    /// The callback runs under the read lock of the adapter storage, and must not
+    /// call into this `Global`, see [`Global::device_as_hal`].
+    ///
+    /// # Safety
+    ///
+    /// The adapter must not be used to open another device for wgpu to use.
+    pub unsafe fn adapter_as_hal<
+        B: GfxBackend,
+        F: FnOnce(Option<&hal::adapter::Adapter<B>>) -> R,
+        R,
+    >(
+        &self,
+        adapter_id: AdapterId,
+        hal_adapter_callback: F,
+    ) -> R {
+        span!(_guard, INFO, "Adapter::as_hal");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (adapter_guard, _) = hub.adapters.read(&mut token);
+        let hal_adapter = adapter_guard
+            .get(adapter_id)
+            .ok()
+            .map(|adapter| &adapter.raw);
+
+        hal_adapter_callback(hal_adapter)
+    }
+
+    /// Give temporary access to the backend surface.
+    ///
+    /// The callback gets `None` if the surface is invalid, or wasn't created
+    /// for this backend.
+    ///
+    /// The callback runs under the read lock of the surface storage, and must not
+    /// call into this `Global`, see [`Global::device_as_hal`].
+    ///
+    /// # Safety
+    ///
+    /// The surface must not be configured through the backend, since wgpu manages
+    /// its swap chain.
+    pub unsafe fn surface_as_hal<B: GfxBackend, F: FnOnce(Option<&B::Surface>) -> R, R>(
+        &self,
+        surface_id: SurfaceId,
+        hal_surface_callback: F,
+    ) -> R {
+        span!(_guard, INFO, "Surface::as_hal");
+
+        let mut token = Token::root();
+        let (surface_guard, _) = self.surfaces.read(&mut token);
+        let hal_surface = surface_guard
+            .get(surface_id)
+            .ok()
+            .and_then(|surface| B::get_surface(surface));
+
+        hal_surface_callback(hal_surface)
+    }
+
     pub fn adapter_drop<B: GfxBackend>(&self, adapter_id: AdapterId) {
         span!(_guard, INFO, "Adapter::drop");
 
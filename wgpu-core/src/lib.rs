@@ -53,7 +53,7 @@ use std::{borrow::Cow, os::raw::c_char, ptr};
 
 pub const MAX_BIND_GROUPS: usize = 8;
 
-type SubmissionIndex = usize;
+pub type SubmissionIndex = usize;
 type Index = u32;
 type Epoch = u32;
 
@@ -223,6 +223,7 @@ struct PrivateFeatures {
     texture_d24: bool,
     texture_d24_s8: bool,
     timestamp_period: f32,
+    copy_pitch_alignment: u32,
 }
 
 #[macro_export]
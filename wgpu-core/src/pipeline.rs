@@ -6,10 +6,13 @@ use crate::{
     binding_model::{CreateBindGroupLayoutError, CreatePipelineLayoutError},
     device::{DeviceError, RenderPassContext},
     hub::Resource,
-    id::{DeviceId, PipelineLayoutId, ShaderModuleId},
+    id::{BindGroupLayoutId, DeviceId, PipelineLayoutId, RenderPipelineId, ShaderModuleId},
     validation, Label, LifeGuard, Stored,
 };
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    hash::{Hash, Hasher},
+};
 use thiserror::Error;
 use wgt::{BufferAddress, IndexFormat, InputStepMode};
 
@@ -27,6 +30,66 @@ pub struct ShaderModuleDescriptor<'a> {
     pub label: Label<'a>,
     #[cfg_attr(any(feature = "replay", feature = "trace"), serde(default))]
     pub flags: wgt::ShaderFlags,
+    /// Origin of the lines of a preprocessed WGSL source, used to report parsing
+    /// errors against the original files. Empty if the source was not preprocessed.
+    #[cfg_attr(any(feature = "replay", feature = "trace"), serde(default))]
+    pub source_map: Cow<'a, [ShaderSourceMapping<'a>]>,
+}
+
+impl ShaderModuleDescriptor<'_> {
+    /// Translate a line of the provided source into the original file and line.
+    fn map_source_line(&self, line: usize) -> (Option<String>, usize) {
+        let mapping = self
+            .source_map
+            .iter()
+            .filter(|mapping| mapping.line as usize <= line)
+            .max_by_key(|mapping| mapping.line);
+        match mapping {
+            Some(mapping) => (
+                Some(mapping.file.to_string()),
+                mapping.original_line as usize + line - mapping.line as usize,
+            ),
+            None => (None, line),
+        }
+    }
+
+    pub(crate) fn parse_error(
+        &self,
+        line: usize,
+        column: usize,
+        message: String,
+    ) -> ShaderParseError {
+        let (file, line) = self.map_source_line(line);
+        ShaderParseError {
+            file,
+            line,
+            column,
+            message,
+        }
+    }
+}
+
+/// Lines of a preprocessed shader source taken from another file.
+///
+/// The lines of the source starting at `line` come from `file`, starting at
+/// `original_line`, up to the next mapping. Lines are numbered from 1.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "trace", derive(serde::Serialize))]
+#[cfg_attr(feature = "replay", derive(serde::Deserialize))]
+pub struct ShaderSourceMapping<'a> {
+    pub line: u32,
+    pub file: Cow<'a, str>,
+    pub original_line: u32,
+}
+
+#[derive(Clone, Debug, Error)]
+#[error("{}line {line}, column {column}: {message}", file.as_ref().map_or_else(String::new, |file| format!("{}, ", file)))]
+pub struct ShaderParseError {
+    /// Original file of the erroneous line, if the source has a source map.
+    pub file: Option<String>,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
 }
 
 #[derive(Debug)]
@@ -34,6 +97,8 @@ pub struct ShaderModule<B: hal::Backend> {
     pub(crate) raw: B::ShaderModule,
     pub(crate) device_id: Stored<DeviceId>,
     pub(crate) interface: Option<validation::Interface>,
+    /// Stable hash of the SPIR-V or WGSL source. Naga modules have none.
+    pub(crate) source_hash: Option<u64>,
     #[cfg(debug_assertions)]
     pub(crate) label: String,
 }
@@ -56,13 +121,38 @@ impl<B: hal::Backend> Resource for ShaderModule<B> {
 #[derive(Clone, Debug, Error)]
 pub enum CreateShaderModuleError {
     #[error("Failed to parse WGSL")]
-    Parsing,
+    Parsing(#[source] ShaderParseError),
+    #[error("Failed to compile the shader: {0}")]
+    Compilation(String),
     #[error(transparent)]
     Device(#[from] DeviceError),
     #[error(transparent)]
     Validation(#[from] naga::proc::ValidationError),
 }
 
+impl ShaderModuleSource<'_> {
+    /// Stable hash of the source code, if it has one.
+    pub(crate) fn stable_hash(&self) -> Option<u64> {
+        match *self {
+            ShaderModuleSource::SpirV(ref spv) => Some(wgt::stable_hash(&(0u32, &spv[..]))),
+            ShaderModuleSource::Wgsl(ref code) => Some(wgt::stable_hash(&(1u32, &code[..]))),
+            ShaderModuleSource::Naga(_) => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Error)]
+pub enum DescriptorHashError {
+    #[error("bind group layout {0:?} is invalid")]
+    InvalidBindGroupLayout(BindGroupLayoutId),
+    #[error("pipeline layout {0:?} is invalid")]
+    InvalidLayout(PipelineLayoutId),
+    #[error("shader module {0:?} is invalid")]
+    InvalidModule(ShaderModuleId),
+    #[error("shader module {0:?} was created from a Naga module, which has no stable hash")]
+    UnhashableModule(ShaderModuleId),
+}
+
 /// Describes a programmable pipeline stage.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "trace", derive(serde::Serialize))]
@@ -75,6 +165,18 @@ pub struct ProgrammableStageDescriptor<'a> {
     pub entry_point: Cow<'a, str>,
 }
 
+impl ProgrammableStageDescriptor<'_> {
+    fn hash_contents<H: Hasher, E>(
+        &self,
+        module_hash: &mut impl FnMut(ShaderModuleId) -> Result<u64, E>,
+        state: &mut H,
+    ) -> Result<(), E> {
+        module_hash(self.module)?.hash(state);
+        self.entry_point.hash(state);
+        Ok(())
+    }
+}
+
 /// Number of implicit bind groups derived at pipeline creation.
 pub type ImplicitBindGroupCount = u8;
 
@@ -102,6 +204,22 @@ pub struct ComputePipelineDescriptor<'a> {
     pub compute_stage: ProgrammableStageDescriptor<'a>,
 }
 
+impl ComputePipelineDescriptor<'_> {
+    /// Stable hash of the pipeline, with the hashes of the contents of its layout
+    /// and shader modules in place of their IDs. The label isn't hashed.
+    pub(crate) fn hash_contents<E>(
+        &self,
+        layout_hash: Option<u64>,
+        mut module_hash: impl FnMut(ShaderModuleId) -> Result<u64, E>,
+    ) -> Result<u64, E> {
+        let mut hasher = wgt::StableHasher::default();
+        wgt::hash_option(&layout_hash, &mut hasher);
+        self.compute_stage
+            .hash_contents(&mut module_hash, &mut hasher)?;
+        Ok(hasher.finish())
+    }
+}
+
 #[derive(Clone, Debug, Error)]
 pub enum CreateComputePipelineError {
     #[error(transparent)]
@@ -131,7 +249,7 @@ impl<B: hal::Backend> Resource for ComputePipeline<B> {
 }
 
 /// Describes how the vertex buffer is interpreted.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Hash)]
 #[cfg_attr(feature = "trace", derive(serde::Serialize))]
 #[cfg_attr(feature = "replay", derive(serde::Deserialize))]
 pub struct VertexBufferDescriptor<'a> {
@@ -154,6 +272,28 @@ pub struct VertexStateDescriptor<'a> {
     pub vertex_buffers: Cow<'a, [VertexBufferDescriptor<'a>]>,
 }
 
+impl Hash for VertexStateDescriptor<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        wgt::hash_option(&self.index_format, state);
+        self.vertex_buffers.hash(state);
+    }
+}
+
+/// Describes the stages of a mesh pipeline.
+///
+/// Primitives are emitted by the workgroups of a mesh shader, which are optionally
+/// launched by a task shader. The pipeline has no vertex input, its primitive
+/// topology is declared by the mesh shader, and it's drawn with `draw_mesh_tasks`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "trace", derive(serde::Serialize))]
+#[cfg_attr(feature = "replay", derive(serde::Deserialize))]
+pub struct MeshStagesDescriptor<'a> {
+    /// The compiled task stage and its entry point, if any.
+    pub task: Option<ProgrammableStageDescriptor<'a>>,
+    /// The compiled mesh stage and its entry point.
+    pub mesh: ProgrammableStageDescriptor<'a>,
+}
+
 /// Describes a render (graphics) pipeline.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "trace", derive(serde::Serialize))]
@@ -163,12 +303,20 @@ pub struct RenderPipelineDescriptor<'a> {
     /// The layout of bind groups for this pipeline.
     pub layout: Option<PipelineLayoutId>,
     /// The compiled vertex stage and its entry point.
+    ///
+    /// The entry point has to be empty if `mesh_stages` are provided. The module
+    /// is then never looked up, so any ID can be given.
     pub vertex_stage: ProgrammableStageDescriptor<'a>,
+    /// The compiled task and mesh stages, replacing the vertex stage, if any.
+    ///
+    /// Requires `Features::MESH_SHADER`.
+    #[cfg_attr(any(feature = "replay", feature = "trace"), serde(default))]
+    pub mesh_stages: Option<MeshStagesDescriptor<'a>>,
     /// The compiled fragment stage and its entry point, if any.
     pub fragment_stage: Option<ProgrammableStageDescriptor<'a>>,
     /// The rasterization process for this pipeline.
     pub rasterization_state: Option<wgt::RasterizationStateDescriptor>,
-    /// The primitive topology used to interpret vertices.
+    /// The primitive topology used to interpret vertices. Ignored by mesh pipelines.
     pub primitive_topology: wgt::PrimitiveTopology,
     /// The effect of draw calls on the color aspect of the output target.
     pub color_states: Cow<'a, [wgt::ColorStateDescriptor]>,
@@ -189,6 +337,67 @@ pub struct RenderPipelineDescriptor<'a> {
     /// The implicit mask produced for alpha of zero is guaranteed to be zero, and for alpha of one
     /// is guaranteed to be all 1-s.
     pub alpha_to_coverage_enabled: bool,
+    /// An existing pipeline this one is a variation of, for example differing only in the
+    /// blend or depth state. Backends supporting pipeline derivatives compile the new
+    /// pipeline faster, the others ignore it.
+    ///
+    /// The base pipeline must have been created with `allow_derivatives`.
+    #[cfg_attr(any(feature = "replay", feature = "trace"), serde(default))]
+    pub base_pipeline: Option<RenderPipelineId>,
+    /// Allow this pipeline to be used as the `base_pipeline` of later ones.
+    ///
+    /// Some backends keep extra data around for such pipelines, so this should only be set
+    /// on pipelines that other ones are derived from.
+    #[cfg_attr(any(feature = "replay", feature = "trace"), serde(default))]
+    pub allow_derivatives: bool,
+}
+
+impl RenderPipelineDescriptor<'_> {
+    /// Stable hash of the pipeline, with the hashes of the contents of its layout
+    /// and shader modules in place of their IDs.
+    ///
+    /// The label and the derivative options aren't hashed, so that they don't
+    /// affect the result.
+    pub(crate) fn hash_contents<E>(
+        &self,
+        layout_hash: Option<u64>,
+        mut module_hash: impl FnMut(ShaderModuleId) -> Result<u64, E>,
+    ) -> Result<u64, E> {
+        let mut hasher = wgt::StableHasher::default();
+        wgt::hash_option(&layout_hash, &mut hasher);
+        self.vertex_stage
+            .hash_contents(&mut module_hash, &mut hasher)?;
+        match self.mesh_stages {
+            Some(ref stages) => {
+                hasher.write_u32(1);
+                match stages.task {
+                    Some(ref task) => {
+                        hasher.write_u32(1);
+                        task.hash_contents(&mut module_hash, &mut hasher)?;
+                    }
+                    None => hasher.write_u32(0),
+                }
+                stages.mesh.hash_contents(&mut module_hash, &mut hasher)?;
+            }
+            None => hasher.write_u32(0),
+        }
+        match self.fragment_stage {
+            Some(ref stage) => {
+                hasher.write_u32(1);
+                stage.hash_contents(&mut module_hash, &mut hasher)?;
+            }
+            None => hasher.write_u32(0),
+        }
+        wgt::hash_option(&self.rasterization_state, &mut hasher);
+        self.primitive_topology.hash(&mut hasher);
+        self.color_states.hash(&mut hasher);
+        wgt::hash_option(&self.depth_stencil_state, &mut hasher);
+        self.vertex_state.hash(&mut hasher);
+        self.sample_count.hash(&mut hasher);
+        self.sample_mask.hash(&mut hasher);
+        self.alpha_to_coverage_enabled.hash(&mut hasher);
+        Ok(hasher.finish())
+    }
 }
 
 #[derive(Clone, Debug, Error)]
@@ -205,6 +414,10 @@ pub enum CreateRenderPipelineError {
     IncompatibleOutputFormat { index: u8 },
     #[error("invalid sample count {0}")]
     InvalidSampleCount(u32),
+    #[error("base pipeline {0:?} is invalid")]
+    InvalidBasePipeline(RenderPipelineId),
+    #[error("base pipeline {0:?} was not created with `allow_derivatives`")]
+    BasePipelineNotDerivable(RenderPipelineId),
     #[error("vertex buffer {index} stride {stride} does not respect `VERTEX_STRIDE_ALIGNMENT`")]
     UnalignedVertexStride { index: u32, stride: BufferAddress },
     #[error("vertex attribute at location {location} has invalid offset {offset}")]
@@ -214,6 +427,14 @@ pub enum CreateRenderPipelineError {
     },
     #[error("missing required device features {0:?}")]
     MissingFeature(wgt::Features),
+    #[error("fragment stage is provided, but the rasterizer discards all primitives")]
+    FragmentStageWithDiscard,
+    #[error("mesh pipelines can't have vertex buffers or an index format")]
+    MeshPipelineVertexInput,
+    #[error("mesh pipelines can't have a vertex stage entry point")]
+    VertexAndMeshStages,
+    #[error("mesh pipelines require an explicit layout, since mesh shaders can't be reflected")]
+    MeshPipelineImplicitLayout,
     #[error("error in stage {flag:?}")]
     Stage {
         flag: wgt::ShaderStage,
@@ -228,6 +449,8 @@ bitflags::bitflags! {
         const BLEND_COLOR = 1;
         const STENCIL_REFERENCE = 2;
         const WRITES_DEPTH_STENCIL = 4;
+        const MESH_SHADING = 8;
+        const ALLOWS_DERIVATIVES = 16;
     }
 }
 
@@ -250,3 +473,89 @@ impl<B: hal::Backend> Resource for RenderPipeline<B> {
         &self.life_guard
     }
 }
+
+#[test]
+fn test_map_source_line() {
+    let mut desc = ShaderModuleDescriptor {
+        label: None,
+        flags: wgt::ShaderFlags::empty(),
+        source_map: Cow::Borrowed(&[]),
+    };
+    // Without a source map, lines are reported as they are.
+    assert_eq!(desc.map_source_line(1), (None, 1));
+    assert_eq!(desc.map_source_line(100), (None, 100));
+
+    desc.source_map = Cow::Owned(vec![
+        ShaderSourceMapping {
+            line: 3,
+            file: Cow::Borrowed("common.wgsl"),
+            original_line: 10,
+        },
+        ShaderSourceMapping {
+            line: 5,
+            file: Cow::Borrowed("main.wgsl"),
+            original_line: 2,
+        },
+    ]);
+    // Lines before the first mapping aren't mapped.
+    assert_eq!(desc.map_source_line(1), (None, 1));
+    assert_eq!(
+        desc.map_source_line(3),
+        (Some("common.wgsl".to_string()), 10)
+    );
+    assert_eq!(
+        desc.map_source_line(4),
+        (Some("common.wgsl".to_string()), 11)
+    );
+    assert_eq!(desc.map_source_line(5), (Some("main.wgsl".to_string()), 2));
+    // The last mapping extends past the end of the source.
+    assert_eq!(
+        desc.map_source_line(20),
+        (Some("main.wgsl".to_string()), 17)
+    );
+}
+
+#[test]
+fn test_pipeline_hash_ignores_ids() {
+    use crate::{
+        binding_model::{hash_bind_group_layout_entries, hash_pipeline_layout},
+        id::TypedId as _,
+    };
+
+    let entries = [wgt::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgt::ShaderStage::COMPUTE,
+        ty: wgt::BindingType::Sampler {
+            filtering: true,
+            comparison: false,
+        },
+        count: None,
+    }];
+    let layout_hash = hash_pipeline_layout(&[hash_bind_group_layout_entries(entries.iter())], &[]);
+    let source = ShaderModuleSource::Wgsl(Cow::Borrowed("[[stage(compute)]] fn main() {}"));
+    // Two processes creating the same module, which got different IDs.
+    let first = ShaderModuleId::zip(1, 0, wgt::Backend::Vulkan);
+    let second = ShaderModuleId::zip(5, 2, wgt::Backend::Vulkan);
+    let module_hash = |_| Ok::<_, ()>(source.stable_hash().unwrap());
+
+    let desc = |module| ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        compute_stage: ProgrammableStageDescriptor {
+            module,
+            entry_point: Cow::Borrowed("main"),
+        },
+    };
+    assert_eq!(
+        desc(first).hash_contents(Some(layout_hash), module_hash),
+        desc(second).hash_contents(Some(layout_hash), module_hash),
+    );
+
+    let other_source = ShaderModuleSource::Wgsl(Cow::Borrowed("[[stage(compute)]] fn other() {}"));
+    assert_ne!(
+        desc(first).hash_contents(Some(layout_hash), module_hash),
+        desc(first).hash_contents(Some(layout_hash), |_| Ok::<_, ()>(
+            other_source.stable_hash().unwrap()
+        )),
+    );
+}
@@ -8,9 +8,10 @@ use crate::{
     id::{DeviceId, SwapChainId, TextureId},
     track::{TextureSelector, DUMMY_SELECTOR},
     validation::MissingBufferUsageError,
-    Label, LifeGuard, RefCount, Stored,
+    FastHashMap, Label, LifeGuard, RefCount, Stored,
 };
 
+use parking_lot::Mutex;
 use thiserror::Error;
 
 use std::{
@@ -18,6 +19,7 @@ use std::{
     num::{NonZeroU32, NonZeroU8},
     ops::Range,
     ptr::NonNull,
+    sync::Arc,
 };
 
 bitflags::bitflags! {
@@ -164,6 +166,8 @@ pub struct Buffer<B: hal::Backend> {
     pub(crate) sync_mapped_writes: Option<hal::memory::Segment>,
     pub(crate) life_guard: LifeGuard,
     pub(crate) map_state: BufferMapState<B>,
+    /// The contents were never written, and have to be zeroed on the GPU before the first use.
+    pub(crate) needs_zero_init: bool,
 }
 
 #[derive(Clone, Debug, Error)]
@@ -176,6 +180,11 @@ pub enum CreateBufferError {
     UnalignedSize,
     #[error("Buffers cannot have empty usage flags")]
     EmptyUsage,
+    #[error("buffer size {requested} is greater than the `max_buffer_size` limit {maximum}")]
+    MaxBufferSize {
+        requested: wgt::BufferAddress,
+        maximum: wgt::BufferAddress,
+    },
     #[error("`MAP` usage can only be combined with the opposite `COPY`, requested {0:?}")]
     UsageMismatch(wgt::BufferUsage),
 }
@@ -209,6 +218,11 @@ pub struct Texture<B: hal::Backend> {
     pub(crate) framebuffer_attachment: hal::image::FramebufferAttachment,
     pub(crate) full_range: TextureSelector,
     pub(crate) life_guard: LifeGuard,
+    /// The contents were never written, and have to be zeroed on the GPU before the first use.
+    pub(crate) needs_zero_init: bool,
+    /// Views of single (mip level, array layer) pairs, for render passes
+    /// attaching the texture without an explicit view.
+    pub(crate) attachment_views: Mutex<FastHashMap<(u32, u32), Arc<B::ImageView>>>,
 }
 
 #[derive(Clone, Debug)]
@@ -244,6 +258,8 @@ pub enum CreateTextureError {
     InvalidUsages(wgt::TextureUsage, wgt::TextureFormat),
     #[error("Feature {0:?} must be enabled to create a texture of type {1:?}")]
     MissingFeature(wgt::Features, wgt::TextureFormat),
+    #[error("Feature {0:?} must be enabled to use 3D textures as render attachments")]
+    RenderAttachment3d(wgt::Features),
 }
 
 impl<B: hal::Backend> Resource for Texture<B> {
@@ -274,6 +290,7 @@ pub struct TextureViewDescriptor<'a> {
     /// `D2`, `D2Array`, `Cube`, and `CubeArray`. For 3D textures it must be `3D`
     pub dimension: Option<wgt::TextureViewDimension>,
     /// Aspect of the texture. Color textures must be [`TextureAspect::All`].
+    /// The stencil aspect of a depth-stencil texture is sampled as unsigned integers.
     pub aspect: wgt::TextureAspect,
     /// Base mip level.
     pub base_mip_level: u32,
@@ -287,6 +304,26 @@ pub struct TextureViewDescriptor<'a> {
     /// If `Some(count)`, `base_array_layer + count` must be less or equal to the underlying array count.
     /// If `None`, considered to include the rest of the array layers, but at least 1 in total.
     pub array_layer_count: Option<NonZeroU32>,
+    /// Sources of the red, green, blue and alpha components seen by shaders.
+    /// Anything but [`wgt::ComponentSwizzle::Identity`] requires
+    /// [`wgt::Features::TEXTURE_COMPONENT_SWIZZLE`].
+    pub swizzle: [wgt::ComponentSwizzle; 4],
+}
+
+impl TextureViewDescriptor<'_> {
+    /// Describes a 2D view of a single array layer and mip level, as required for
+    /// render attachments. Rendering into each layer of a 2D array texture, like the
+    /// cascades of a shadow map, takes one such view per layer.
+    pub fn single_layer(base_array_layer: u32, base_mip_level: u32) -> Self {
+        Self {
+            dimension: Some(wgt::TextureViewDimension::D2),
+            base_mip_level,
+            level_count: NonZeroU32::new(1),
+            base_array_layer,
+            array_layer_count: NonZeroU32::new(1),
+            ..Self::default()
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -299,6 +336,12 @@ pub(crate) enum TextureViewInner<B: hal::Backend> {
         image: <B::Surface as hal::window::PresentationSurface<B>>::SwapchainImage,
         source_id: Stored<SwapChainId>,
     },
+    /// Texture subresource attached to a render pass without an explicit view.
+    /// It only lives while the pass is encoded, the raw view is cached by the texture.
+    Attachment {
+        raw: Arc<B::ImageView>,
+        source_id: Stored<TextureId>,
+    },
 }
 
 #[derive(Debug)]
@@ -311,6 +354,11 @@ pub struct TextureView<B: hal::Backend> {
     pub(crate) extent: wgt::Extent3d,
     pub(crate) samples: hal::image::NumSamples,
     pub(crate) framebuffer_attachment: hal::image::FramebufferAttachment,
+    /// Whether the components are remapped, which rules out attachments and storage.
+    pub(crate) swizzled: bool,
+    /// Number of slices that can be rendered into, when the view is a single mip level
+    /// of a 3D render attachment. Zero for other views.
+    pub(crate) depth_slice_count: u32,
     /// Internal use of this texture view when used as `BindingType::Texture`.
     pub(crate) sampled_internal_use: TextureUse,
     pub(crate) selector: TextureSelector,
@@ -348,6 +396,8 @@ pub enum CreateTextureViewError {
         requested: hal::format::Aspects,
         total: hal::format::Aspects,
     },
+    #[error("Feature {0:?} must be enabled")]
+    MissingFeature(wgt::Features),
 }
 
 #[derive(Clone, Debug, Error)]
@@ -486,6 +536,16 @@ impl<B: hal::Backend> Borrow<()> for QuerySet<B> {
     }
 }
 
+#[derive(Clone, Debug, Error)]
+pub enum SetLabelError {
+    #[error("resource is invalid")]
+    Invalid,
+}
+
+#[error("texture is invalid")]
+#[derive(Clone, Debug, Error)]
+pub struct InvalidTexture;
+
 #[derive(Clone, Debug, Error)]
 pub enum DestroyError {
     #[error("resource is invalid")]
@@ -30,6 +30,14 @@
     chain view.
 
     In `present()` we return the swap chain image back and wait on the semaphore.
+
+    ## Suspension
+
+    When the native window goes away (e.g. an Android activity is paused), the swap chain
+    has to be released before the window is destroyed. `swap_chain_invalidate()` unconfigures
+    it, and from then on acquiring a frame reports `SwapChainStatus::Lost`. Once a new window
+    is available, `surface_recreate()` replaces the surfaces in place, keeping the surface ID,
+    and a new swap chain can be created for it.
 !*/
 
 #[cfg(feature = "trace")]
@@ -48,8 +56,7 @@ use hal::{queue::CommandQueue as _, window::PresentationSurface as _};
 use thiserror::Error;
 use wgt::{SwapChainDescriptor, SwapChainStatus};
 
-const FRAME_TIMEOUT_MS: u64 = 1000;
-pub const DESIRED_NUM_FRAMES: u32 = 3;
+pub const DESIRED_NUM_FRAMES: u32 = wgt::DEFAULT_MAXIMUM_FRAME_LATENCY + 1;
 
 #[derive(Debug)]
 pub struct SwapChain<B: hal::Backend> {
@@ -61,6 +68,7 @@ pub struct SwapChain<B: hal::Backend> {
     pub(crate) acquired_view_id: Option<Stored<TextureViewId>>,
     pub(crate) active_submission_index: SubmissionIndex,
     pub(crate) framebuffer_attachment: hal::image::FramebufferAttachment,
+    pub(crate) invalidated: bool,
 }
 
 impl<B: hal::Backend> crate::hub::Resource for SwapChain<B> {
@@ -102,6 +110,22 @@ pub enum CreateSwapChainError {
         requested: hal::format::Format,
         available: Vec<hal::format::Format>,
     },
+    #[error("swap chain usage {0:?} must include `RENDER_ATTACHMENT`")]
+    MissingRenderAttachmentUsage(wgt::TextureUsage),
+    #[error("swap chain frames can only be used as render attachments, not as {0:?}")]
+    UnusableFrameUsage(wgt::TextureUsage),
+    #[error(
+        "requested usage {requested:?} is not supported by the surface, which allows {available:?}"
+    )]
+    UnsupportedUsage {
+        requested: hal::image::Usage,
+        available: hal::image::Usage,
+    },
+    #[error("requested alpha mode {requested:?} is not supported by the surface, which allows {available:?}")]
+    UnsupportedAlphaMode {
+        requested: hal::window::CompositeAlphaMode,
+        available: hal::window::CompositeAlphaMode,
+    },
 }
 
 pub(crate) fn swap_chain_descriptor_to_hal(
@@ -115,14 +139,9 @@ pub(crate) fn swap_chain_descriptor_to_hal(
         conv::map_texture_format(desc.format, private_features),
         num_frames,
     );
-    //TODO: check for supported
     config.image_usage = conv::map_texture_usage(desc.usage, hal::format::Aspects::COLOR);
-    config.composite_alpha_mode = hal::window::CompositeAlphaMode::OPAQUE;
-    config.present_mode = match desc.present_mode {
-        wgt::PresentMode::Immediate => hal::window::PresentMode::IMMEDIATE,
-        wgt::PresentMode::Mailbox => hal::window::PresentMode::MAILBOX,
-        wgt::PresentMode::Fifo => hal::window::PresentMode::FIFO,
-    };
+    config.composite_alpha_mode = conv::map_composite_alpha_mode(desc.alpha_mode);
+    config.present_mode = conv::map_present_mode(desc.present_mode);
     config
 }
 
@@ -156,8 +175,19 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         #[cfg_attr(not(feature = "trace"), allow(unused_variables))]
         let device = &device_guard[sc.device_id.value];
 
+        if sc.invalidated {
+            return Ok(SwapChainOutput {
+                status: SwapChainStatus::Lost,
+                view_id: None,
+            });
+        }
+
         let suf = B::get_surface_mut(surface);
-        let (image, status) = match unsafe { suf.acquire_image(FRAME_TIMEOUT_MS * 1_000_000) } {
+        let timeout_ns = match sc.desc.acquire_timeout_ms {
+            u32::MAX => !0,
+            ms => ms as u64 * 1_000_000,
+        };
+        let (image, status) = match unsafe { suf.acquire_image(timeout_ns) } {
             Ok((surface_image, None)) => (Some(surface_image), SwapChainStatus::Good),
             Ok((surface_image, Some(_))) => (Some(surface_image), SwapChainStatus::Suboptimal),
             Err(err) => (
@@ -185,7 +215,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     aspects: hal::format::Aspects::COLOR,
                     format: sc.desc.format,
                     format_features: wgt::TextureFormatFeatures {
-                        allowed_usages: wgt::TextureUsage::RENDER_ATTACHMENT,
+                        allowed_usages: sc.desc.usage,
                         flags: wgt::TextureFormatFeatureFlags::empty(),
                     },
                     extent: wgt::Extent3d {
@@ -195,6 +225,8 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     },
                     samples: 1,
                     framebuffer_attachment: sc.framebuffer_attachment.clone(),
+                    swizzled: false,
+                    depth_slice_count: 0,
                     sampled_internal_use: resource::TextureUse::empty(),
                     selector: TextureSelector {
                         layers: 0..1,
@@ -270,7 +302,8 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             return Err(SwapChainError::StillReferenced);
         }
         let image = match view.inner {
-            resource::TextureViewInner::Native { .. } => unreachable!(),
+            resource::TextureViewInner::Native { .. }
+            | resource::TextureViewInner::Attachment { .. } => unreachable!(),
             resource::TextureViewInner::SwapChain { image, .. } => image,
         };
 
@@ -299,4 +332,40 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             },
         }
     }
+
+    /// Release the swap chain images before the native window is destroyed.
+    ///
+    /// Acquiring from an invalidated swap chain returns `SwapChainStatus::Lost`
+    /// until a new swap chain is created for the surface.
+    pub fn swap_chain_invalidate<B: GfxBackend>(
+        &self,
+        swap_chain_id: SwapChainId,
+    ) -> Result<(), SwapChainError> {
+        span!(_guard, INFO, "SwapChain::invalidate");
+
+        let hub = B::hub(self);
+        let mut token = Token::root();
+
+        let (mut surface_guard, mut token) = self.surfaces.write(&mut token);
+        let surface = surface_guard
+            .get_mut(swap_chain_id.to_surface_id())
+            .map_err(|_| SwapChainError::InvalidSurface)?;
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        let (mut swap_chain_guard, _) = hub.swap_chains.write(&mut token);
+        let sc = swap_chain_guard
+            .get_mut(swap_chain_id)
+            .map_err(|_| SwapChainError::Invalid)?;
+
+        if sc.acquired_view_id.is_some() {
+            return Err(SwapChainError::AlreadyAcquired);
+        }
+        if !sc.invalidated {
+            let device = &device_guard[sc.device_id.value];
+            unsafe {
+                B::get_surface_mut(surface).unconfigure_swapchain(&device.raw);
+            }
+            sc.invalidated = true;
+        }
+        Ok(())
+    }
 }
@@ -791,7 +791,7 @@ impl Interface {
         derived_layouts: &mut [BindEntryMap],
         entry_point_name: &str,
         stage_bit: wgt::ShaderStage,
-        inputs: StageIo,
+        inputs: Option<StageIo>,
     ) -> Result<StageIo, StageError> {
         // Since a shader module can have multiple entry points with the same name,
         // we need to look for one with the right execution model.
@@ -854,25 +854,28 @@ impl Interface {
             }
         }
 
-        for input in entry_point.inputs.iter() {
-            match *input {
-                Varying::Local { location, ty } => {
-                    let result =
-                        inputs
-                            .get(&location)
-                            .ok_or(InputError::Missing)
-                            .and_then(|provided| {
-                                if ty.is_subtype_of(provided) {
-                                    Ok(())
-                                } else {
-                                    Err(InputError::WrongType)
-                                }
-                            });
-                    if let Err(error) = result {
-                        return Err(StageError::Input { location, error });
+        // The inputs are unknown when the previous stage can't be reflected.
+        if let Some(ref inputs) = inputs {
+            for input in entry_point.inputs.iter() {
+                match *input {
+                    Varying::Local { location, ty } => {
+                        let result =
+                            inputs
+                                .get(&location)
+                                .ok_or(InputError::Missing)
+                                .and_then(|provided| {
+                                    if ty.is_subtype_of(provided) {
+                                        Ok(())
+                                    } else {
+                                        Err(InputError::WrongType)
+                                    }
+                                });
+                        if let Err(error) = result {
+                            return Err(StageError::Input { location, error });
+                        }
                     }
+                    Varying::BuiltIn(_) => {}
                 }
-                Varying::BuiltIn(_) => {}
             }
         }
 
@@ -388,6 +388,62 @@ bitflags::bitflags! {
         ///
         /// This is a native-only feature.
         const TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES = 0x0000_0000_1000_0000;
+        /// Allows [`TextureFormat::Depth32Float`] textures, and the stencil aspect of
+        /// [`TextureFormat::Depth24PlusStencil8`] textures, to be the destination of
+        /// [`Queue::write_texture`] and [`CommandEncoder::copy_buffer_to_texture`].
+        ///
+        /// This lets depth data authored on the CPU be uploaded without a fullscreen pass.
+        ///
+        /// The feature covers both formats, so it is only exposed on adapters that can copy
+        /// into both of them. An adapter that supports only one of them doesn't expose it.
+        ///
+        /// Supported platforms:
+        /// - DX12
+        /// - Vulkan
+        /// - Metal
+        ///
+        /// This is a native-only feature.
+        const DEPTH_TEXTURE_COPY_DST = 0x0000_0000_2000_0000;
+        /// Relaxes the alignment of `bytes_per_row` in buffer <-> texture copies from
+        /// [`COPY_BYTES_PER_ROW_ALIGNMENT`] to the one reported by the adapter in
+        /// [`CopyAlignments::bytes_per_row`], rounded up to the texel block size.
+        ///
+        /// This avoids padding every row of a readback to 256 bytes.
+        ///
+        /// Supported platforms:
+        /// - Vulkan
+        /// - Metal
+        ///
+        /// This is a native-only feature.
+        const ADAPTER_SPECIFIC_COPY_ALIGNMENT = 0x0000_0000_4000_0000;
+        /// Allows texture views with a [`ComponentSwizzle`] other than the identity,
+        /// to present single-channel textures to shaders as e.g. `RRR1`.
+        ///
+        /// Swizzled views can't be used as render attachments or storage textures.
+        ///
+        /// Supported platforms:
+        /// - DX12
+        /// - Vulkan
+        ///
+        /// This is a native-only feature.
+        const TEXTURE_COMPONENT_SWIZZLE = 0x0000_0000_8000_0000;
+        /// Allows 3D textures to be render attachments, rendering into a single depth slice
+        /// selected with `depth_slice` of the color attachment.
+        ///
+        /// Supported platforms:
+        /// - Vulkan
+        ///
+        /// This is a native-only feature.
+        const RENDER_ATTACHMENT_3D_SLICES = 0x0000_0001_0000_0000;
+        /// Allows render pipelines that generate their primitives with a mesh shader,
+        /// optionally launched by a task shader, instead of a vertex shader fed from
+        /// vertex buffers. Such pipelines are drawn with `draw_mesh_tasks` commands.
+        ///
+        /// Supported platforms:
+        /// - Vulkan (with VK_NV_mesh_shader)
+        ///
+        /// This is a native-only feature.
+        const MESH_SHADER = 0x0000_0002_0000_0000;
         /// Features which are part of the upstream WebGPU standard.
         const ALL_WEBGPU = 0x0000_0000_0000_FFFF;
         /// Features that are only available when targeting native (not web).
@@ -440,6 +496,9 @@ pub struct Limits {
     /// - DX11 & OpenGL don't natively support push constants, and are emulated with uniforms,
     ///   so this number is less useful but likely 256.
     pub max_push_constant_size: u32,
+    /// Maximum size in bytes of a buffer. Defaults to 268435456 (256 MiB). Higher is "better".
+    #[cfg_attr(feature = "replay", serde(default = "Limits::default_max_buffer_size"))]
+    pub max_buffer_size: BufferAddress,
 }
 
 impl Default for Limits {
@@ -455,10 +514,53 @@ impl Default for Limits {
             max_uniform_buffers_per_shader_stage: 12,
             max_uniform_buffer_binding_size: 16384,
             max_push_constant_size: 0,
+            max_buffer_size: 1 << 28,
         }
     }
 }
 
+impl Limits {
+    /// Returns `true` if none of the limits exceeds the corresponding one in `allowed`.
+    ///
+    /// Each limit is compared on its own. The derived ordering is lexicographic,
+    /// so it can't tell whether a set of limits fits into another.
+    pub fn check_limits(&self, allowed: &Self) -> bool {
+        let Self {
+            max_bind_groups,
+            max_dynamic_uniform_buffers_per_pipeline_layout,
+            max_dynamic_storage_buffers_per_pipeline_layout,
+            max_sampled_textures_per_shader_stage,
+            max_samplers_per_shader_stage,
+            max_storage_buffers_per_shader_stage,
+            max_storage_textures_per_shader_stage,
+            max_uniform_buffers_per_shader_stage,
+            max_uniform_buffer_binding_size,
+            max_push_constant_size,
+            max_buffer_size,
+        } = *self;
+        max_bind_groups <= allowed.max_bind_groups
+            && max_dynamic_uniform_buffers_per_pipeline_layout
+                <= allowed.max_dynamic_uniform_buffers_per_pipeline_layout
+            && max_dynamic_storage_buffers_per_pipeline_layout
+                <= allowed.max_dynamic_storage_buffers_per_pipeline_layout
+            && max_sampled_textures_per_shader_stage
+                <= allowed.max_sampled_textures_per_shader_stage
+            && max_samplers_per_shader_stage <= allowed.max_samplers_per_shader_stage
+            && max_storage_buffers_per_shader_stage <= allowed.max_storage_buffers_per_shader_stage
+            && max_storage_textures_per_shader_stage
+                <= allowed.max_storage_textures_per_shader_stage
+            && max_uniform_buffers_per_shader_stage <= allowed.max_uniform_buffers_per_shader_stage
+            && max_uniform_buffer_binding_size <= allowed.max_uniform_buffer_binding_size
+            && max_push_constant_size <= allowed.max_push_constant_size
+            && max_buffer_size <= allowed.max_buffer_size
+    }
+
+    #[cfg(feature = "replay")]
+    fn default_max_buffer_size() -> BufferAddress {
+        Self::default().max_buffer_size
+    }
+}
+
 /// Supported physical device types.
 #[repr(u8)]
 #[derive(Clone, Debug, PartialEq)]
@@ -492,6 +594,64 @@ pub struct AdapterInfo {
     pub device_type: DeviceType,
     /// Backend used for device
     pub backend: Backend,
+    /// Driver name, if the backend reports it.
+    ///
+    /// Always `None` for now, since gfx-hal doesn't report driver details.
+    pub driver: Option<String>,
+    /// Driver version and any additional driver information, if the backend reports it.
+    ///
+    /// Always `None` for now, like `driver`.
+    pub driver_info: Option<String>,
+}
+
+bitflags::bitflags! {
+    /// Known driver and platform bugs that wgpu works around on a given adapter.
+    ///
+    /// These are detected when the adapter is enumerated and can be queried with
+    /// [`Adapter::driver_workarounds`], so that applications can apply consistent
+    /// mitigations of their own.
+    #[repr(transparent)]
+    #[derive(Default)]
+    #[cfg_attr(feature = "trace", derive(Serialize))]
+    #[cfg_attr(feature = "replay", derive(Deserialize))]
+    pub struct DriverWorkarounds: u32 {
+        /// Sampling with [`AddressMode::ClampToBorder`] is broken on this platform,
+        /// so [`Features::ADDRESS_MODE_CLAMP_TO_BORDER`] is never exposed.
+        const NO_CLAMP_TO_BORDER = 0x0000_0001;
+        /// The driver reports some limits below the WebGPU defaults. wgpu raises them to the
+        /// defaults, so using more than the driver advertises may misbehave.
+        const LIMITS_BELOW_DEFAULTS = 0x0000_0002;
+    }
+}
+
+/// Coarse power state hints of an adapter, queried at runtime.
+///
+/// This complements [`PowerPreference`]: applications can poll it periodically and reduce
+/// resolution or framerate before the OS does it for them. Each hint is `None` when the
+/// platform doesn't provide the information.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct PowerHints {
+    /// The system is running on battery.
+    pub on_battery: Option<bool>,
+    /// The adapter is throttled for power or thermal reasons.
+    pub throttled: Option<bool>,
+}
+
+/// Alignment requirements of buffer <-> texture copies reported by an adapter.
+///
+/// WebGPU requires [`COPY_BYTES_PER_ROW_ALIGNMENT`] for all of them, which is often
+/// more than the hardware needs. The relaxed row alignment is only honored with
+/// [`Features::ADAPTER_SPECIFIC_COPY_ALIGNMENT`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct CopyAlignments {
+    /// Alignment of the number of bytes between the rows of a copy.
+    pub bytes_per_row: u32,
+    /// Optimal alignment of the buffer offset of a copy.
+    pub buffer_offset: BufferAddress,
 }
 
 /// Describes a [`Device`].
@@ -537,6 +697,14 @@ bitflags::bitflags! {
         const FRAGMENT = 2;
         /// Binding is visible from the compute shader of a compute pipeline.
         const COMPUTE = 4;
+        /// Binding is visible from the task shader of a mesh pipeline.
+        ///
+        /// Requires [`Features::MESH_SHADER`].
+        const TASK = 8;
+        /// Binding is visible from the mesh shader of a mesh pipeline.
+        ///
+        /// Requires [`Features::MESH_SHADER`].
+        const MESH = 16;
     }
 }
 
@@ -559,7 +727,7 @@ bitflags::bitflags! {
 
 /// Dimensions of a particular texture view.
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "trace", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub enum TextureViewDimension {
@@ -598,7 +766,7 @@ impl TextureViewDimension {
 ///
 /// Alpha blending is very complicated: see the OpenGL or Vulkan spec for more information.
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "trace", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub enum BlendFactor {
@@ -621,7 +789,7 @@ pub enum BlendFactor {
 ///
 /// Alpha blending is very complicated: see the OpenGL or Vulkan spec for more information.
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "trace", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub enum BlendOperation {
@@ -705,7 +873,7 @@ impl From<TextureFormat> for ColorStateDescriptor {
 
 /// Primitive type the input mesh is composed of.
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "trace", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub enum PrimitiveTopology {
@@ -731,7 +899,7 @@ pub enum PrimitiveTopology {
 
 /// Winding order which classifies the "front" face.
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "trace", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub enum FrontFace {
@@ -753,7 +921,7 @@ impl Default for FrontFace {
 
 /// Type of faces to be culled.
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "trace", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub enum CullMode {
@@ -773,7 +941,7 @@ impl Default for CullMode {
 
 /// Type of drawing mode for polygons
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "trace", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub enum PolygonMode {
@@ -807,11 +975,37 @@ pub struct RasterizationStateDescriptor {
     ///
     /// Requires `Features::DEPTH_CLAMPING` enabled.
     pub clamp_depth: bool,
+    /// If enabled, primitives are discarded right after the vertex stage and never reach the
+    /// rasterizer. No fragments are generated, and neither color nor depth/stencil attachments
+    /// are touched.
+    ///
+    /// This is useful for pipelines that only run for their vertex-stage side effects, such as
+    /// storage writes or pipeline statistics queries. Requires the fragment stage to be `None`.
+    #[cfg_attr(any(feature = "replay", feature = "trace"), serde(default))]
+    pub discard: bool,
     pub depth_bias: i32,
     pub depth_bias_slope_scale: f32,
     pub depth_bias_clamp: f32,
 }
 
+impl std::hash::Hash for RasterizationStateDescriptor {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        // Positive and negative zeros compare equal, so they have to hash the same.
+        fn hash_float<H: std::hash::Hasher>(value: f32, state: &mut H) {
+            let bits = if value == 0.0 { 0 } else { value.to_bits() };
+            state.write_u32(bits);
+        }
+        self.front_face.hash(state);
+        self.cull_mode.hash(state);
+        self.polygon_mode.hash(state);
+        self.clamp_depth.hash(state);
+        self.discard.hash(state);
+        self.depth_bias.hash(state);
+        hash_float(self.depth_bias_slope_scale, state);
+        hash_float(self.depth_bias_clamp, state);
+    }
+}
+
 bitflags::bitflags! {
     /// Feature flags for a texture format.
     #[repr(transparent)]
@@ -822,6 +1016,18 @@ bitflags::bitflags! {
         const STORAGE_READ_WRITE = 1;
         /// When used as a STORAGE texture, then a texture with this format can be written to with atomics. TODO: No access flag exposed as of writing
         const STORAGE_ATOMICS = 2;
+        /// When used as a SAMPLED texture, then a texture with this format can be sampled with linear filtering.
+        const FILTERABLE = 4;
+        /// When used as a RENDER_ATTACHMENT, then a texture with this format can be blended into.
+        const BLENDABLE = 8;
+        /// A texture with this format can be created with a sample count of 2.
+        const MULTISAMPLE_X2 = 16;
+        /// A texture with this format can be created with a sample count of 4.
+        const MULTISAMPLE_X4 = 32;
+        /// A texture with this format can be created with a sample count of 8.
+        const MULTISAMPLE_X8 = 64;
+        /// A texture with this format can be created with a sample count of 16.
+        const MULTISAMPLE_X16 = 128;
     }
 }
 
@@ -829,6 +1035,8 @@ bitflags::bitflags! {
 ///
 /// Features are defined by WebGPU specification unless `Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES` is enabled.
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
 pub struct TextureFormatFeatures {
     pub allowed_usages: TextureUsage,
     pub flags: TextureFormatFeatureFlags,
@@ -856,7 +1064,7 @@ pub struct TextureFormatInfo {
 /// If there is a conversion in the format (such as srgb -> linear), The conversion listed is for
 /// loading from texture in a shader. When writing to the texture, the opposite conversion takes place.
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum TextureFormat {
     // Normal 8 bit formats
@@ -1239,6 +1447,100 @@ pub enum TextureFormat {
 }
 
 impl TextureFormat {
+    /// All the texture formats, in declaration order.
+    pub const ALL: &'static [TextureFormat] = &[
+        TextureFormat::R8Unorm,
+        TextureFormat::R8Snorm,
+        TextureFormat::R8Uint,
+        TextureFormat::R8Sint,
+        TextureFormat::R16Uint,
+        TextureFormat::R16Sint,
+        TextureFormat::R16Float,
+        TextureFormat::Rg8Unorm,
+        TextureFormat::Rg8Snorm,
+        TextureFormat::Rg8Uint,
+        TextureFormat::Rg8Sint,
+        TextureFormat::R32Uint,
+        TextureFormat::R32Sint,
+        TextureFormat::R32Float,
+        TextureFormat::Rg16Uint,
+        TextureFormat::Rg16Sint,
+        TextureFormat::Rg16Float,
+        TextureFormat::Rgba8Unorm,
+        TextureFormat::Rgba8UnormSrgb,
+        TextureFormat::Rgba8Snorm,
+        TextureFormat::Rgba8Uint,
+        TextureFormat::Rgba8Sint,
+        TextureFormat::Bgra8Unorm,
+        TextureFormat::Bgra8UnormSrgb,
+        TextureFormat::Rgb10a2Unorm,
+        TextureFormat::Rg11b10Float,
+        TextureFormat::Rg32Uint,
+        TextureFormat::Rg32Sint,
+        TextureFormat::Rg32Float,
+        TextureFormat::Rgba16Uint,
+        TextureFormat::Rgba16Sint,
+        TextureFormat::Rgba16Float,
+        TextureFormat::Rgba32Uint,
+        TextureFormat::Rgba32Sint,
+        TextureFormat::Rgba32Float,
+        TextureFormat::Depth32Float,
+        TextureFormat::Depth24Plus,
+        TextureFormat::Depth24PlusStencil8,
+        TextureFormat::Bc1RgbaUnorm,
+        TextureFormat::Bc1RgbaUnormSrgb,
+        TextureFormat::Bc2RgbaUnorm,
+        TextureFormat::Bc2RgbaUnormSrgb,
+        TextureFormat::Bc3RgbaUnorm,
+        TextureFormat::Bc3RgbaUnormSrgb,
+        TextureFormat::Bc4RUnorm,
+        TextureFormat::Bc4RSnorm,
+        TextureFormat::Bc5RgUnorm,
+        TextureFormat::Bc5RgSnorm,
+        TextureFormat::Bc6hRgbUfloat,
+        TextureFormat::Bc6hRgbSfloat,
+        TextureFormat::Bc7RgbaUnorm,
+        TextureFormat::Bc7RgbaUnormSrgb,
+        TextureFormat::Etc2RgbUnorm,
+        TextureFormat::Etc2RgbUnormSrgb,
+        TextureFormat::Etc2RgbA1Unorm,
+        TextureFormat::Etc2RgbA1UnormSrgb,
+        TextureFormat::Etc2RgbA8Unorm,
+        TextureFormat::Etc2RgbA8UnormSrgb,
+        TextureFormat::EacRUnorm,
+        TextureFormat::EacRSnorm,
+        TextureFormat::EtcRgUnorm,
+        TextureFormat::EtcRgSnorm,
+        TextureFormat::Astc4x4RgbaUnorm,
+        TextureFormat::Astc4x4RgbaUnormSrgb,
+        TextureFormat::Astc5x4RgbaUnorm,
+        TextureFormat::Astc5x4RgbaUnormSrgb,
+        TextureFormat::Astc5x5RgbaUnorm,
+        TextureFormat::Astc5x5RgbaUnormSrgb,
+        TextureFormat::Astc6x5RgbaUnorm,
+        TextureFormat::Astc6x5RgbaUnormSrgb,
+        TextureFormat::Astc6x6RgbaUnorm,
+        TextureFormat::Astc6x6RgbaUnormSrgb,
+        TextureFormat::Astc8x5RgbaUnorm,
+        TextureFormat::Astc8x5RgbaUnormSrgb,
+        TextureFormat::Astc8x6RgbaUnorm,
+        TextureFormat::Astc8x6RgbaUnormSrgb,
+        TextureFormat::Astc10x5RgbaUnorm,
+        TextureFormat::Astc10x5RgbaUnormSrgb,
+        TextureFormat::Astc10x6RgbaUnorm,
+        TextureFormat::Astc10x6RgbaUnormSrgb,
+        TextureFormat::Astc8x8RgbaUnorm,
+        TextureFormat::Astc8x8RgbaUnormSrgb,
+        TextureFormat::Astc10x8RgbaUnorm,
+        TextureFormat::Astc10x8RgbaUnormSrgb,
+        TextureFormat::Astc10x10RgbaUnorm,
+        TextureFormat::Astc10x10RgbaUnormSrgb,
+        TextureFormat::Astc12x10RgbaUnorm,
+        TextureFormat::Astc12x10RgbaUnormSrgb,
+        TextureFormat::Astc12x12RgbaUnorm,
+        TextureFormat::Astc12x12RgbaUnormSrgb,
+    ];
+
     /// Get useful information about the texture format.
     pub fn describe(&self) -> TextureFormatInfo {
         // Features
@@ -1378,6 +1680,20 @@ impl TextureFormat {
                 Self::Astc12x12RgbaUnormSrgb => (astc_ldr, float, srgb, (12, 12), 16, basic),
             };
 
+        let mut flags = TextureFormatFeatureFlags::empty();
+        if sample_type == float {
+            flags |= TextureFormatFeatureFlags::FILTERABLE;
+        }
+        if allowed_usages.contains(TextureUsage::RENDER_ATTACHMENT) {
+            if sample_type == float {
+                flags |= TextureFormatFeatureFlags::BLENDABLE;
+            }
+            // 32-bit float formats and 128-bit formats aren't guaranteed to support multisampling.
+            if sample_type != nearest && block_size <= 8 {
+                flags |= TextureFormatFeatureFlags::MULTISAMPLE_X4;
+            }
+        }
+
         TextureFormatInfo {
             required_features,
             sample_type,
@@ -1386,7 +1702,7 @@ impl TextureFormat {
             srgb,
             guaranteed_format_features: TextureFormatFeatures {
                 allowed_usages,
-                flags: TextureFormatFeatureFlags::empty(),
+                flags,
             },
         }
     }
@@ -1475,7 +1791,7 @@ impl DepthStencilStateDescriptor {
 
 /// Format of indices used with pipeline.
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum IndexFormat {
     /// Indices are 16 bit unsigned integers.
@@ -1492,7 +1808,7 @@ impl Default for IndexFormat {
 
 /// Operation to perform on the stencil value.
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "trace", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub enum StencilOperation {
@@ -1555,7 +1871,7 @@ impl Default for StencilStateFaceDescriptor {
 
 /// Comparison function used for depth and stencil operations.
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "trace", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub enum CompareFunction {
@@ -1588,7 +1904,7 @@ impl CompareFunction {
 
 /// Rate that determines when vertex data is advanced.
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "trace", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub enum InputStepMode {
@@ -1616,7 +1932,7 @@ pub struct VertexAttributeDescriptor {
 
 /// Vertex Format for a Vertex Attribute (input).
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "trace", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub enum VertexFormat {
@@ -1825,6 +2141,41 @@ pub enum PresentMode {
     /// the current image. The framerate will be capped at the display refresh rate,
     /// corresponding to the `VSync`. Tearing cannot be observed. Optimal for mobile.
     Fifo = 2,
+    /// Like `Fifo`, but a late frame is presented immediately instead of waiting for the
+    /// next vertical blanking period, so tearing may be observed when the framerate drops
+    /// below the refresh rate. Will fallback to `Fifo` if unavailable.
+    FifoRelaxed = 3,
+    /// Picks the best available mode synchronized with the vertical blanking period:
+    /// `FifoRelaxed`, falling back to `Fifo`. There is no tearing while the framerate
+    /// keeps up with the refresh rate, but late frames may tear with `FifoRelaxed`.
+    AutoVsync = 4,
+    /// Picks the best available mode without waiting for the vertical blanking period:
+    /// `Immediate`, falling back to `Mailbox`, then to `Fifo`.
+    AutoNoVsync = 5,
+}
+
+/// How the alpha channel of the swap chain textures is handled when compositing
+/// them with the rest of the desktop.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub enum CompositeAlphaMode {
+    /// The alpha channel is ignored, and the window is fully opaque.
+    /// This is the only mode guaranteed to be supported.
+    Opaque = 0,
+    /// The color channels are expected to be already multiplied by the alpha channel.
+    PreMultiplied = 1,
+    /// The color channels are multiplied by the alpha channel by the compositor.
+    PostMultiplied = 2,
+    /// The alpha handling is configured by the platform, outside of wgpu.
+    Inherit = 3,
+}
+
+impl Default for CompositeAlphaMode {
+    fn default() -> Self {
+        Self::Opaque
+    }
 }
 
 bitflags::bitflags! {
@@ -1857,7 +2208,8 @@ bitflags::bitflags! {
 #[cfg_attr(feature = "trace", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub struct SwapChainDescriptor {
-    /// The usage of the swap chain. The only supported usage is `RENDER_ATTACHMENT`.
+    /// The usage of the swap chain. The only supported usage is `RENDER_ATTACHMENT`,
+    /// since frames can't be copied from or bound to bind groups yet.
     pub usage: TextureUsage,
     /// The texture format of the swap chain. The only formats that are guaranteed are
     /// `Bgra8Unorm` and `Bgra8UnormSrgb`
@@ -1869,6 +2221,66 @@ pub struct SwapChainDescriptor {
     /// Presentation mode of the swap chain. FIFO is the only guaranteed to be supported, though
     /// other formats will automatically fall back to FIFO.
     pub present_mode: PresentMode,
+    /// Compositing of the swap chain textures with the desktop. Modes other than `Opaque`
+    /// allow transparent windows, and are only accepted if the surface supports them.
+    #[cfg_attr(any(feature = "replay", feature = "trace"), serde(default))]
+    pub alpha_mode: CompositeAlphaMode,
+    /// Maximum number of frames queued for presentation before acquiring the next frame
+    /// blocks. Lower values reduce the input latency, higher values smooth out the frame
+    /// pacing. Clamped to what the surface supports. Defaults to
+    /// [`DEFAULT_MAXIMUM_FRAME_LATENCY`].
+    #[cfg_attr(
+        feature = "replay",
+        serde(default = "SwapChainDescriptor::default_maximum_frame_latency")
+    )]
+    pub desired_maximum_frame_latency: u32,
+    /// Time in milliseconds to wait for the next frame to become available before
+    /// reporting [`SwapChainStatus::Timeout`]. Zero doesn't wait at all, and `u32::MAX`
+    /// waits without a timeout. Defaults to [`DEFAULT_ACQUIRE_TIMEOUT_MS`].
+    #[cfg_attr(
+        feature = "replay",
+        serde(default = "SwapChainDescriptor::default_acquire_timeout_ms")
+    )]
+    pub acquire_timeout_ms: u32,
+}
+
+/// Default value of [`SwapChainDescriptor::desired_maximum_frame_latency`].
+pub const DEFAULT_MAXIMUM_FRAME_LATENCY: u32 = 2;
+
+/// Default value of [`SwapChainDescriptor::acquire_timeout_ms`].
+pub const DEFAULT_ACQUIRE_TIMEOUT_MS: u32 = 1000;
+
+impl SwapChainDescriptor {
+    #[cfg(feature = "replay")]
+    fn default_maximum_frame_latency() -> u32 {
+        DEFAULT_MAXIMUM_FRAME_LATENCY
+    }
+
+    #[cfg(feature = "replay")]
+    fn default_acquire_timeout_ms() -> u32 {
+        DEFAULT_ACQUIRE_TIMEOUT_MS
+    }
+}
+
+/// Passed to `Device::poll` to control how and if it should block.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Maintain<T> {
+    /// Checks the status of the submitted work without blocking.
+    Poll,
+    /// Blocks until all the submitted work is done.
+    Wait,
+    /// Blocks until the given submission, and all the ones before it, are done.
+    WaitForSubmission(T),
+}
+
+impl<T> Maintain<T> {
+    /// Returns `true` if polling may block.
+    pub fn is_wait(&self) -> bool {
+        match *self {
+            Self::Poll => false,
+            Self::Wait | Self::WaitForSubmission(_) => true,
+        }
+    }
 }
 
 /// Status of the recieved swapchain image.
@@ -2130,6 +2542,9 @@ pub enum TextureAspect {
     /// Depth, Stencil, and Color.
     All,
     /// Stencil.
+    ///
+    /// Views of the stencil aspect of depth-stencil textures can be bound as
+    /// [`TextureSampleType::Uint`] sampled textures, to read the stencil values in shaders.
     StencilOnly,
     /// Depth.
     DepthOnly,
@@ -2141,6 +2556,37 @@ impl Default for TextureAspect {
     }
 }
 
+/// Source of a single component of a texture view, when reading it in shaders.
+///
+/// Anything other than [`ComponentSwizzle::Identity`] requires
+/// [`Features::TEXTURE_COMPONENT_SWIZZLE`].
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub enum ComponentSwizzle {
+    /// The component at the same position in the texture.
+    Identity,
+    /// Always 0.
+    Zero,
+    /// Always 1.
+    One,
+    /// The red component of the texture.
+    R,
+    /// The green component of the texture.
+    G,
+    /// The blue component of the texture.
+    B,
+    /// The alpha component of the texture.
+    A,
+}
+
+impl Default for ComponentSwizzle {
+    fn default() -> Self {
+        Self::Identity
+    }
+}
+
 /// How edges should be handled in texture addressing.
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
@@ -2217,6 +2663,8 @@ pub struct PushConstantRange {
 #[cfg_attr(feature = "trace", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub struct CommandBufferDescriptor<L> {
+    /// Debug label of the command buffer. If set, it replaces the label of the
+    /// command encoder, and is given to the backend command buffers.
     pub label: L,
 }
 
@@ -2279,7 +2727,7 @@ pub struct TextureDataLayout {
 /// Specific type of a buffer binding.
 ///
 /// WebGPU spec: https://gpuweb.github.io/gpuweb/#enumdef-gpubufferbindingtype
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "trace", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub enum BufferBindingType {
@@ -2322,10 +2770,22 @@ impl Default for BufferBindingType {
     }
 }
 
+impl std::hash::Hash for BufferBindingType {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        match *self {
+            Self::Uniform => state.write_u32(0),
+            Self::Storage { read_only } => {
+                state.write_u32(1);
+                read_only.hash(state);
+            }
+        }
+    }
+}
+
 /// Specific type of a sample in a texture binding.
 ///
 /// WebGPU spec: https://gpuweb.github.io/gpuweb/#enumdef-gputexturesampletype
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "trace", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub enum TextureSampleType {
@@ -2372,10 +2832,24 @@ impl Default for TextureSampleType {
     }
 }
 
+impl std::hash::Hash for TextureSampleType {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        match *self {
+            Self::Float { filterable } => {
+                state.write_u32(0);
+                filterable.hash(state);
+            }
+            Self::Depth => state.write_u32(1),
+            Self::Sint => state.write_u32(2),
+            Self::Uint => state.write_u32(3),
+        }
+    }
+}
+
 /// Specific type of a sample in a texture binding.
 ///
 /// WebGPU spec: https://gpuweb.github.io/gpuweb/#enumdef-gpustoragetextureaccess
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "trace", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub enum StorageTextureAccess {
@@ -2410,7 +2884,7 @@ pub enum StorageTextureAccess {
 /// - https://gpuweb.github.io/gpuweb/#dictdef-gpusamplerbindinglayout
 /// - https://gpuweb.github.io/gpuweb/#dictdef-gputexturebindinglayout
 /// - https://gpuweb.github.io/gpuweb/#dictdef-gpustoragetexturebindinglayout
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "trace", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub enum BindingType {
@@ -2491,8 +2965,53 @@ impl BindingType {
     }
 }
 
+impl std::hash::Hash for BindingType {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        match *self {
+            Self::Buffer {
+                ty,
+                has_dynamic_offset,
+                min_binding_size,
+            } => {
+                state.write_u32(0);
+                ty.hash(state);
+                has_dynamic_offset.hash(state);
+                hash_option(&min_binding_size, state);
+            }
+            Self::Sampler {
+                filtering,
+                comparison,
+            } => {
+                state.write_u32(1);
+                filtering.hash(state);
+                comparison.hash(state);
+            }
+            Self::Texture {
+                sample_type,
+                view_dimension,
+                multisampled,
+            } => {
+                state.write_u32(2);
+                sample_type.hash(state);
+                view_dimension.hash(state);
+                multisampled.hash(state);
+            }
+            Self::StorageTexture {
+                access,
+                format,
+                view_dimension,
+            } => {
+                state.write_u32(3);
+                access.hash(state);
+                format.hash(state);
+                view_dimension.hash(state);
+            }
+        }
+    }
+}
+
 /// Describes a single binding inside a bind group.
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "trace", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub struct BindGroupLayoutEntry {
@@ -2512,6 +3031,15 @@ pub struct BindGroupLayoutEntry {
     pub count: Option<NonZeroU32>,
 }
 
+impl std::hash::Hash for BindGroupLayoutEntry {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.binding.hash(state);
+        self.visibility.hash(state);
+        self.ty.hash(state);
+        hash_option(&self.count, state);
+    }
+}
+
 /// View of a buffer which can be used to copy to/from a texture.
 #[repr(C)]
 #[derive(Clone, Debug)]
@@ -2537,6 +3065,12 @@ pub struct TextureCopyView<T> {
     /// The base texel of the texture in the selected `mip_level`.
     #[cfg_attr(any(feature = "replay", feature = "trace"), serde(default))]
     pub origin: Origin3d,
+    /// The aspect of the texture to copy to/from.
+    ///
+    /// Copies between a buffer and a combined depth-stencil texture
+    /// have to select a single aspect.
+    #[cfg_attr(any(feature = "replay", feature = "trace"), serde(default))]
+    pub aspect: TextureAspect,
 }
 
 /// Color variation to use when sampler addressing mode is [`AddressMode::ClampToBorder`]
@@ -2621,3 +3155,419 @@ bitflags::bitflags! {
         const COMPUTE_SHADER_INVOCATIONS = 0x10;
     }
 }
+
+/// Rules for laying out host-shareable structures in uniform and storage buffers.
+///
+/// Only 32-bit scalars (`f32`, `i32`, `u32`) are considered.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum BufferLayoutRules {
+    /// GLSL `std140`, required for uniform buffers. The alignment of arrays and structures
+    /// is rounded up to 16 bytes, as is the stride of array elements.
+    Std140,
+    /// GLSL `std430`, available for storage buffers. Same as `Std140`, without the rounding.
+    Std430,
+}
+
+impl BufferLayoutRules {
+    const fn round_up_aggregate(self, value: u64) -> u64 {
+        match self {
+            Self::Std140 => align_up_u64(value, 16),
+            Self::Std430 => value,
+        }
+    }
+}
+
+const fn align_up_u64(value: u64, alignment: u64) -> u64 {
+    (value + alignment - 1) / alignment * alignment
+}
+
+/// Size and alignment of a member of a host-shareable structure.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub struct MemberLayout {
+    /// Size of the member in bytes, without any trailing padding.
+    pub size: u64,
+    /// Required alignment of the member in bytes.
+    pub align: u64,
+}
+
+impl MemberLayout {
+    /// Layout of a 32-bit scalar.
+    pub const fn scalar() -> Self {
+        Self { size: 4, align: 4 }
+    }
+
+    /// Layout of a vector of 32-bit scalars, with `components` between 2 and 4.
+    ///
+    /// Note that a 3-component vector is aligned like a 4-component one.
+    pub const fn vector(components: u64) -> Self {
+        Self {
+            size: 4 * components,
+            align: if components == 2 { 8 } else { 16 },
+        }
+    }
+
+    /// Layout of an array of `count` elements.
+    pub const fn array(rules: BufferLayoutRules, element: Self, count: u64) -> Self {
+        let stride = rules.round_up_aggregate(align_up_u64(element.size, element.align));
+        Self {
+            size: stride * count,
+            align: rules.round_up_aggregate(element.align),
+        }
+    }
+
+    /// Layout of a column-major matrix, which is laid out as an array of column vectors.
+    pub const fn matrix(rules: BufferLayoutRules, columns: u64, rows: u64) -> Self {
+        Self::array(rules, Self::vector(rows), columns)
+    }
+
+    /// Layout of a nested structure.
+    pub const fn structure(layout: BufferLayout) -> Self {
+        Self {
+            size: layout.size(),
+            align: layout.align,
+        }
+    }
+}
+
+/// Computes the layout of a host-shareable structure, one member at a time.
+///
+/// All the methods are `const`, so layouts can be computed at compile time and checked
+/// against the matching Rust structures with [`assert_buffer_layout_size!`]:
+///
+/// ```rust
+/// use wgpu_types::{BufferLayout, BufferLayoutRules, MemberLayout};
+///
+/// #[repr(C)]
+/// struct Light {
+///     position: [f32; 3],
+///     intensity: f32,
+///     color: [f32; 4],
+/// }
+///
+/// const RULES: BufferLayoutRules = BufferLayoutRules::Std140;
+/// const LIGHT: BufferLayout = BufferLayout::new(RULES)
+///     .member(MemberLayout::vector(3))
+///     .member(MemberLayout::scalar())
+///     .member(MemberLayout::vector(4));
+///
+/// assert_eq!(LIGHT.offset_of(MemberLayout::vector(4)), 32);
+/// wgpu_types::assert_buffer_layout_size!(Light, LIGHT);
+/// ```
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub struct BufferLayout {
+    /// Layout rules in use.
+    pub rules: BufferLayoutRules,
+    /// End of the last member, in bytes.
+    pub end: u64,
+    /// Alignment of the structure, in bytes.
+    pub align: u64,
+}
+
+impl BufferLayout {
+    /// Starts an empty structure.
+    pub const fn new(rules: BufferLayoutRules) -> Self {
+        Self {
+            rules,
+            end: 0,
+            align: rules.round_up_aggregate(1),
+        }
+    }
+
+    /// Offset at which a member would be placed if it was appended next.
+    pub const fn offset_of(&self, member: MemberLayout) -> u64 {
+        align_up_u64(self.end, member.align)
+    }
+
+    /// Appends a member to the structure.
+    pub const fn member(self, member: MemberLayout) -> Self {
+        let align = self.rules.round_up_aggregate(member.align);
+        Self {
+            rules: self.rules,
+            end: self.offset_of(member) + member.size,
+            align: if align > self.align { align } else { self.align },
+        }
+    }
+
+    /// Size of the structure, including the trailing padding.
+    pub const fn size(&self) -> u64 {
+        align_up_u64(self.end, self.align)
+    }
+}
+
+/// Fails to compile if the size of a Rust type doesn't match the size of a [`BufferLayout`].
+///
+/// This catches most of the mismatches between a Rust structure and its shader counterpart,
+/// such as a `vec3` that is not followed by a scalar or explicit padding.
+#[macro_export]
+macro_rules! assert_buffer_layout_size {
+    ($ty:ty, $layout:expr) => {
+        const _: [(); ($layout).size() as usize] = [(); ::std::mem::size_of::<$ty>()];
+    };
+}
+
+#[test]
+fn test_buffer_layout() {
+    use BufferLayoutRules::{Std140, Std430};
+
+    let floats = MemberLayout::array(Std140, MemberLayout::scalar(), 3);
+    assert_eq!(floats, MemberLayout { size: 48, align: 16 });
+    let floats = MemberLayout::array(Std430, MemberLayout::scalar(), 3);
+    assert_eq!(floats, MemberLayout { size: 12, align: 4 });
+
+    let mat3 = MemberLayout::matrix(Std430, 3, 3);
+    assert_eq!(mat3, MemberLayout { size: 48, align: 16 });
+
+    let inner = BufferLayout::new(Std140).member(MemberLayout::scalar());
+    assert_eq!(inner.size(), 16);
+    let outer = BufferLayout::new(Std140)
+        .member(MemberLayout::scalar())
+        .member(MemberLayout::structure(inner))
+        .member(MemberLayout::vector(2));
+    assert_eq!(outer.offset_of(MemberLayout::scalar()), 40);
+    assert_eq!(outer.size(), 48);
+
+    let packed = BufferLayout::new(Std430)
+        .member(MemberLayout::vector(3))
+        .member(MemberLayout::scalar());
+    assert_eq!(packed.size(), 16);
+}
+
+/// Hasher producing the same values on every platform and in every process.
+///
+/// The hashers of the standard library are seeded per process, and [`Hash`] feeds
+/// them native-endian integers of native width. This one is FNV-1a over little-endian
+/// bytes, with `usize` and `isize` widened to 64 bits, so descriptors can be hashed
+/// offline and looked up by the runtime, for example to pre-bake pipeline caches.
+///
+/// The [`Hash`] implementations of descriptors only see their contents, so a borrowed
+/// and an owned `Cow` hash the same. Enums feed their explicit `u32` discriminants and
+/// optional fields go through [`hash_option`], so nothing depends on how the compiler
+/// derives [`Hash`]. The values are only stable for a given version of this crate,
+/// since adding a field to a descriptor changes its hash.
+///
+/// ```rust
+/// use wgpu_types::{stable_hash, BindGroupLayoutEntry, BindingType, ShaderStage};
+///
+/// let entries = [BindGroupLayoutEntry {
+///     binding: 0,
+///     visibility: ShaderStage::VERTEX,
+///     ty: BindingType::Sampler { filtering: true, comparison: false },
+///     count: None,
+/// }];
+/// let key = stable_hash(&entries[..]);
+/// assert_eq!(key, stable_hash(&entries.to_vec()));
+/// ```
+///
+/// [`Hash`]: std::hash::Hash
+#[derive(Clone, Copy, Debug)]
+pub struct StableHasher(u64);
+
+impl StableHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+}
+
+impl std::hash::Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16(i as u16);
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(i as u32);
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128(i as u128);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as i64 as u64);
+    }
+}
+
+/// Implements [`Hash`](std::hash::Hash) of fieldless enums with their explicit `u32`
+/// discriminant, instead of the derived hash which depends on the discriminant type.
+macro_rules! impl_hash_as_u32 {
+    ($($ty:ident),* $(,)?) => {
+        $(
+            impl std::hash::Hash for $ty {
+                fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                    state.write_u32(*self as u32);
+                }
+            }
+        )*
+    };
+}
+
+impl_hash_as_u32!(
+    BlendFactor,
+    BlendOperation,
+    CompareFunction,
+    CullMode,
+    FrontFace,
+    IndexFormat,
+    InputStepMode,
+    PolygonMode,
+    PrimitiveTopology,
+    StencilOperation,
+    StorageTextureAccess,
+    TextureFormat,
+    TextureViewDimension,
+    VertexFormat,
+);
+
+/// Hashes an optional value with an explicit `u32` discriminant,
+/// for the [`Hash`](std::hash::Hash) implementations of descriptors.
+pub fn hash_option<T: std::hash::Hash, H: std::hash::Hasher>(value: &Option<T>, state: &mut H) {
+    match *value {
+        Some(ref value) => {
+            state.write_u32(1);
+            value.hash(state);
+        }
+        None => state.write_u32(0),
+    }
+}
+
+/// Hashes a value with [`StableHasher`].
+pub fn stable_hash<T: std::hash::Hash + ?Sized>(value: &T) -> u64 {
+    use std::hash::Hasher as _;
+    let mut hasher = StableHasher::default();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn test_check_limits() {
+    let adapter = Limits {
+        max_buffer_size: 1 << 30,
+        ..Limits::default()
+    };
+    let bigger_buffers = Limits {
+        max_bind_groups: 2,
+        max_buffer_size: 1 << 31,
+        ..Limits::default()
+    };
+    // Sorts before the adapter limits, but still exceeds one of them.
+    assert!(bigger_buffers < adapter);
+    assert!(!bigger_buffers.check_limits(&adapter));
+    assert!(Limits::default().check_limits(&adapter));
+}
+
+#[test]
+fn test_stable_hash() {
+    use std::{borrow::Cow, hash::Hasher as _};
+
+    // Reference values of FNV-1a.
+    assert_eq!(stable_hash(&()), 0xcbf2_9ce4_8422_2325);
+    assert_eq!(stable_hash(&0x61_u8), 0xaf63_dc4c_8601_ec8c);
+
+    let owned: Cow<str> = Cow::Owned("shadow".to_string());
+    let borrowed: Cow<str> = Cow::Borrowed("shadow");
+    assert_eq!(stable_hash(&owned), stable_hash(&borrowed));
+
+    let rasterization = RasterizationStateDescriptor {
+        depth_bias_slope_scale: -0.0,
+        ..Default::default()
+    };
+    assert_eq!(
+        stable_hash(&rasterization),
+        stable_hash(&RasterizationStateDescriptor::default())
+    );
+
+    assert_eq!(
+        stable_hash(&TextureFormat::Rgba8Unorm),
+        stable_hash(&(TextureFormat::Rgba8Unorm as u32))
+    );
+    let entry = BindGroupLayoutEntry {
+        binding: 0,
+        visibility: ShaderStage::FRAGMENT,
+        ty: BindingType::Texture {
+            sample_type: TextureSampleType::Depth,
+            view_dimension: TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    };
+    // binding, visibility, variant, sample type, view dimension, multisampled, count
+    let mut expected = StableHasher::default();
+    for &word in &[0, ShaderStage::FRAGMENT.bits(), 2, 1] {
+        expected.write_u32(word);
+    }
+    expected.write_u32(TextureViewDimension::D2 as u32);
+    expected.write_u8(0);
+    expected.write_u32(0);
+    assert_eq!(stable_hash(&entry), expected.finish());
+}
+
+#[test]
+fn test_texture_format_all() {
+    // The formats have consecutive discriminants, the last one being declared last.
+    assert_eq!(
+        TextureFormat::ALL.len(),
+        TextureFormat::Astc12x12RgbaUnormSrgb as usize + 1
+    );
+    for (index, format) in TextureFormat::ALL.iter().enumerate() {
+        assert!(
+            !TextureFormat::ALL[..index].contains(format),
+            "{:?} is listed twice",
+            format
+        );
+    }
+    for format in &[
+        TextureFormat::R8Unorm,
+        TextureFormat::Rgba8UnormSrgb,
+        TextureFormat::Depth24PlusStencil8,
+        TextureFormat::Bc7RgbaUnormSrgb,
+        TextureFormat::Etc2RgbA8Unorm,
+        TextureFormat::Astc12x12RgbaUnormSrgb,
+    ] {
+        assert!(
+            TextureFormat::ALL.contains(format),
+            "{:?} is missing",
+            format
+        );
+    }
+}